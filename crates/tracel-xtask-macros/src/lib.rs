@@ -9,12 +9,58 @@ use syn::{
     parse_macro_input, punctuated::Punctuated, token::Comma, ItemEnum, ItemStruct, Meta, Variant,
 };
 
+/// Returns the keys of the map in alphabetical order for reproducible error messages
+fn sorted_keys<'a, V>(map: &HashMap<&'a str, V>) -> Vec<&'a str> {
+    let mut keys: Vec<&str> = map.keys().cloned().collect();
+    keys.sort();
+    keys
+}
+
 // Targets
 // =======
 
-fn generate_target_enum(input: TokenStream) -> TokenStream {
-    let item = parse_macro_input!(input as ItemEnum);
-    let enum_name = &item.ident;
+/// Returns the members of the custom target variant declared with `#[target(members = "a,b")]`
+/// and removes the attribute from the variant.
+fn take_target_members(variant: &mut Variant) -> syn::Result<Vec<String>> {
+    let mut members = vec![];
+    let mut result = Ok(());
+    variant.attrs.retain(|attr| {
+        if !attr.path().is_ident("target") {
+            return true;
+        }
+        result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("members") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                members.extend(
+                    value
+                        .value()
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|m| !m.is_empty())
+                        .map(String::from),
+                );
+                Ok(())
+            } else {
+                Err(meta.error("unsupported target attribute, expected `members = \"...\"`"))
+            }
+        });
+        false
+    });
+    result.map(|_| members)
+}
+
+fn generate_target_enum(mut item: ItemEnum) -> syn::Result<proc_macro2::TokenStream> {
+    let enum_name = item.ident.clone();
+    let mut member_arms = vec![];
+    for variant in item.variants.iter_mut() {
+        let members = take_target_members(variant)?;
+        if !members.is_empty() {
+            let variant_ident = &variant.ident;
+            member_arms.push(quote! {
+                #enum_name::#variant_ident => &[#(#members),*],
+            });
+        }
+    }
     let original_variants = &item.variants;
 
     let output = quote! {
@@ -27,18 +73,28 @@ fn generate_target_enum(input: TokenStream) -> TokenStream {
             Crates,
             #[doc = r"Targets all example crates."]
             Examples,
+            #[doc = r"Targets the binary and library crates which are published, i.e. without publish = false."]
+            PublishableCrates,
             #[default]
             #[doc = r"Targets the whole workspace using cargo --workspace."]
             Workspace,
             #original_variants
         }
+
+        impl #enum_name {
+            /// Returns the workspace members selected by a custom target, or an empty slice.
+            pub fn members(&self) -> &'static [&'static str] {
+                match self {
+                    #(#member_arms)*
+                    _ => &[],
+                }
+            }
+        }
     };
-    TokenStream::from(output)
+    Ok(output)
 }
 
-fn generate_target_tryinto(_args: TokenStream, input: TokenStream) -> TokenStream {
-    let item = parse_macro_input!(input as ItemEnum);
-    let item_ident = &item.ident;
+fn generate_target_tryinto(item_ident: &syn::Ident) -> proc_macro2::TokenStream {
     let tryinto = quote! {
         impl std::convert::TryInto<tracel_xtask::commands::Target> for #item_ident {
             type Error = anyhow::Error;
@@ -47,30 +103,58 @@ fn generate_target_tryinto(_args: TokenStream, input: TokenStream) -> TokenStrea
                     #item_ident::AllPackages => Ok(tracel_xtask::commands::Target::AllPackages),
                     #item_ident::Crates => Ok(tracel_xtask::commands::Target::Crates),
                     #item_ident::Examples => Ok(tracel_xtask::commands::Target::Examples),
+                    #item_ident::PublishableCrates => Ok(tracel_xtask::commands::Target::PublishableCrates),
                     #item_ident::Workspace => Ok(tracel_xtask::commands::Target::Workspace),
-                    _ => Err(anyhow::anyhow!("{} target is not supported.", self))
+                    // custom targets with members run on their members package by package
+                    _ if !self.members().is_empty() => Ok(tracel_xtask::commands::Target::AllPackages),
+                    _ => Err(anyhow::anyhow!(
+                        "{} target is not supported by the base commands, declare its members with #[target(members = \"...\")].",
+                        self
+                    ))
                 }
             }
         }
     };
-    TokenStream::from(tryinto)
+    tryinto
 }
 
 #[proc_macro_attribute]
 pub fn declare_targets(_args: TokenStream, input: TokenStream) -> TokenStream {
-    generate_target_enum(input)
+    let item = parse_macro_input!(input as ItemEnum);
+    match generate_target_enum(item) {
+        Ok(output) => TokenStream::from(output),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
 }
 
 #[proc_macro_attribute]
-pub fn extend_targets(args: TokenStream, input: TokenStream) -> TokenStream {
-    let mut output = generate_target_enum(input);
-    output.extend(generate_target_tryinto(args, output.clone()));
-    output
+pub fn extend_targets(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemEnum);
+    let tryinto = generate_target_tryinto(&item.ident);
+    match generate_target_enum(item) {
+        Ok(output) => TokenStream::from(quote! {
+            #output
+            #tryinto
+        }),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
 }
 
 // Commands
 // ========
 
+/// Convert a command name to the name of its module, for instance ListTargets to list_targets
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
 fn generate_dispatch_function(
     enum_ident: &syn::Ident,
     args: &Punctuated<Meta, Comma>,
@@ -78,22 +162,32 @@ fn generate_dispatch_function(
     let arms: Vec<proc_macro2::TokenStream> = args.iter().map(|meta| {
         let cmd_ident = meta.path().get_ident().unwrap();
         let cmd_ident_string = cmd_ident.to_string();
-        let module_ident = syn::Ident::new(cmd_ident_string.to_lowercase().as_str(), cmd_ident.span());
-        match cmd_ident_string.as_str() {
+        let module_ident = syn::Ident::new(&to_snake_case(&cmd_ident_string), cmd_ident.span());
+        // name of the command on the command line, the hooks are registered with it
+        let cmd_name = to_snake_case(&cmd_ident_string).replace('_', "-");
+        let handler = match cmd_ident_string.as_str() {
             "Fix" => quote! {
-                #enum_ident::#cmd_ident(args) => base_commands::#module_ident::handle_command(args, None),
+                base_commands::#module_ident::handle_command(args, None)
+            },
+            "Completions" | "Man" => quote! {
+                base_commands::#module_ident::handle_command(args, tracel_xtask::xtask_command::<#enum_ident, A>())
             },
             _ => quote! {
-                #enum_ident::#cmd_ident(args) => base_commands::#module_ident::handle_command(args),
+                base_commands::#module_ident::handle_command(args)
             }
+        };
+        quote! {
+            #enum_ident::#cmd_ident(args) => tracel_xtask::utils::hooks::with_hooks(#cmd_name, || #handler),
         }
     }).collect();
     let func = quote! {
-        fn dispatch_base_commands(args: XtaskArgs<Command>) -> anyhow::Result<()> {
-            match args.command {
+        fn dispatch_base_commands<A: clap::Args>(args: XtaskArgs<Command, A>) -> anyhow::Result<()> {
+            let result = match args.command {
                 #(#arms)*
                 _ => Err(anyhow::anyhow!("Unknown command")),
-            }
+            };
+            tracel_xtask::utils::sccache::print_sccache_stats();
+            result
         }
     };
     TokenStream::from(func)
@@ -107,6 +201,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Supported commands and their quoted expansions
     let mut variant_map: HashMap<&str, proc_macro2::TokenStream> = HashMap::new();
+    variant_map.insert(
+        "Bench",
+        quote! {
+            #[doc = r"Run the benchmarks, optionally saving or comparing to Criterion baselines."]
+            Bench(tracel_xtask::commands::bench::BenchCmdArgs)
+        },
+    );
     variant_map.insert(
         "Build",
         quote! {
@@ -142,6 +243,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Compile(tracel_xtask::commands::compile::CompileCmdArgs)
         },
     );
+    variant_map.insert(
+        "Completions",
+        quote! {
+            #[doc = r"Generate the shell completion script of this xtask binary."]
+            Completions(tracel_xtask::commands::completions::CompletionsCmdArgs)
+        },
+    );
     variant_map.insert(
         "Coverage",
         quote! {
@@ -156,6 +264,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Doc(tracel_xtask::commands::doc::DocCmdArgs)
         },
     );
+    variant_map.insert(
+        "Expand",
+        quote! {
+            #[doc = r"Print the source code of a package with its macros expanded."]
+            Expand(tracel_xtask::commands::expand::ExpandCmdArgs)
+        },
+    );
     variant_map.insert(
         "Dependencies",
         quote! {
@@ -163,6 +278,41 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Dependencies(tracel_xtask::commands::dependencies::DependenciesCmdArgs)
         },
     );
+    variant_map.insert(
+        "Generate",
+        quote! {
+            #[doc = r"Run the registered code generators and report the changed files."]
+            Generate(tracel_xtask::commands::generate::GenerateCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Graph",
+        quote! {
+            #[doc = r"Generate the Graphviz DOT of the dependency graph of the workspace members."]
+            Graph(tracel_xtask::commands::graph::GraphCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "ListTargets",
+        quote! {
+            #[doc = r"List the workspace members matched by each target, for instance to know what --only and --exclude will match."]
+            ListTargets(tracel_xtask::commands::list_targets::ListTargetsCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Man",
+        quote! {
+            #[doc = r"Generate the man pages of this xtask binary and of each of its commands."]
+            Man(tracel_xtask::commands::man::ManCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Profile",
+        quote! {
+            #[doc = r"Profile a binary, an example or a benchmark and generate a flamegraph."]
+            Profile(tracel_xtask::commands::profile::ProfileCmdArgs)
+        },
+    );
     variant_map.insert(
         "Publish",
         quote! {
@@ -170,6 +320,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Publish(tracel_xtask::commands::publish::PublishCmdArgs)
         },
     );
+    variant_map.insert(
+        "Size",
+        quote! {
+            #[doc = r"Analyze the size of a binary or an example with cargo-bloat."]
+            Size(tracel_xtask::commands::size::SizeCmdArgs)
+        },
+    );
     variant_map.insert(
         "Test",
         quote! {
@@ -201,11 +358,7 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
                     let err_msg = format!(
                         "Unknown command: {}\nPossible commands are:\n  {}",
                         ident_string,
-                        variant_map
-                            .keys()
-                            .cloned()
-                            .collect::<Vec<&str>>()
-                            .join("\n  "),
+                        sorted_keys(&variant_map).join("\n  "),
                     );
                     return TokenStream::from(quote! {
                         compile_error!(#err_msg);
@@ -232,14 +385,367 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
 // Command arguments
 // =================
 
+/// Additional fields of each base command arguments struct, every base command has an entry
 fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStream> {
     HashMap::from([
+        (
+            "BenchCmdArgs",
+            quote! {
+                #[doc = r"Run only the passed benchmark target."]
+                #[arg(long, value_name = "NAME", required = false)]
+                pub bench: Option<String>,
+                #[doc = r"Save the results of the Criterion benchmarks as the passed baseline."]
+                #[arg(long = "save-baseline", value_name = "NAME", required = false, conflicts_with = "baseline")]
+                pub save_baseline: Option<String>,
+                #[doc = r"Compare the results of the Criterion benchmarks to the passed saved baseline."]
+                #[arg(long = "baseline", value_name = "NAME", required = false)]
+                pub baseline: Option<String>,
+                #[doc = r"Fail if the mean time of a benchmark regressed by more than this percentage against the baseline."]
+                #[arg(long = "regression-threshold", value_name = "PCT", required = false, requires = "baseline")]
+                pub regression_threshold: Option<f64>,
+            },
+        ),
+        (
+            "BuildCmdArgs",
+            quote! {
+                #[doc = r"Continue after the first failure to report all the errors at once."]
+                #[arg(long = "keep-going", visible_alias = "no-fail-fast", required = false)]
+                pub keep_going: bool,
+                #[doc = r"Print a summary of the compiler errors and warnings per crate."]
+                #[arg(long = "summary", required = false)]
+                pub summary: bool,
+                #[doc = r"Stream the full compiler output along with the summary."]
+                #[arg(long = "verbose", required = false, requires = "summary")]
+                pub verbose: bool,
+                #[doc = r"Copy the built binaries and libraries to this directory."]
+                #[arg(long = "out-dir", value_name = "PATH", required = false)]
+                pub out_dir: Option<std::path::PathBuf>,
+                #[doc = r"Copy only the binaries to the output directory."]
+                #[arg(long = "bins-only", required = false, requires = "out_dir")]
+                pub bins_only: bool,
+                #[doc = r"Build artifacts in release mode, with optimizations."]
+                #[arg(long = "release", required = false, conflicts_with = "profile")]
+                pub release: bool,
+                #[doc = r"Build artifacts with the specified profile."]
+                #[arg(long = "profile", value_name = "PROFILE-NAME", required = false)]
+                pub profile: Option<String>,
+                #[doc = r"Record the compilation timings of each crate with cargo and report the path of the HTML report."]
+                #[arg(long = "timings", required = false)]
+                pub timings: bool,
+                #[doc = r"Open the HTML timings report once the command is done."]
+                #[arg(long = "open", required = false, requires = "timings")]
+                pub open: bool,
+                #[doc = r"Write the unit graph of cargo as JSON to target/unit-graph.json instead of building, requires the nightly toolchain."]
+                #[arg(long = "profile-cargo", required = false, conflicts_with_all = ["summary", "out_dir", "timings"])]
+                pub profile_cargo: bool,
+            },
+        ),
         (
             "CheckCmdArgs",
             quote! {
                 #[doc = r"Ignore audit errors."]
                 #[arg(long = "ignore-audit", required = false)]
                 pub ignore_audit: bool,
+                #[doc = r"Fail the audit on any advisory, including the unmaintained, unsound and yanked crates warnings."]
+                #[arg(long = "deny-warnings", required = false)]
+                pub deny_warnings: bool,
+                #[doc = r"Advisory to ignore during the audit, for instance RUSTSEC-2020-0071. Can be repeated."]
+                #[arg(long = "ignore-advisory", value_name = "ID", required = false)]
+                pub ignored_advisories: Vec<String>,
+                #[doc = r"Run the lint check over the feature powerset using cargo-hack, the other checks do not support it."]
+                #[arg(long = "hack", required = false)]
+                pub hack: bool,
+                #[doc = r"Format with the nightly toolchain and the unstable rustfmt options of xtask.toml."]
+                #[arg(long = "nightly-fmt", required = false)]
+                pub nightly_fmt: bool,
+                #[doc = r"Continue after the first failure to report all the errors at once."]
+                #[arg(long = "keep-going", visible_alias = "no-fail-fast", required = false)]
+                pub keep_going: bool,
+                #[doc = r"Print a summary of the compiler errors and warnings per crate."]
+                #[arg(long = "summary", required = false, conflicts_with_all = ["hack", "sarif"])]
+                pub summary: bool,
+                #[doc = r"Stream the full compiler output along with the summary."]
+                #[arg(long = "verbose", required = false, requires = "summary")]
+                pub verbose: bool,
+                #[doc = r"Write the lint and audit findings to the given SARIF file."]
+                #[arg(long = "sarif", value_name = "FILE", required = false, conflicts_with = "hack")]
+                pub sarif: Option<std::path::PathBuf>,
+                #[doc = r"Typos config file, defaults to the typos.toml or _typos.toml file at the root of the workspace."]
+                #[arg(long = "typos-config", value_name = "FILE", required = false)]
+                pub typos_config: Option<std::path::PathBuf>,
+                #[doc = r"Glob of the paths to ignore when checking typos, for instance generated files. Can be repeated."]
+                #[arg(long = "exclude-path", value_name = "GLOB", required = false)]
+                pub exclude_paths: Vec<String>,
+                #[doc = r"Fail the unused-features check when unused features are found instead of only reporting them."]
+                #[arg(long = "deny-unused-features", required = false)]
+                pub deny_unused_features: bool,
+                #[doc = r"Report only the lint and format issues of the lines changed since the given git reference, HEAD by default."]
+                #[arg(long = "diff", value_name = "REF", num_args = 0..=1, require_equals = true, default_missing_value = "HEAD", required = false, conflicts_with_all = ["hack", "sarif", "summary"])]
+                pub diff: Option<String>,
+                #[doc = r"Report the issues of all the lines, overriding --diff."]
+                #[arg(long = "all-lines", required = false)]
+                pub all_lines: bool,
+            },
+        ),
+        (
+            "CompileCmdArgs",
+            quote! {
+                #[doc = r"Run the command over the feature powerset using cargo-hack."]
+                #[arg(long = "hack", required = false)]
+                pub hack: bool,
+                #[doc = r"Continue after the first failure to report all the errors at once."]
+                #[arg(long = "keep-going", visible_alias = "no-fail-fast", required = false)]
+                pub keep_going: bool,
+                #[doc = r"Print a summary of the compiler errors and warnings per crate."]
+                #[arg(long = "summary", required = false)]
+                pub summary: bool,
+                #[doc = r"Stream the full compiler output along with the summary."]
+                #[arg(long = "verbose", required = false, requires = "summary")]
+                pub verbose: bool,
+                #[doc = r"Build artifacts in release mode, with optimizations."]
+                #[arg(long = "release", required = false, conflicts_with = "profile")]
+                pub release: bool,
+                #[doc = r"Build artifacts with the specified profile."]
+                #[arg(long = "profile", value_name = "PROFILE-NAME", required = false)]
+                pub profile: Option<String>,
+                #[doc = r"Record the compilation timings of each crate with cargo and report the path of the HTML report."]
+                #[arg(long = "timings", required = false, conflicts_with = "hack")]
+                pub timings: bool,
+                #[doc = r"Open the HTML timings report once the command is done."]
+                #[arg(long = "open", required = false, requires = "timings")]
+                pub open: bool,
+            },
+        ),
+        (
+            "BumpCmdArgs",
+            quote! {
+                #[doc = r"Comma-separated list of crates to bump, all the crates are bumped by default."]
+                #[arg(
+                    short = 'p',
+                    long = "package",
+                    value_name = "CRATE,CRATE,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub packages: Vec<String>,
+                #[doc = r"Commit the bumped manifests and create an annotated git tag of the new version."]
+                #[arg(long = "tag", required = false)]
+                pub tag: bool,
+                #[doc = r"Prefix of the name of the git tag, the tag of the version 1.2.3 is v1.2.3 by default."]
+                #[arg(long = "tag-prefix", value_name = "PREFIX", default_value = "v")]
+                pub tag_prefix: String,
+                #[doc = r"Push the created git tag to the origin remote."]
+                #[arg(long = "push", required = false, requires = "tag")]
+                pub push: bool,
+                #[doc = r"Print the new version of each bumped crate and the rewritten dependency requirements without modifying the manifests."]
+                #[arg(long = "dry-run", required = false)]
+                pub dry_run: bool,
+            },
+        ),
+        (
+            "CompletionsCmdArgs",
+            quote! {
+                #[doc = r"Shell to generate the completion script for."]
+                #[arg(value_enum)]
+                pub shell: clap_complete::Shell,
+                #[doc = r"Write the completion script to this file instead of stdout."]
+                #[arg(short, long, value_name = "PATH", required = false)]
+                pub output: Option<std::path::PathBuf>,
+                #[doc = r"Name of the binary the completions are registered for."]
+                #[arg(long, value_name = "NAME", default_value = "xtask")]
+                pub bin_name: String,
+            },
+        ),
+        ("CoverageCmdArgs", quote! {}),
+        (
+            "DependenciesCmdArgs",
+            quote! {
+                #[doc = r"Path of the cargo-deny configuration file. [default: deny.toml]"]
+                #[arg(long, value_name = "PATH", required = false)]
+                pub config: Option<std::path::PathBuf>,
+                #[doc = r"Tool finding the unused dependencies: cargo-machete is fast and runs on stable, cargo-udeps is more accurate as it builds the crates but requires nightly."]
+                #[arg(long, value_name = "BACKEND", value_parser = ["machete", "udeps"], default_value = "machete")]
+                pub backend: String,
+                #[doc = r"Comma-separated list of crates excluded from the unused dependencies checks."]
+                #[arg(
+                    long,
+                    value_name = "CRATE,CRATE,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub exclude: Vec<String>,
+            },
+        ),
+        (
+            "DocCmdArgs",
+            quote! {
+                #[doc = r"Comma-separated list of URLs for which dead links are ignored by check-links."]
+                #[arg(
+                    long = "allow-link",
+                    value_name = "URL,URL,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub allowed_links: Vec<String>,
+                #[doc = r"Minimum percentage of documented items over all the crates, coverage fails below it."]
+                #[arg(long, value_name = "PCT", required = false)]
+                pub min: Option<f64>,
+                #[doc = r"Document the private items as well with --document-private-items."]
+                #[arg(long, required = false)]
+                pub private: bool,
+            },
+        ),
+        (
+            "GenerateCmdArgs",
+            quote! {
+                #[doc = r"Fail if the generated code differs from the committed code, for instance on CI."]
+                #[arg(long = "check", required = false)]
+                pub check: bool,
+                #[doc = r"Comma-separated list of the generators to run, all the registered generators run by default."]
+                #[arg(
+                    long = "generator",
+                    value_name = "NAME,NAME,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub generators: Vec<String>,
+            },
+        ),
+        (
+            "ExpandCmdArgs",
+            quote! {
+                #[doc = r"Package to expand, deduced from --bin or --test when omitted."]
+                #[arg(short, long, value_name = "CRATE", required = false)]
+                pub package: Option<String>,
+                #[doc = r"Expand the binary with this name instead of the library."]
+                #[arg(long, value_name = "NAME", conflicts_with = "test")]
+                pub bin: Option<String>,
+                #[doc = r"Expand the integration test with this name instead of the library."]
+                #[arg(long, value_name = "NAME")]
+                pub test: Option<String>,
+                #[doc = r"Only print the expansion of this item, for instance 'commands::Target'."]
+                #[arg(long, value_name = "PATH", required = false)]
+                pub item: Option<String>,
+            },
+        ),
+        (
+            "FixCmdArgs",
+            quote! {
+                #[doc = r"Format with the nightly toolchain and the unstable rustfmt options of xtask.toml."]
+                #[arg(long = "nightly-fmt", required = false)]
+                pub nightly_fmt: bool,
+                #[doc = r"Typos config file, defaults to the typos.toml or _typos.toml file at the root of the workspace."]
+                #[arg(long = "typos-config", value_name = "FILE", required = false)]
+                pub typos_config: Option<std::path::PathBuf>,
+                #[doc = r"Glob of the paths to ignore when checking typos, for instance generated files. Can be repeated."]
+                #[arg(long = "exclude-path", value_name = "GLOB", required = false)]
+                pub exclude_paths: Vec<String>,
+                #[doc = r"Fix only the files staged in git and stage the fixes again, for instance in a git pre-commit hook."]
+                #[arg(long, required = false, conflicts_with_all = ["changed_only", "changed_since"])]
+                pub staged: bool,
+            },
+        ),
+        (
+            "GraphCmdArgs",
+            quote! {
+                #[doc = r"Include the external dependencies, for instance the crates.io dependencies."]
+                #[arg(long, required = false)]
+                pub include_external: bool,
+                #[doc = r"Write the graph to this file instead of stdout, a file with the svg extension is rendered with the dot command of Graphviz."]
+                #[arg(short, long, value_name = "PATH", required = false)]
+                pub output: Option<std::path::PathBuf>,
+            },
+        ),
+        (
+            "ListTargetsCmdArgs",
+            quote! {
+                #[doc = r"Output format."]
+                #[arg(long, value_name = "FORMAT", value_parser = ["json", "plain"], default_value = "plain")]
+                pub format: String,
+            },
+        ),
+        (
+            "ManCmdArgs",
+            quote! {
+                #[doc = r"Directory where the man pages are written. [default: target/man at the root of the workspace]"]
+                #[arg(short, long, value_name = "DIR", required = false)]
+                pub output_dir: Option<std::path::PathBuf>,
+                #[doc = r"Name of the binary the man pages are generated for."]
+                #[arg(long, value_name = "NAME", default_value = "xtask")]
+                pub bin_name: String,
+            },
+        ),
+        (
+            "ProfileCmdArgs",
+            quote! {
+                #[doc = r"Binary to profile."]
+                #[arg(
+                    long,
+                    value_name = "NAME",
+                    conflicts_with_all = ["example", "bench"],
+                    required_unless_present_any = ["example", "bench"]
+                )]
+                pub bin: Option<String>,
+                #[doc = r"Example to profile."]
+                #[arg(long, value_name = "NAME", conflicts_with = "bench")]
+                pub example: Option<String>,
+                #[doc = r"Benchmark to profile."]
+                #[arg(long, value_name = "NAME")]
+                pub bench: Option<String>,
+                #[doc = r"Path of the generated flamegraph. [default: flamegraph.svg at the root of the workspace]"]
+                #[arg(short, long, value_name = "PATH", required = false)]
+                pub output: Option<std::path::PathBuf>,
+                #[doc = r"Arguments passed to the profiled binary."]
+                #[arg(last = true)]
+                pub args: Vec<String>,
+            },
+        ),
+        (
+            "PublishCmdArgs",
+            quote! {
+                #[doc = r"Publish even if the git tree has uncommitted changes."]
+                #[arg(long = "allow-dirty", required = false)]
+                pub allow_dirty: bool,
+                #[doc = r"Branch the releases are published from, a warning is printed on other branches."]
+                #[arg(long = "branch", value_name = "BRANCH", default_value = "main")]
+                pub branch: String,
+                #[doc = r"Registry to publish to instead of crates.io, as configured in the cargo config."]
+                #[arg(long = "registry", value_name = "NAME", required = false)]
+                pub registry: Option<String>,
+                #[doc = r"Token of the registry, prefer --token-env as the arguments are visible to the other processes."]
+                #[arg(long = "token", value_name = "TOKEN", required = false, conflicts_with = "token_env")]
+                pub token: Option<String>,
+                #[doc = r"Environment variable containing the token of the registry."]
+                #[arg(long = "token-env", value_name = "VAR", default_value = "CRATES_IO_API_TOKEN")]
+                pub token_env: String,
+            },
+        ),
+        (
+            "SizeCmdArgs",
+            quote! {
+                #[doc = r"Binary to analyze."]
+                #[arg(
+                    long,
+                    value_name = "NAME",
+                    conflicts_with = "example",
+                    required_unless_present = "example"
+                )]
+                pub bin: Option<String>,
+                #[doc = r"Example to analyze."]
+                #[arg(long, value_name = "NAME")]
+                pub example: Option<String>,
+                #[doc = r"Analyze the binary built in release mode."]
+                #[arg(long = "release", required = false)]
+                pub release: bool,
+                #[doc = r"Report the size per crate instead of per function."]
+                #[arg(long = "crates", required = false)]
+                pub crates: bool,
+                #[doc = r"Number of largest items to report."]
+                #[arg(short = 'n', long, value_name = "NUMBER", default_value_t = 10)]
+                pub count: u32,
+                #[doc = r"Fail if the binary is larger than this size in bytes, a K, M or G suffix is accepted."]
+                #[arg(long, value_name = "SIZE", required = false)]
+                pub threshold: Option<String>,
             },
         ),
         (
@@ -273,6 +779,42 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                     required = false
                 )]
                 pub no_default_features: bool,
+                #[doc = r"Run the command over the feature powerset using cargo-hack."]
+                #[arg(long = "hack", required = false)]
+                pub hack: bool,
+                #[doc = r"Number of times the failing tests are run again before declaring failure."]
+                #[arg(long = "retries", value_name = "N", default_value_t = 0)]
+                pub retries: u32,
+                #[doc = r"Continue testing after a failing crate and report all the failing crates at once."]
+                #[arg(long = "no-fail-fast", visible_alias = "keep-going", required = false)]
+                pub no_fail_fast: bool,
+                #[doc = r"Build artifacts in release mode, with optimizations."]
+                #[arg(long = "release", required = false, conflicts_with = "profile")]
+                pub release: bool,
+                #[doc = r"Build artifacts with the specified profile."]
+                #[arg(long = "profile", value_name = "PROFILE-NAME", required = false)]
+                pub profile: Option<String>,
+                #[doc = r"Headless browser running the tests of the wasm execution environment."]
+                #[arg(long, value_name = "BROWSER", value_parser = ["chrome", "firefox", "safari"], default_value = "chrome")]
+                pub browser: String,
+                #[doc = r"Instrument the tests for code coverage and generate a coverage report with grcov."]
+                #[arg(long = "coverage", required = false)]
+                pub coverage: bool,
+                #[doc = r"Format of the coverage report generated with --coverage."]
+                #[arg(long = "coverage-format", value_name = "FORMAT", value_parser = ["lcov", "html"], default_value = "lcov")]
+                pub coverage_format: String,
+                #[doc = r"Remove the .profraw files of the previous coverage runs from target/coverage/profraw before running the tests."]
+                #[arg(long = "clean-first", required = false)]
+                pub clean_first: bool,
+                #[doc = r"Run only the workspace members of one shard, for instance 1/4 for the first of four shards."]
+                #[arg(long = "shard", value_name = "INDEX/TOTAL", required = false)]
+                pub shard: Option<String>,
+                #[doc = r"Run the documentation tests with the all subcommand, it is the default."]
+                #[arg(long = "doc", required = false, overrides_with = "no_doc")]
+                pub doc: bool,
+                #[doc = r"Skip the documentation tests with the all subcommand, they are often the slowest tests."]
+                #[arg(long = "no-doc", required = false, overrides_with = "doc")]
+                pub no_doc: bool,
             },
         ),
         (
@@ -283,6 +825,7 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                 pub ignore_audit: bool,
             },
         ),
+        ("VulnerabilitiesCmdArgs", quote! {}),
     ])
 }
 
@@ -374,6 +917,18 @@ fn generate_command_args_struct(
                     required = false
                 )]
                 pub only: Vec<String>,
+                #[doc = r"File listing crates to exclude along with --exclude, one name or glob per line, lines starting with # are comments."]
+                #[arg(long = "exclude-from", value_name = "PATH", required = false)]
+                pub exclude_from: Option<std::path::PathBuf>,
+                #[doc = r"File listing crates to include exclusively along with --only, one name or glob per line, lines starting with # are comments."]
+                #[arg(long = "only-from", value_name = "PATH", required = false)]
+                pub only_from: Option<std::path::PathBuf>,
+                #[doc = r"Run only on the crates with changes since HEAD and the crates depending on them."]
+                #[arg(long = "changed-only", required = false)]
+                pub changed_only: bool,
+                #[doc = r"Run only on the crates with changes since the given git reference and the crates depending on them."]
+                #[arg(long = "changed-since", value_name = "REF", required = false)]
+                pub changed_since: Option<String>,
             }
         } else {
             quote! {}
@@ -437,6 +992,22 @@ fn generate_command_args_struct(
     }
 }
 
+/// Fields added along with the target field, the target and the crates lists are converted separately
+const TARGET_FIELDS: &[&str] = &["changed_only", "changed_since"];
+
+/// Returns the names of the fields declared in the passed tokens
+fn field_names(fields: &proc_macro2::TokenStream) -> Vec<String> {
+    syn::parse2::<syn::FieldsNamed>(quote! { { #fields } })
+        .map(|fields| {
+            fields
+                .named
+                .iter()
+                .filter_map(|f| f.ident.as_ref().map(|ident| ident.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 fn generate_command_args_tryinto(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args with Punctuated::<Meta, Comma>::parse_terminated);
     let base_type = args.get(0).unwrap();
@@ -463,12 +1034,33 @@ fn generate_command_args_tryinto(args: TokenStream, input: TokenStream) -> Token
         });
 
     // expand
-    let target = if has_target {
-        quote! {
-            target: self.target.try_into()?,
-        }
+    let (target_let, target) = if has_target {
+        (
+            quote! {
+                let mut exclude = self.exclude;
+                let mut only = self.only;
+                tracel_xtask::commands::add_listed_crates(
+                    &mut exclude,
+                    self.exclude_from.as_deref(),
+                    &mut only,
+                    self.only_from.as_deref(),
+                )?;
+                let only = tracel_xtask::commands::target_members_only(
+                    &self.target,
+                    self.target.members(),
+                    only,
+                )?;
+            },
+            quote! {
+                target: self.target.try_into()?,
+                exclude,
+                only,
+                exclude_from: None,
+                only_from: None,
+            },
+        )
     } else {
-        quote! {}
+        (quote! {}, quote! {})
     };
     let (subcommand_let, subcommand_assign) = if has_subcommand {
         (
@@ -482,29 +1074,20 @@ fn generate_command_args_tryinto(args: TokenStream, input: TokenStream) -> Token
     } else {
         (quote! {}, quote! {})
     };
+    // forward the fields shared with the base command, the custom fields only exist on the extended struct
+    let mut base_fields = get_additional_cmd_args_map()
+        .get(base_type_string.as_str())
+        .map(field_names)
+        .unwrap_or_default();
+    if has_target {
+        base_fields.extend(TARGET_FIELDS.iter().map(|f| f.to_string()));
+    }
     let fields: Vec<_> = item
         .fields
         .iter()
-        .filter_map(|f| {
-            f.ident.as_ref().map(|ident| {
-                let ident_str = ident.to_string();
-                // TODO this hardcoded predicates are awful, they should be unneccesarry if
-                // we can use an inventory (see TODO at the top of the file)
-                if ident_str != "target"
-                    && (ident_str == "exclude"
-                        || ident_str == "features"
-                        || ident_str == "no_default_features"
-                        || ident_str == "only"
-                        || ident_str == "ignore_audit"
-                        || ident_str == "jobs"
-                        || ident_str == "threads")
-                {
-                    quote! { #ident: self.#ident, }
-                } else {
-                    quote! {}
-                }
-            })
-        })
+        .filter_map(|f| f.ident.as_ref())
+        .filter(|ident| base_fields.contains(&ident.to_string()))
+        .map(|ident| quote! { #ident: self.#ident, })
         .collect();
 
     let tryinto = quote! {
@@ -512,6 +1095,7 @@ fn generate_command_args_tryinto(args: TokenStream, input: TokenStream) -> Token
             type Error = anyhow::Error;
             fn try_into(self) -> Result<#base_type, Self::Error> {
                 #subcommand_let
+                #target_let
                 Ok(#base_type {
                     #target
                     #subcommand_assign
@@ -535,10 +1119,10 @@ pub fn declare_command_args(args: TokenStream, input: TokenStream) -> TokenStrea
         output.extend(additional_output);
         output
     } else {
-        let error_msg = r#"declare_commands_args macro takes 2 arguments.
+        let error_msg = r#"declare_command_args macro takes 2 arguments.
  First argument is the target type (None if there is no target).
  Second argument is the subcommand type (None if there is no subcommand)."#;
-        TokenStream::from(quote! {compile_error!(#error_msg)})
+        TokenStream::from(quote! {compile_error!(#error_msg);})
     }
 }
 
@@ -554,6 +1138,19 @@ pub fn extend_command_args(args: TokenStream, input: TokenStream) -> TokenStream
  Third argument is the subcommand type (None if there is no subcommand)"#;
         return TokenStream::from(quote! {compile_error!(#error_msg);});
     }
+    let base_type = parsed_args.get(0).unwrap();
+    let additional_cmd_args_map = get_additional_cmd_args_map();
+    let is_base_type = base_type
+        .path()
+        .get_ident()
+        .is_some_and(|ident| additional_cmd_args_map.contains_key(ident.to_string().as_str()));
+    if !is_base_type {
+        let error_msg = format!(
+            "Unknown base command arguments struct, possible structs are:\n  {}",
+            sorted_keys(&additional_cmd_args_map).join("\n  ")
+        );
+        return TokenStream::from(syn::Error::new_spanned(base_type, error_msg).to_compile_error());
+    }
     let mut output: TokenStream = quote! {}.into();
     let (struct_output, additional_output) = generate_command_args_struct(args.clone(), input);
     let tryinto = generate_command_args_tryinto(args, struct_output.clone());
@@ -590,10 +1187,16 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Audit,
                 #[doc = r"Run format command."]
                 Format,
+                #[doc = r"Verify that the committed Cargo.lock is up to date."]
+                Lockfile,
+                #[doc = r"Verify that the manifests of the publishable crates have the fields required by crates.io."]
+                PublishReady,
                 #[doc = r"Run lint command."]
                 Lint,
                 #[doc = r"Report typos in source code."]
                 Typos,
+                #[doc = r"Report the declared features which are never used by the crates."]
+                UnusedFeatures,
             },
         ),
         (
@@ -604,6 +1207,10 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Install,
                 #[doc = r"Generate lcov.info file. [default with default debug profile]"]
                 Generate(GenerateCmdArgs),
+                #[doc = r"Merge the .profraw files of all the coverage runs in target/coverage/profraw into a single report."]
+                Merge(MergeCmdArgs),
+                #[doc = r"Remove the .profraw files of the coverage runs from target/coverage/profraw and the workspace."]
+                Clean,
             },
         ),
         (
@@ -614,7 +1221,7 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 All,
                 #[doc = r"Run cargo-deny Lint dependency graph to ensure all dependencies meet requirements `<https://crates.io/crates/cargo-deny>`. [default]"]
                 Deny,
-                #[doc = r"Run cargo-machete to find unused dependencies `<https://crates.io/crates/cargo-machete>`"]
+                #[doc = r"Find unused dependencies with cargo-machete `<https://crates.io/crates/cargo-machete>` or with cargo-udeps `<https://crates.io/crates/cargo-udeps>` using --backend udeps"]
                 Unused,
             },
         ),
@@ -626,6 +1233,12 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Build,
                 #[doc = r"Run documentation tests."]
                 Tests,
+                #[doc = r"Build documentation and check it for dead links."]
+                CheckLinks,
+                #[doc = r"Report the documentation coverage of the public API of the crates, requires the nightly toolchain."]
+                Coverage,
+                #[doc = r"Fail if a public item of the crates is not documented."]
+                CheckMissing,
             },
         ),
         (
@@ -648,12 +1261,14 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
             "TestSubCommand",
             quote! {
                 #[default]
-                #[doc = r"Run all the checks."]
+                #[doc = r"Run the unit, integration and documentation tests, --no-doc skips the documentation tests."]
                 All,
-                #[doc = r"Run unit tests."]
+                #[doc = r"Run the unit tests of the libraries and binaries (cargo test --lib --bins)."]
                 Unit,
-                #[doc = r"Run integration tests."]
+                #[doc = r"Run the integration tests of the tests directories (cargo test --test '*')."]
                 Integration,
+                #[doc = r"Run the documentation tests (cargo test --doc)."]
+                Doc,
             },
         ),
         (
@@ -764,7 +1379,7 @@ pub fn extend_subcommands(args: TokenStream, input: TokenStream) -> TokenStream
         parse_macro_input!(args_clone with Punctuated::<Meta, Comma>::parse_terminated);
     if parsed_args.len() != 1 {
         return TokenStream::from(quote! {
-            compile_error!("extend_subcommand takes one argument which is the type of the subcommand enum.");
+            compile_error!("extend_subcommands takes one argument which is the type of the subcommand enum.");
         });
     }
     let base_subcommand = parsed_args.get(0).unwrap();
@@ -776,13 +1391,9 @@ pub fn extend_subcommands(args: TokenStream, input: TokenStream) -> TokenStream
     let variant_map = get_subcommand_variant_map();
     if !variant_map.contains_key(base_subcommand_string.as_str()) {
         let err_msg = format!(
-            "Unknown command: {}\nPossible commands are:\n  {}",
+            "Unknown subcommand: {}\nPossible subcommands are:\n  {}",
             base_subcommand_string,
-            variant_map
-                .keys()
-                .cloned()
-                .collect::<Vec<&str>>()
-                .join("\n  "),
+            sorted_keys(&variant_map).join("\n  "),
         );
         return TokenStream::from(quote! { compile_error!(#err_msg); });
     }
@@ -797,3 +1408,15 @@ pub fn extend_subcommands(args: TokenStream, input: TokenStream) -> TokenStream
     ));
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_sorted_keys() {
+        let map = HashMap::from([("Test", 0), ("Build", 1), ("Check", 2), ("Bump", 3)]);
+        assert_eq!(sorted_keys(&map), ["Build", "Bump", "Check", "Test"]);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{path::Path, time::SystemTime};
+
+use anyhow::Ok;
+use serde::Deserialize;
+
+use crate::{
+    commands::{
+        add_listed_crates, ensure_only_default_members, restrict_to_changed_members,
+        target_package_args,
+    },
+    endgroup, group,
+    utils::{process::run_process, workspace::target_dir},
+};
+
+use super::Target;
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct BenchCmdArgs {}
+
+/// Maximum number of benchmark changes listed in the summary
+const MAX_REPORTED_CHANGES: usize = 10;
+
+/// Estimates of the `change/estimates.json` file written by Criterion when comparing to a baseline,
+/// the point estimate of the mean is the relative change of the mean time, 0.05 for 5% slower.
+#[derive(Debug, Deserialize)]
+struct ChangeEstimates {
+    mean: Estimate,
+}
+
+#[derive(Debug, Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+/// Change of the mean time of a benchmark relative to the baseline
+#[derive(Debug, PartialEq)]
+struct BenchmarkChange {
+    name: String,
+    percentage: f64,
+}
+
+pub fn handle_command(mut args: BenchCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
+    }
+    let start = SystemTime::now();
+    group!("Bench: {}", args.target);
+    let mut cmd_args = vec!["bench".to_string()];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if let Some(bench) = &args.bench {
+        cmd_args.extend(["--bench".to_string(), bench.clone()]);
+    }
+    let criterion = criterion_args(args.save_baseline.as_deref(), args.baseline.as_deref());
+    if !criterion.is_empty() {
+        cmd_args.push("--".to_string());
+        cmd_args.extend(criterion);
+    }
+    run_process(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+        "Benchmarks failed",
+    )?;
+    endgroup!();
+    match &args.baseline {
+        Some(baseline) => {
+            let changes = read_benchmark_changes(&target_dir().join("criterion"), start)?;
+            report_changes(&changes, baseline, args.regression_threshold)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns the Criterion arguments saving or comparing to the passed baselines
+fn criterion_args(save_baseline: Option<&str>, baseline: Option<&str>) -> Vec<String> {
+    let mut args = vec![];
+    if let Some(name) = save_baseline {
+        args.extend(["--save-baseline".to_string(), name.to_string()]);
+    }
+    if let Some(name) = baseline {
+        args.extend(["--baseline".to_string(), name.to_string()]);
+    }
+    args
+}
+
+/// Read the changes of the benchmarks compared since the passed time from the Criterion output directory.
+/// The changes of the benchmarks which did not run are left over from previous runs and are skipped.
+fn read_benchmark_changes(
+    criterion_dir: &Path,
+    since: SystemTime,
+) -> anyhow::Result<Vec<BenchmarkChange>> {
+    let mut changes = vec![];
+    if criterion_dir.is_dir() {
+        collect_changes(criterion_dir, criterion_dir, since, &mut changes)?;
+    }
+    changes.sort_by(|a, b| b.percentage.total_cmp(&a.percentage));
+    Ok(changes)
+}
+
+fn collect_changes(
+    criterion_dir: &Path,
+    dir: &Path,
+    since: SystemTime,
+    changes: &mut Vec<BenchmarkChange>,
+) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+    {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let estimates = path.join("estimates.json");
+        if path.file_name().is_some_and(|name| name == "change") && estimates.is_file() {
+            if std::fs::metadata(&estimates)?.modified()? < since {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&estimates)?;
+            let change: ChangeEstimates = serde_json::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", estimates.display(), e))?;
+            let name = dir
+                .strip_prefix(criterion_dir)
+                .unwrap_or(dir)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            changes.push(BenchmarkChange {
+                name,
+                percentage: change.mean.point_estimate * 100.0,
+            });
+        } else {
+            collect_changes(criterion_dir, &path, since, changes)?;
+        }
+    }
+    Ok(())
+}
+
+/// Log the largest changes against the baseline and fail when a benchmark regressed by more than the threshold.
+fn report_changes(
+    changes: &[BenchmarkChange],
+    baseline: &str,
+    threshold: Option<f64>,
+) -> anyhow::Result<()> {
+    if changes.is_empty() {
+        warn!(
+            "No benchmark result compared to the baseline '{}' was found, only Criterion benchmarks are compared.",
+            baseline
+        );
+        return Ok(());
+    }
+    group!("Benchmark changes against baseline '{}'", baseline);
+    changes
+        .iter()
+        .take(MAX_REPORTED_CHANGES)
+        .for_each(|change| info!("{:+8.2}%  {}", change.percentage, change.name));
+    if changes.len() > MAX_REPORTED_CHANGES {
+        info!("... and {} more", changes.len() - MAX_REPORTED_CHANGES);
+    }
+    endgroup!();
+    let Some(threshold) = threshold else {
+        return Ok(());
+    };
+    let regressions = changes
+        .iter()
+        .filter(|change| change.percentage > threshold)
+        .count();
+    if regressions > 0 {
+        return Err(anyhow::anyhow!(
+            "{} benchmark(s) regressed by more than {}% against the baseline '{}'",
+            regressions,
+            threshold,
+            baseline
+        ));
+    }
+    info!("No benchmark regressed by more than {}%.", threshold);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn write_change(dir: &Path, name: &str, mean: f64) {
+        let change_dir = dir.join(name).join("change");
+        std::fs::create_dir_all(&change_dir).unwrap();
+        std::fs::write(
+            change_dir.join("estimates.json"),
+            format!(r#"{{"mean":{{"confidence_interval":{{"lower_bound":0.0,"upper_bound":0.1}},"point_estimate":{mean},"standard_error":0.01}},"median":{{"point_estimate":{mean}}}}}"#),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_read_benchmark_changes() {
+        let dir = std::env::temp_dir().join(format!("xtask-criterion-{}", std::process::id()));
+        write_change(&dir, "parse/small", -0.02);
+        write_change(&dir, "parse/large", 0.125);
+        write_change(&dir, "serialize", 0.03);
+        std::fs::create_dir_all(dir.join("serialize/main")).unwrap();
+        std::fs::write(dir.join("serialize/main/estimates.json"), "{}").unwrap();
+        let changes = read_benchmark_changes(&dir, SystemTime::UNIX_EPOCH);
+        let stale =
+            read_benchmark_changes(&dir, SystemTime::now() + std::time::Duration::from_secs(60));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<String> = changes.unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["parse/large", "serialize", "parse/small"]);
+        assert_eq!(stale.unwrap(), vec![]);
+    }
+
+    #[rstest]
+    #[case::no_threshold(None, true)]
+    #[case::below_threshold(Some(15.0), true)]
+    #[case::above_threshold(Some(10.0), false)]
+    fn test_report_changes(#[case] threshold: Option<f64>, #[case] passes: bool) {
+        let changes = vec![
+            BenchmarkChange {
+                name: "parse".to_string(),
+                percentage: 12.5,
+            },
+            BenchmarkChange {
+                name: "serialize".to_string(),
+                percentage: -3.0,
+            },
+        ];
+        assert_eq!(report_changes(&changes, "main", threshold).is_ok(), passes);
+    }
+}
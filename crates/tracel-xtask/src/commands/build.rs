@@ -1,39 +1,133 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::Ok;
+use serde_json::Value;
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_ONLY_ARGS,
+    commands::{
+        add_listed_crates, ensure_nightly_toolchain, ensure_only_default_members, profile_args,
+        report_timings, restrict_to_changed_members, target_package_args, timings_args,
+        try_for_each_keep_going,
+    },
     endgroup, group,
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
-        workspace::{get_workspace_members, WorkspaceMemberType},
+        explain::explain_write,
+        outcome::record_artifact,
+        process::run_process_with_diagnostics,
+        process::{run_process_for_package, run_process_for_workspace, run_process_with_output},
+        workspace::{
+            get_workspace_members, invocation_path, target_dir, WorkspaceMemberType,
+            MEMBER_PATH_PREFIX,
+        },
     },
 };
 
+/// Target kinds of the library artifacts copied to the output directory
+const LIB_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib"];
+
 use super::Target;
 
 #[tracel_xtask_macros::declare_command_args(Target, None)]
 pub struct BuildCmdArgs {}
 
-pub fn handle_command(args: BuildCmdArgs) -> anyhow::Result<()> {
-    if args.target == Target::Workspace && !args.only.is_empty() {
-        warn!("{}", WARN_IGNORED_ONLY_ARGS);
+pub fn handle_command(mut args: BuildCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
+    }
+    let profile = profile_args(args.release, args.profile.as_deref());
+    if args.profile_cargo {
+        return write_unit_graph(&args, &profile);
+    }
+    let build_args = timings_args(&profile, args.timings);
+    if args.summary {
+        run_build_with_summary(&args, &build_args)?;
+    } else {
+        run_build(
+            &args.target,
+            &args.exclude,
+            &args.only,
+            args.keep_going,
+            &build_args,
+        )?;
+    }
+    if args.timings {
+        report_timings(args.open)?;
+    }
+    if let Some(out_dir) = &args.out_dir {
+        group!("Collect artifacts");
+        let artifacts = collect_artifacts(
+            &args.target,
+            &args.exclude,
+            &args.only,
+            &profile,
+            args.bins_only,
+        )?;
+        copy_artifacts(&artifacts, &invocation_path(out_dir))?;
+        endgroup!();
+    }
+    Ok(())
+}
+
+/// Build all the packages of the target in one cargo invocation and summarize the diagnostics.
+fn run_build_with_summary(args: &BuildCmdArgs, profile: &[&str]) -> anyhow::Result<()> {
+    group!("Build: {}", args.target);
+    let mut cmd_args = vec![
+        "build".to_string(),
+        "--color".to_string(),
+        "always".to_string(),
+    ];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if args.keep_going {
+        cmd_args.push("--keep-going".to_string());
     }
-    run_build(&args.target, &args.exclude, &args.only)
+    cmd_args.extend(profile.iter().map(|s| s.to_string()));
+    run_process_with_diagnostics(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        args.verbose,
+        "Build failed",
+    )?;
+    endgroup!();
+    Ok(())
 }
 
 pub(crate) fn run_build(
     target: &Target,
     excluded: &Vec<String>,
     only: &Vec<String>,
+    keep_going: bool,
+    profile: &[&str],
 ) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Build Workspace");
+            let mut cmd_args = vec!["build", "--workspace", "--color", "always"];
+            if keep_going {
+                cmd_args.push("--keep-going");
+            }
+            cmd_args.extend(profile);
             run_process_for_workspace(
                 "cargo",
-                &["build", "--workspace", "--color", "always"],
+                &cmd_args,
                 excluded,
+                only,
                 None,
                 None,
                 "Workspace build failed",
@@ -42,19 +136,29 @@ pub(crate) fn run_build(
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
+            try_for_each_keep_going(members, keep_going, |member| {
                 group!("Build: {}", member.name);
+                let mut cmd_args = vec!["build", "-p", &member.name, "--color", "always"];
+                cmd_args.extend(profile);
                 run_process_for_package(
                     "cargo",
                     &member.name,
-                    &["build", "-p", &member.name, "--color", "always"],
+                    &cmd_args,
                     excluded,
                     only,
                     &format!("Build command failed for {}", &member.name),
@@ -62,13 +166,235 @@ pub(crate) fn run_build(
                     None,
                 )?;
                 endgroup!();
-            }
+                Ok(())
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_build(&t, excluded, only))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                keep_going,
+                |t| run_build(&t, excluded, only, keep_going, profile),
+            )?;
         }
     }
     Ok(())
 }
+
+/// Write the unit graph of cargo for the target to `target/unit-graph.json` with the nightly toolchain.
+/// Nothing is built, cargo only resolves the units it would compile.
+fn write_unit_graph(args: &BuildCmdArgs, profile: &[&str]) -> anyhow::Result<()> {
+    ensure_nightly_toolchain("The unit graph of cargo", "build --profile-cargo", &[])?;
+    group!("Unit graph: {}", args.target);
+    let cmd_args = unit_graph_args(&args.target, &args.exclude, &args.only, profile)?;
+    let output = run_process_with_output(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to retrieve the unit graph of cargo"
+        ));
+    }
+    let path = target_dir().join("unit-graph.json");
+    if explain_write(&format!("write {}", path.display())) {
+        endgroup!();
+        return Ok(());
+    }
+    let graph = String::from_utf8_lossy(&output.stdout);
+    let units = count_units(&graph)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, graph.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    info!(
+        "Unit graph of {} units written to {}",
+        units,
+        path.display()
+    );
+    record_artifact(&path);
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the arguments of the nightly cargo build printing the unit graph of the target
+fn unit_graph_args(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    profile: &[&str],
+) -> anyhow::Result<Vec<String>> {
+    let mut cmd_args: Vec<String> = [
+        "+nightly",
+        "build",
+        "--unit-graph",
+        "-Z",
+        "unstable-options",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    cmd_args.extend(target_package_args(target, excluded, only)?);
+    cmd_args.extend(profile.iter().map(|s| s.to_string()));
+    Ok(cmd_args)
+}
+
+/// Returns the number of units of the unit graph printed by cargo
+fn count_units(graph: &str) -> anyhow::Result<usize> {
+    let graph: Value = serde_json::from_str(graph)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the unit graph: {}", e))?;
+    graph["units"]
+        .as_array()
+        .map(Vec::len)
+        .ok_or_else(|| anyhow::anyhow!("The unit graph has no units"))
+}
+
+/// Build again with JSON messages to retrieve the paths of the artifacts of the workspace members.
+/// Everything is fresh at this point so cargo only reports the artifacts.
+fn collect_artifacts(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    profile: &[&str],
+    bins_only: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut cmd_args = vec!["build".to_string(), "--message-format=json".to_string()];
+    cmd_args.extend(target_package_args(target, excluded, only)?);
+    cmd_args.extend(profile.iter().map(|s| s.to_string()));
+    let output = run_process_with_output(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to retrieve the build artifacts"));
+    }
+    Ok(parse_artifacts(
+        &String::from_utf8_lossy(&output.stdout),
+        bins_only,
+    ))
+}
+
+/// Parse the compiler-artifact messages of cargo and returns the paths of the produced
+/// binaries and libraries of the local packages.
+fn parse_artifacts(messages: &str, bins_only: bool) -> Vec<PathBuf> {
+    messages
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact")
+        .filter(|msg| {
+            msg["package_id"]
+                .as_str()
+                .is_some_and(|id| id.contains(MEMBER_PATH_PREFIX))
+        })
+        .flat_map(|msg| {
+            let kinds: Vec<&str> = msg["target"]["kind"]
+                .as_array()
+                .map(|kinds| kinds.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let mut paths = vec![];
+            if kinds.contains(&"bin") {
+                if let Some(executable) = msg["executable"].as_str() {
+                    paths.push(PathBuf::from(executable));
+                }
+            }
+            if !bins_only && kinds.iter().any(|k| LIB_KINDS.contains(k)) {
+                if let Some(filenames) = msg["filenames"].as_array() {
+                    paths.extend(
+                        filenames
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .filter(|f| !f.ends_with(".rmeta"))
+                            .map(PathBuf::from),
+                    );
+                }
+            }
+            paths
+        })
+        .collect()
+}
+
+fn copy_artifacts(artifacts: &[PathBuf], out_dir: &Path) -> anyhow::Result<()> {
+    if explain_write(&format!(
+        "copy the build artifacts to {}",
+        out_dir.display()
+    )) {
+        return Ok(());
+    }
+    std::fs::create_dir_all(out_dir).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create output directory {}: {}",
+            out_dir.display(),
+            e
+        )
+    })?;
+    for artifact in artifacts {
+        let Some(file_name) = artifact.file_name() else {
+            continue;
+        };
+        let destination = out_dir.join(file_name);
+        std::fs::copy(artifact, &destination)
+            .map_err(|e| anyhow::anyhow!("Failed to copy {}: {}", artifact.display(), e))?;
+        info!("Copied {} to {}", artifact.display(), destination.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const MESSAGES: &str = r#"{"reason":"compiler-artifact","package_id":"registry+https://github.com/rust-lang/crates.io-index#serde@1.0.200","target":{"kind":["lib"]},"filenames":["/ws/target/debug/deps/libserde-1.rlib","/ws/target/debug/deps/libserde-1.rmeta"],"executable":null}
+{"reason":"compiler-artifact","package_id":"path+file:///ws/crates/foo#0.1.0","target":{"kind":["lib"]},"filenames":["/ws/target/debug/libfoo.rlib","/ws/target/debug/deps/libfoo-1.rmeta"],"executable":null}
+{"reason":"compiler-artifact","package_id":"path+file:///ws/crates/foo#0.1.0","target":{"kind":["custom-build"]},"filenames":["/ws/target/debug/build/foo-1/build-script-build"],"executable":null}
+{"reason":"compiler-artifact","package_id":"path+file:///ws/crates/bar#0.1.0","target":{"kind":["bin"]},"filenames":["/ws/target/debug/bar"],"executable":"/ws/target/debug/bar"}
+{"reason":"build-finished","success":true}"#;
+
+    #[rstest]
+    fn test_unit_graph_args() {
+        let args = unit_graph_args(
+            &Target::Workspace,
+            &["foo".to_string()],
+            &[],
+            &["--release"],
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "+nightly",
+                "build",
+                "--unit-graph",
+                "-Z",
+                "unstable-options",
+                "--workspace",
+                "--exclude",
+                "foo",
+                "--release"
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::units(
+        r#"{"version":1,"units":[{"pkg_id":"foo"},{"pkg_id":"bar"}],"roots":[0]}"#,
+        Some(2)
+    )]
+    #[case::no_units(r#"{"version":1}"#, None)]
+    #[case::not_json("warning: nothing", None)]
+    fn test_count_units(#[case] graph: &str, #[case] expected: Option<usize>) {
+        assert_eq!(count_units(graph).ok(), expected);
+    }
+
+    #[rstest]
+    #[case::all(false, vec!["/ws/target/debug/libfoo.rlib", "/ws/target/debug/bar"])]
+    #[case::bins_only(true, vec!["/ws/target/debug/bar"])]
+    fn test_parse_artifacts(#[case] bins_only: bool, #[case] expected: Vec<&str>) {
+        let expected: Vec<PathBuf> = expected.into_iter().map(PathBuf::from).collect();
+        assert_eq!(parse_artifacts(MESSAGES, bins_only), expected);
+    }
+}
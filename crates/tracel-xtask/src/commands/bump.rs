@@ -1,23 +1,113 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
 use anyhow::Ok;
+use serde_json::Value;
+use toml_edit::{DocumentMut, TableLike};
 
 use crate::{
     endgroup, group,
-    utils::{cargo::ensure_cargo_crate_is_installed, process::run_process},
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        explain::explain_write,
+        git::{create_tag, modified_files, repository_root, tag_exists},
+        process::{run_process, run_process_with_output},
+        prompt::ask_once,
+        workspace::{dependent_member_names, get_workspace_members, metadata, WorkspaceMemberType},
+    },
 };
 
 #[tracel_xtask_macros::declare_command_args(None, BumpSubCommand)]
 pub struct BumpCmdArgs {}
 
+/// Version declaration of a workspace member, the version is inherited when the manifest
+/// sets `version.workspace = true`.
+#[derive(Debug, PartialEq)]
+struct MemberVersion {
+    name: String,
+    manifest: PathBuf,
+    inherited: bool,
+}
+
+/// Crates bumped by a version bump: the crates inheriting the version of the workspace share
+/// the bump of `workspace.package.version`, the other crates are bumped by cargo set-version,
+/// all of them when the list is empty.
+#[derive(Debug, PartialEq)]
+struct BumpPlan {
+    inherited: Vec<String>,
+    set_version: Option<Vec<String>>,
+}
+
 pub fn handle_command(args: BumpCmdArgs) -> anyhow::Result<()> {
-    bump(&args.get_command())
+    let command = args.get_command();
+    let metadata = metadata()?;
+    let members = member_versions(&metadata)?;
+    let plan = plan_bump(&members, &args.packages);
+    if !args.packages.is_empty() {
+        let implied: Vec<&String> = plan
+            .inherited
+            .iter()
+            .filter(|name| !args.packages.contains(name))
+            .collect();
+        if !implied.is_empty() {
+            warn!(
+                "The version of the workspace is shared, the crates {} inheriting it are bumped as well.",
+                implied
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            );
+        }
+        let mut bumped = plan.inherited.clone();
+        bumped.extend(plan.set_version.iter().flatten().cloned());
+        warn_incompatible_dependents(&metadata, &command, &bumped);
+    }
+    if !plan.inherited.is_empty() {
+        let root = metadata["workspace_root"]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("The cargo metadata has no workspace root"))?;
+        if args.dry_run {
+            group!("Bump version preview: {command} (workspace)");
+        } else {
+            group!("Bump version: {command} (workspace)");
+        }
+        let changes = bump_workspace_version(&root, &members, &command, args.dry_run)?;
+        changes.iter().for_each(|change| info!("  {}", change));
+        endgroup!();
+    }
+    if let Some(packages) = &plan.set_version {
+        if args.dry_run {
+            preview_bump(&command, packages)?;
+        } else {
+            bump(&command, packages)?;
+        }
+    }
+    if args.dry_run {
+        if args.tag {
+            info!("The git tag of the new version is not created with '--dry-run'.");
+        }
+        return Ok(());
+    }
+    if args.tag {
+        tag_version(&args.packages, &args.tag_prefix, args.push)?;
+    }
+    Ok(())
 }
 
-fn bump(command: &BumpSubCommand) -> anyhow::Result<()> {
+fn bump(command: &BumpSubCommand, packages: &[String]) -> anyhow::Result<()> {
     group!("Bump version: {command}");
     ensure_cargo_crate_is_installed("cargo-edit", None, None, false)?;
+    // cargo set-version also updates the requirements of the workspace members depending on the bumped crates
     run_process(
         "cargo",
-        &["set-version", "--bump", &command.to_string()],
+        &set_version_args(command, packages)
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
         None,
         None,
         &format!("Error trying to bump {command} version"),
@@ -25,3 +115,751 @@ fn bump(command: &BumpSubCommand) -> anyhow::Result<()> {
     endgroup!();
     Ok(())
 }
+
+/// Print the changes of the bump without modifying the manifests, the new versions are computed by
+/// the dry run of cargo set-version so that they are the ones of the actual bump.
+fn preview_bump(command: &BumpSubCommand, packages: &[String]) -> anyhow::Result<()> {
+    group!("Bump version preview: {command}");
+    ensure_cargo_crate_is_installed("cargo-edit", None, None, false)?;
+    let mut args = set_version_args(command, packages);
+    args.push("--dry-run".to_string());
+    let output = run_process_with_output(
+        "cargo",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    // cargo set-version reports the changes on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Error trying to preview the {command} version bump: {}",
+            stderr.trim()
+        ));
+    }
+    let changes = version_changes(&stderr);
+    if changes.is_empty() {
+        info!("No version change.");
+    }
+    changes.iter().for_each(|change| info!("  {}", change));
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the changes reported by cargo set-version as `<crate>: <old> -> <new>` lines,
+/// the rewritten requirements of the dependent crates are reported as well.
+fn version_changes(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if let Some(change) = line.strip_prefix("Upgrading ") {
+                let (name, versions) = change.split_once(" from ")?;
+                let (old, new) = versions.split_once(" to ")?;
+                Some(format!("{name}: {old} -> {new}"))
+            } else if let Some(change) = line.strip_prefix("Updating ") {
+                let (name, versions) = change.split_once("'s dependency from ")?;
+                let (old, new) = versions.split_once(" to ")?;
+                Some(format!("{name}: dependency requirement {old} -> {new}"))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns the version declaration of the workspace members read from their manifest
+fn member_versions(metadata: &Value) -> anyhow::Result<Vec<MemberVersion>> {
+    let ids: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let mut members = vec![];
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        if !package["id"].as_str().is_some_and(|id| ids.contains(id)) {
+            continue;
+        }
+        let (Some(name), Some(manifest)) =
+            (package["name"].as_str(), package["manifest_path"].as_str())
+        else {
+            continue;
+        };
+        let manifest = PathBuf::from(manifest);
+        let contents: toml::Value = std::fs::read_to_string(&manifest)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", manifest.display(), e))?
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest.display(), e))?;
+        let inherited = contents
+            .get("package")
+            .and_then(|package| package.get("version"))
+            .and_then(|version| version.get("workspace"))
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false);
+        members.push(MemberVersion {
+            name: name.to_string(),
+            manifest,
+            inherited,
+        });
+    }
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(members)
+}
+
+/// Returns the crates bumped with the shared version of the workspace and the ones bumped by
+/// cargo set-version. Selecting a crate which inherits the version bumps all the inheriting crates.
+fn plan_bump(members: &[MemberVersion], packages: &[String]) -> BumpPlan {
+    let inherited: Vec<String> = members
+        .iter()
+        .filter(|m| m.inherited)
+        .map(|m| m.name.clone())
+        .collect();
+    if inherited.is_empty() {
+        return BumpPlan {
+            inherited,
+            set_version: Some(packages.to_vec()),
+        };
+    }
+    let (selected, others): (Vec<String>, Vec<String>) = if packages.is_empty() {
+        (
+            inherited.clone(),
+            members
+                .iter()
+                .filter(|m| !m.inherited)
+                .map(|m| m.name.clone())
+                .collect(),
+        )
+    } else {
+        packages
+            .iter()
+            .cloned()
+            .partition(|package| inherited.contains(package))
+    };
+    BumpPlan {
+        inherited: if selected.is_empty() {
+            vec![]
+        } else {
+            inherited
+        },
+        set_version: (!others.is_empty()).then_some(others),
+    }
+}
+
+/// Bump `workspace.package.version` and the version requirements on the crates inheriting it,
+/// returns the changes. The manifests are not written with `dry_run`.
+fn bump_workspace_version(
+    root: &Path,
+    members: &[MemberVersion],
+    command: &BumpSubCommand,
+    dry_run: bool,
+) -> anyhow::Result<Vec<String>> {
+    let root_manifest = root.join("Cargo.toml");
+    let mut documents = BTreeMap::new();
+    let document = load_manifest(&mut documents, &root_manifest)?;
+    let version = document
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("package"))
+        .and_then(|package| package.get_mut("version"))
+        .and_then(|version| version.as_value_mut())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "The crates inherit the version of the workspace but {} does not set workspace.package.version",
+                root_manifest.display()
+            )
+        })?;
+    let old = version
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("workspace.package.version is not a string"))?
+        .to_string();
+    let new = bumped_version(&old, command)?;
+    set_string(version, &new);
+    let mut changes = vec![format!("workspace.package: {old} -> {new}")];
+    let inherited: BTreeSet<&str> = members
+        .iter()
+        .filter(|m| m.inherited)
+        .map(|m| m.name.as_str())
+        .collect();
+    if let Some(dependencies) = document
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(|dependencies| dependencies.as_table_like_mut())
+    {
+        update_requirements(dependencies, &inherited, &new)
+            .into_iter()
+            .for_each(|(old, new)| {
+                changes.push(format!("workspace: dependency requirement {old} -> {new}"))
+            });
+    }
+    for member in members {
+        let document = load_manifest(&mut documents, &member.manifest)?;
+        for table in dependency_tables(document) {
+            update_requirements(table, &inherited, &new)
+                .into_iter()
+                .for_each(|(old, new)| {
+                    changes.push(format!(
+                        "{}: dependency requirement {old} -> {new}",
+                        member.name
+                    ))
+                });
+        }
+    }
+    if !dry_run {
+        for (path, document) in &documents {
+            if explain_write(&format!("write {}", path.display())) {
+                continue;
+            }
+            std::fs::write(path, document.to_string())
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(changes)
+}
+
+/// Dependency tables of a manifest, they can also be declared per target
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Returns the dependency tables of a manifest, including the ones of the `target` tables
+fn dependency_tables(document: &mut DocumentMut) -> Vec<&mut dyn TableLike> {
+    let mut tables = vec![];
+    for (key, item) in document.iter_mut() {
+        if DEPENDENCY_TABLES.contains(&key.get()) {
+            tables.extend(item.as_table_like_mut());
+        } else if key == "target" {
+            let platforms = item
+                .as_table_like_mut()
+                .into_iter()
+                .flat_map(|t| t.iter_mut());
+            for (_, platform) in platforms {
+                let dependencies = platform
+                    .as_table_like_mut()
+                    .into_iter()
+                    .flat_map(|t| t.iter_mut())
+                    .filter(|(key, _)| DEPENDENCY_TABLES.contains(&key.get()));
+                tables.extend(dependencies.filter_map(|(_, item)| item.as_table_like_mut()));
+            }
+        }
+    }
+    tables
+}
+
+/// Returns the parsed manifest at the passed path, each manifest is parsed once so that the
+/// changes of the root manifest and of a root package are kept together.
+fn load_manifest<'a>(
+    documents: &'a mut BTreeMap<PathBuf, DocumentMut>,
+    path: &Path,
+) -> anyhow::Result<&'a mut DocumentMut> {
+    if !documents.contains_key(path) {
+        let document = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?
+            .parse::<DocumentMut>()
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        documents.insert(path.to_path_buf(), document);
+    }
+    Ok(documents
+        .get_mut(path)
+        .expect("The manifest should be loaded"))
+}
+
+/// Update the version requirements of the dependencies on the passed crates to the new version,
+/// returns the old and new requirements. The dependencies inherited from the workspace are skipped.
+fn update_requirements(
+    dependencies: &mut dyn TableLike,
+    crates: &BTreeSet<&str>,
+    version: &str,
+) -> Vec<(String, String)> {
+    let mut updated = vec![];
+    for (key, dependency) in dependencies.iter_mut() {
+        let Some(dependency) = dependency.as_table_like_mut() else {
+            continue;
+        };
+        let name = dependency
+            .get("package")
+            .and_then(|package| package.as_str())
+            .unwrap_or(key.get())
+            .to_string();
+        if !crates.contains(name.as_str()) {
+            continue;
+        }
+        let Some(requirement) = dependency
+            .get_mut("version")
+            .and_then(|requirement| requirement.as_value_mut())
+        else {
+            continue;
+        };
+        let Some(old) = requirement.as_str().map(String::from) else {
+            continue;
+        };
+        match bumped_requirement(&old, version) {
+            Some(new) if new != old => {
+                set_string(requirement, &new);
+                updated.push((old, new));
+            }
+            Some(_) => {}
+            None => warn!(
+                "The requirement '{}' on {} is not updated to the version {}, only the requirements with a single                  version are updated.",
+                old, name, version
+            ),
+        }
+    }
+    updated
+}
+
+/// Replace the string of a toml value while keeping its comments
+fn set_string(value: &mut toml_edit::Value, new: &str) {
+    let decor = value.decor().clone();
+    *value = new.into();
+    *value.decor_mut() = decor;
+}
+
+/// Returns the requirement on the passed version keeping the operator of the passed requirement,
+/// none when the requirement is not a single version.
+fn bumped_requirement(requirement: &str, version: &str) -> Option<String> {
+    let requirement = requirement.trim();
+    let operator_len = requirement
+        .find(|c: char| !matches!(c, '=' | '^' | '~' | ' '))
+        .unwrap_or(requirement.len());
+    let (operator, rest) = requirement.split_at(operator_len);
+    if rest.is_empty() || rest.contains([',', '*', '<', '>']) {
+        return None;
+    }
+    Some(format!("{}{}", operator.trim(), version))
+}
+
+/// Returns the bumped version like cargo set-version: the pre-release of a version is released
+/// when the bumped component is the lowest non-zero one, otherwise the component is incremented.
+fn bumped_version(version: &str, command: &BumpSubCommand) -> anyhow::Result<String> {
+    let invalid = || anyhow::anyhow!("Invalid version '{}'", version);
+    // the build metadata is dropped
+    let version = version
+        .split_once('+')
+        .map_or(version, |(version, _)| version);
+    let (release, prerelease) = match version.split_once('-') {
+        Some((release, _)) => (release, true),
+        None => (version, false),
+    };
+    let components = release
+        .split('.')
+        .map(|c| c.parse::<u64>().map_err(|_| invalid()))
+        .collect::<anyhow::Result<Vec<u64>>>()?;
+    let [major, minor, patch] = components[..] else {
+        return Err(invalid());
+    };
+    let (major, minor, patch) = match command {
+        BumpSubCommand::Major if prerelease && minor == 0 && patch == 0 => (major, 0, 0),
+        BumpSubCommand::Major => (major + 1, 0, 0),
+        BumpSubCommand::Minor if prerelease && patch == 0 => (major, minor, 0),
+        BumpSubCommand::Minor => (major, minor + 1, 0),
+        BumpSubCommand::Patch if prerelease => (major, minor, patch),
+        BumpSubCommand::Patch => (major, minor, patch + 1),
+    };
+    Ok(format!("{major}.{minor}.{patch}"))
+}
+
+fn set_version_args(command: &BumpSubCommand, packages: &[String]) -> Vec<String> {
+    let mut args = vec![
+        "set-version".to_string(),
+        "--bump".to_string(),
+        command.to_string(),
+    ];
+    packages
+        .iter()
+        .for_each(|package| args.extend(["--package".to_string(), package.clone()]));
+    args
+}
+
+/// Warn about the workspace members which are not bumped while they depend on a crate whose
+/// new version is not semver compatible with the previous one.
+fn warn_incompatible_dependents(metadata: &Value, command: &BumpSubCommand, packages: &[String]) {
+    for package in packages {
+        let Some(version) = package_version(metadata, package) else {
+            continue;
+        };
+        if !is_breaking_bump(command, &version) {
+            continue;
+        }
+        let dependents: Vec<String> = dependent_member_names(metadata, package)
+            .into_iter()
+            .filter(|dependent| !packages.contains(dependent))
+            .collect();
+        if !dependents.is_empty() {
+            warn!(
+                "The {} bump of '{}' {} is not compatible with the previous version, the crates {} depend on it \
+                 and are not bumped: their published versions require the previous version.",
+                command,
+                package,
+                version,
+                dependents.join(", ")
+            );
+        }
+    }
+}
+
+/// Commit the bumped manifests then create the annotated tag of the new version and optionally push it.
+fn tag_version(packages: &[String], prefix: &str, push: bool) -> anyhow::Result<()> {
+    group!("Tag version");
+    let metadata = metadata()?;
+    let packages = if packages.is_empty() {
+        get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+            .into_iter()
+            .map(|m| m.name)
+            .collect()
+    } else {
+        packages.to_vec()
+    };
+    let versions: Vec<(String, String)> = packages
+        .into_iter()
+        .filter_map(|package| {
+            let version = package_version(&metadata, &package)?;
+            Some((package, version))
+        })
+        .collect();
+    let version = release_version(&versions)?;
+    let tag = format!("{prefix}{version}");
+    if tag_exists(&tag)? {
+        return Err(anyhow::anyhow!("The git tag {} already exists", tag));
+    }
+    let manifests = manifest_files(&modified_files()?);
+    let mut prompt = format!("This will create the annotated git tag {}", tag);
+    if !manifests.is_empty() {
+        prompt = format!(
+            "This will commit the changes of {} and create the annotated git tag {}",
+            manifests.join(", "),
+            tag
+        );
+    }
+    if push {
+        prompt.push_str(" then push the tag to origin");
+    }
+    if !ask_once(&format!("{prompt}.")) {
+        info!("The git tag {} has not been created.", tag);
+        endgroup!();
+        return Ok(());
+    }
+    if !manifests.is_empty() {
+        let message = format!("Bump version to {version}");
+        let mut args = vec!["commit", "-m", &message, "--"];
+        args.extend(manifests.iter().map(String::as_str));
+        // the paths of the modified files are relative to the repository root
+        run_process(
+            "git",
+            &args,
+            None,
+            Some(&repository_root()?),
+            "Error committing the bumped manifests",
+        )?;
+    }
+    create_tag(&tag, &format!("Release {version}"))?;
+    if push {
+        run_process(
+            "git",
+            &["push", "origin", &tag],
+            None,
+            None,
+            &format!("Error pushing the git tag {tag}"),
+        )?;
+    }
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the version shared by the bumped crates, a single tag cannot be created for different versions
+fn release_version(versions: &[(String, String)]) -> anyhow::Result<String> {
+    let Some((_, version)) = versions.first() else {
+        return Err(anyhow::anyhow!("No bumped crate to tag the version of"));
+    };
+    let different: Vec<String> = versions
+        .iter()
+        .filter(|(_, v)| v != version)
+        .map(|(name, v)| format!("{name} {v}"))
+        .collect();
+    if !different.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The bumped crates do not share the version {}: {}, use --package to tag the version of some crates",
+            version,
+            different.join(", ")
+        ));
+    }
+    Ok(version.clone())
+}
+
+/// Returns the cargo manifests and lock files of the passed files
+fn manifest_files(files: &[String]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|file| file.ends_with("Cargo.toml") || file.ends_with("Cargo.lock"))
+        .cloned()
+        .collect()
+}
+
+/// Returns the version of the passed package of the cargo metadata
+fn package_version(metadata: &Value, name: &str) -> Option<String> {
+    metadata["packages"]
+        .as_array()?
+        .iter()
+        .find(|p| p["name"].as_str() == Some(name) && p["source"].is_null())?["version"]
+        .as_str()
+        .map(String::from)
+}
+
+/// Returns true if the bumped version is not semver compatible with the passed version,
+/// the first non-zero component of a version is its compatibility boundary.
+fn is_breaking_bump(command: &BumpSubCommand, version: &str) -> bool {
+    let mut components = version
+        .split(['.', '-', '+'])
+        .map(|c| c.parse::<u64>().unwrap_or(0));
+    let (major, minor) = (
+        components.next().unwrap_or(0),
+        components.next().unwrap_or(0),
+    );
+    match command {
+        BumpSubCommand::Major => true,
+        BumpSubCommand::Minor => major == 0,
+        BumpSubCommand::Patch => major == 0 && minor == 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    use crate::utils::process::run_query_with_output;
+
+    /// Write a workspace whose crates a and b inherit the version of the workspace, c overrides it
+    fn write_inherited_workspace(dir: &Path) {
+        let manifests = [
+            (
+                "Cargo.toml",
+                r#"[workspace]
+resolver = "2"
+members = ["a", "b", "c"]
+
+[workspace.package]
+version = "0.4.1" # shared version
+
+[workspace.dependencies]
+a = { path = "a", version = "0.4.1" }
+"#,
+            ),
+            (
+                "a/Cargo.toml",
+                "[package]\nname = \"a\"\nversion.workspace = true\nedition = \"2021\"\n",
+            ),
+            (
+                "b/Cargo.toml",
+                r#"[package]
+name = "b"
+version.workspace = true
+edition = "2021"
+
+[dependencies]
+a = { workspace = true }
+c = { path = "../c", version = "1.0.0" }
+"#,
+            ),
+            (
+                "c/Cargo.toml",
+                r#"[package]
+name = "c"
+version = "1.0.0"
+edition = "2021"
+
+[target.'cfg(unix)'.dependencies]
+a = { path = "../a", version = "=0.4.1" }
+"#,
+            ),
+        ];
+        for (path, contents) in manifests {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap().join("src")).unwrap();
+            std::fs::write(path.parent().unwrap().join("src/lib.rs"), "").unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[rstest]
+    fn test_bump_workspace_version() {
+        let dir = std::env::temp_dir().join(format!("xtask-bump-{}", std::process::id()));
+        write_inherited_workspace(&dir);
+        let output = run_query_with_output(
+            "cargo",
+            &["metadata", "--format-version", "1", "--no-deps"],
+            Some(&dir),
+        )
+        .unwrap();
+        let metadata: Value = serde_json::from_slice(&output.stdout).unwrap();
+        let members = member_versions(&metadata).unwrap();
+        let preview = bump_workspace_version(&dir, &members, &BumpSubCommand::Minor, true).unwrap();
+        let unchanged = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        let changes =
+            bump_workspace_version(&dir, &members, &BumpSubCommand::Minor, false).unwrap();
+        let root = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        let c = std::fs::read_to_string(dir.join("c/Cargo.toml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let inherited: Vec<(&str, bool)> = members
+            .iter()
+            .map(|m| (m.name.as_str(), m.inherited))
+            .collect();
+        assert_eq!(inherited, vec![("a", true), ("b", true), ("c", false)]);
+        assert_eq!(
+            changes,
+            vec![
+                "workspace.package: 0.4.1 -> 0.5.0",
+                "workspace: dependency requirement 0.4.1 -> 0.5.0",
+                "c: dependency requirement =0.4.1 -> =0.5.0",
+            ]
+        );
+        assert_eq!(preview, changes);
+        assert!(unchanged.contains(r#"version = "0.4.1" # shared version"#));
+        assert!(root.contains(r#"version = "0.5.0" # shared version"#));
+        assert!(root.contains(r#"a = { path = "a", version = "0.5.0" }"#));
+        assert!(c.contains(r#"version = "1.0.0""#));
+        assert!(c.contains(r#"a = { path = "../a", version = "=0.5.0" }"#));
+    }
+
+    fn member(name: &str, inherited: bool) -> MemberVersion {
+        MemberVersion {
+            name: name.to_string(),
+            manifest: PathBuf::from(name).join("Cargo.toml"),
+            inherited,
+        }
+    }
+
+    #[rstest]
+    #[case::no_inheritance(vec![("a", false), ("b", false)], vec![], vec![], Some(vec![]))]
+    #[case::all_crates(vec![("a", true), ("b", true), ("c", false)], vec![], vec!["a", "b"], Some(vec!["c"]))]
+    #[case::all_inherited(vec![("a", true), ("b", true)], vec![], vec!["a", "b"], None)]
+    #[case::inherited_package(vec![("a", true), ("b", true), ("c", false)], vec!["a"], vec!["a", "b"], None)]
+    #[case::overriding_package(vec![("a", true), ("b", true), ("c", false)], vec!["c"], vec![], Some(vec!["c"]))]
+    fn test_plan_bump(
+        #[case] members: Vec<(&str, bool)>,
+        #[case] packages: Vec<&str>,
+        #[case] inherited: Vec<&str>,
+        #[case] set_version: Option<Vec<&str>>,
+    ) {
+        let members: Vec<MemberVersion> = members
+            .into_iter()
+            .map(|(name, inherited)| member(name, inherited))
+            .collect();
+        let packages: Vec<String> = packages.into_iter().map(String::from).collect();
+        assert_eq!(
+            plan_bump(&members, &packages),
+            BumpPlan {
+                inherited: inherited.into_iter().map(String::from).collect(),
+                set_version: set_version
+                    .map(|packages| packages.into_iter().map(String::from).collect()),
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::major(BumpSubCommand::Major, "1.2.3", "2.0.0")]
+    #[case::minor(BumpSubCommand::Minor, "0.4.1", "0.5.0")]
+    #[case::patch(BumpSubCommand::Patch, "0.4.1", "0.4.2")]
+    #[case::release_patch(BumpSubCommand::Patch, "1.0.1-alpha.1", "1.0.1")]
+    #[case::release_minor(BumpSubCommand::Minor, "1.1.0-rc.1", "1.1.0")]
+    #[case::prerelease_minor(BumpSubCommand::Minor, "1.1.2-rc.1", "1.2.0")]
+    #[case::build_metadata(BumpSubCommand::Patch, "1.0.0+build.5", "1.0.1")]
+    fn test_bumped_version(
+        #[case] command: BumpSubCommand,
+        #[case] version: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(bumped_version(version, &command).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::caret("0.4.1", Some("0.5.0"))]
+    #[case::exact("=0.4.1", Some("=0.5.0"))]
+    #[case::tilde("~ 0.4", Some("~0.5.0"))]
+    #[case::range(">=0.4, <0.6", None)]
+    fn test_bumped_requirement(#[case] requirement: &str, #[case] expected: Option<&str>) {
+        assert_eq!(
+            bumped_requirement(requirement, "0.5.0").as_deref(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::all_crates(vec![], vec!["set-version", "--bump", "minor"])]
+    #[case::some_crates(
+        vec!["a", "b"],
+        vec!["set-version", "--bump", "minor", "--package", "a", "--package", "b"]
+    )]
+    fn test_set_version_args(#[case] packages: Vec<&str>, #[case] expected: Vec<&str>) {
+        let packages: Vec<String> = packages.into_iter().map(String::from).collect();
+        assert_eq!(
+            set_version_args(&BumpSubCommand::Minor, &packages),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_version_changes() {
+        let output = "   Upgrading my-crate from 0.4.1 to 0.5.0
+    Updating my-app's dependency from 0.4.1 to 0.5.0
+   Upgrading my-macros from 0.4.1 to 0.5.0
+warning: aborting set-version due to dry run
+";
+        assert_eq!(
+            version_changes(output),
+            vec![
+                "my-crate: 0.4.1 -> 0.5.0",
+                "my-app: dependency requirement 0.4.1 -> 0.5.0",
+                "my-macros: 0.4.1 -> 0.5.0",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::same_version(vec![("a", "1.2.3"), ("b", "1.2.3")], Result::Ok("1.2.3"))]
+    #[case::different_versions(
+        vec![("a", "1.2.3"), ("b", "0.4.0")],
+        Err("The bumped crates do not share the version 1.2.3: b 0.4.0, use --package to tag the version of some crates")
+    )]
+    #[case::no_crate(vec![], Err("No bumped crate to tag the version of"))]
+    fn test_release_version(
+        #[case] versions: Vec<(&str, &str)>,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let versions: Vec<(String, String)> = versions
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect();
+        assert_eq!(
+            release_version(&versions).map_err(|e| e.to_string()),
+            expected.map(String::from).map_err(String::from)
+        );
+    }
+
+    #[rstest]
+    fn test_manifest_files() {
+        let files: Vec<String> = [
+            "Cargo.toml",
+            "crates/a/Cargo.toml",
+            "src/lib.rs",
+            "Cargo.lock",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(
+            manifest_files(&files),
+            vec!["Cargo.toml", "crates/a/Cargo.toml", "Cargo.lock"]
+        );
+    }
+
+    #[rstest]
+    #[case::major(BumpSubCommand::Major, "1.2.3", true)]
+    #[case::minor_stable(BumpSubCommand::Minor, "1.2.3", false)]
+    #[case::minor_unstable(BumpSubCommand::Minor, "0.2.3", true)]
+    #[case::patch_unstable(BumpSubCommand::Patch, "0.2.3", false)]
+    #[case::patch_initial(BumpSubCommand::Patch, "0.0.3", true)]
+    #[case::prerelease(BumpSubCommand::Patch, "1.0.0-alpha.1", false)]
+    fn test_is_breaking_bump(
+        #[case] command: BumpSubCommand,
+        #[case] version: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(is_breaking_bump(&command, version), expected);
+    }
+}
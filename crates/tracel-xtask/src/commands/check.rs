@@ -1,15 +1,36 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::Ok;
+use regex::Regex;
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS,
-    endgroup, group,
+    commands::{
+        add_listed_crates, changed_reference, ensure_only_default_members,
+        ensure_typos_is_installed, fmt_args, nightly_fmt_config, publish::check_publish_ready,
+        restrict_to_changed_members, target_member_names, target_members, target_package_args,
+        try_for_each_keep_going, typos_config_args, WARN_IGNORED_EXCLUDE_ARGS,
+    },
+    endgroup, group, group_info,
     utils::{
-        cargo::ensure_cargo_crate_is_installed,
-        process::{run_process, run_process_for_package, run_process_for_workspace},
-        workspace::{get_workspace_members, WorkspaceMemberType},
+        cargo::{ensure_cargo_crate_is_installed, run_cargo_hack},
+        features::unused_features,
+        git::{changed_files, changed_lines, is_repo, ChangedLines},
+        process::{
+            cargo_program, parse_diagnostic, process_options, remove_ansi_codes, run_process,
+            run_process_for_package, run_process_for_workspace, run_process_with_diagnostics,
+            run_process_with_output, run_query_with_output, workspace_package_args, Diagnostic,
+        },
+        sarif::{audit_results, clippy_results, write_sarif, CARGO_AUDIT_TOOL, CLIPPY_TOOL},
+        steps::run_steps,
+        workspace::{
+            get_workspace_members, member_containing, workspace_root, WorkspaceMember,
+            WorkspaceMemberType,
+        },
     },
-    versions::TYPOS_VERSION,
 };
 
 use super::Target;
@@ -17,112 +38,468 @@ use super::Target;
 #[tracel_xtask_macros::declare_command_args(Target, CheckSubCommand)]
 pub struct CheckCmdArgs {}
 
-pub fn handle_command(args: CheckCmdArgs) -> anyhow::Result<()> {
-    if args.target == Target::Workspace && (!args.exclude.is_empty() || !args.only.is_empty()) {
-        warn!("{}", WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS);
+pub fn handle_command(mut args: CheckCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if args.hack
+        && !matches!(
+            args.get_command(),
+            CheckSubCommand::Lint | CheckSubCommand::All
+        )
+    {
+        return Err(anyhow::anyhow!(
+            "--hack is only supported by the lint check, not by '{}'.",
+            args.get_command()
+        ));
+    }
+    // typos checks the changed files rather than the changed members, the lockfile is shared by the
+    // workspace and each command of 'all' restricts its own packages
+    if !matches!(
+        args.get_command(),
+        CheckSubCommand::Typos | CheckSubCommand::Lockfile | CheckSubCommand::All
+    ) && !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
+    }
+    // cargo fmt does not support --exclude
+    if args.target == Target::Workspace
+        && !args.exclude.is_empty()
+        && matches!(
+            args.get_command(),
+            CheckSubCommand::Format | CheckSubCommand::All
+        )
+    {
+        warn!("{}", WARN_IGNORED_EXCLUDE_ARGS);
     }
 
+    let changed = match args.get_command() {
+        CheckSubCommand::Format | CheckSubCommand::Lint => {
+            diff_changed_lines(args.diff.as_deref(), args.all_lines)
+        }
+        _ => None,
+    };
+
     match args.get_command() {
         CheckSubCommand::Audit if args.ignore_audit => {
-            if run_audit().is_err() {
+            if run_audit(&args).is_err() {
                 warn!("Ignoring audit error because of '--ignore-audit' flag.");
             }
             Ok(())
         }
-        CheckSubCommand::Audit => run_audit(),
-        CheckSubCommand::Format => run_format(&args.target, &args.exclude, &args.only),
-        CheckSubCommand::Lint => run_lint(&args.target, &args.exclude, &args.only),
-        CheckSubCommand::Typos => run_typos(),
-        CheckSubCommand::All => CheckSubCommand::iter()
-            .filter(|c| *c != CheckSubCommand::All)
-            .try_for_each(|c| {
+        CheckSubCommand::Audit => run_audit(&args),
+        CheckSubCommand::Format => {
+            let nightly_config = nightly_fmt_config(args.nightly_fmt)?;
+            run_format(
+                &args.target,
+                &args.exclude,
+                &args.only,
+                nightly_config.as_deref(),
+                args.keep_going,
+                changed.as_ref(),
+            )
+        }
+        CheckSubCommand::Lockfile => run_lockfile_check(),
+        CheckSubCommand::PublishReady => run_publish_ready(&args.exclude, &args.only),
+        CheckSubCommand::Lint if changed.is_some() => {
+            run_lint_on_changed_lines(&args, changed.as_ref().unwrap())
+        }
+        CheckSubCommand::Lint if args.hack => run_lint_hack(&args.exclude, &args.only),
+        CheckSubCommand::Lint if args.sarif.is_some() => {
+            run_lint_with_sarif(&args, args.sarif.as_deref().unwrap())
+        }
+        CheckSubCommand::Lint if args.summary => run_lint_with_summary(&args),
+        CheckSubCommand::Lint => run_lint(&args.target, &args.exclude, &args.only, args.keep_going),
+        CheckSubCommand::Typos => run_typos(
+            &typos_config_args(args.typos_config.as_deref(), &args.exclude_paths),
+            changed_reference(args.changed_only, args.changed_since.as_deref()),
+        ),
+        CheckSubCommand::UnusedFeatures => run_unused_features(
+            &args.target,
+            &args.exclude,
+            &args.only,
+            args.deny_unused_features,
+        ),
+        CheckSubCommand::All => run_steps(
+            CheckSubCommand::iter().filter(|c| *c != CheckSubCommand::All),
+            args.keep_going,
+            |c| {
                 handle_command(CheckCmdArgs {
-                    command: Some(c),
+                    command: Some(c.clone()),
                     target: args.target.clone(),
                     exclude: args.exclude.clone(),
                     only: args.only.clone(),
+                    changed_only: args.changed_only,
+                    changed_since: args.changed_since.clone(),
+                    exclude_from: None,
+                    only_from: None,
                     ignore_audit: args.ignore_audit,
+                    deny_warnings: args.deny_warnings,
+                    ignored_advisories: args.ignored_advisories.clone(),
+                    hack: args.hack && *c == CheckSubCommand::Lint,
+                    nightly_fmt: args.nightly_fmt,
+                    keep_going: args.keep_going,
+                    summary: args.summary,
+                    verbose: args.verbose,
+                    sarif: args.sarif.clone(),
+                    typos_config: args.typos_config.clone(),
+                    exclude_paths: args.exclude_paths.clone(),
+                    deny_unused_features: args.deny_unused_features,
+                    diff: args.diff.clone(),
+                    all_lines: args.all_lines,
                 })
-            }),
+            },
+        ),
+    }
+}
+
+/// Returns the lines changed since the `--diff` reference, none when the issues of all the lines are reported.
+/// Falls back to all the lines when the changed lines cannot be determined.
+fn diff_changed_lines(diff: Option<&str>, all_lines: bool) -> Option<ChangedLines> {
+    let reference = diff.filter(|_| !all_lines)?;
+    if !is_repo() {
+        warn!("Not in a git repository, the issues of all the lines are reported.");
+        return None;
+    }
+    changed_lines(reference)
+        .map_err(|e| {
+            warn!(
+                "Cannot determine the changed lines, the issues of all the lines are reported: {}",
+                e
+            )
+        })
+        .ok()
+}
+
+/// Check the manifests of the publishable crates selected by `--exclude` and `--only` before publishing them
+fn run_publish_ready(excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+    group!("Check Publish Ready");
+    let crates = target_member_names(&Target::PublishableCrates, excluded, only)?;
+    if crates.is_empty() {
+        info!("No publishable crate to check.");
+    } else {
+        check_publish_ready(&crates)?;
     }
+    endgroup!();
+    Ok(())
 }
 
-fn run_audit() -> anyhow::Result<()> {
+/// Returns the arguments of cargo audit to report the advisories without fixing them.
+pub(crate) fn audit_args<'a>(
+    base_args: &[&'a str],
+    deny_warnings: bool,
+    ignored_advisories: &'a [String],
+) -> Vec<&'a str> {
+    let mut args = vec!["audit"];
+    args.extend(base_args);
+    if deny_warnings {
+        args.extend(["--deny", "warnings"]);
+    }
+    ignored_advisories
+        .iter()
+        .for_each(|id| args.extend(["--ignore", id]));
+    args
+}
+
+fn run_audit(args: &CheckCmdArgs) -> anyhow::Result<()> {
     group!("Audit Rust Dependencies");
     ensure_cargo_crate_is_installed("cargo-audit", Some("fix"), None, false)?;
-    run_process(
-        "cargo",
-        &["audit", "-q", "--color", "always"],
-        None,
-        None,
-        "Audit check execution failed",
-    )?;
+    match args.sarif.as_deref() {
+        Some(path) => {
+            let output = run_process_with_output(
+                "cargo",
+                &audit_args(&["--json"], args.deny_warnings, &args.ignored_advisories),
+                None,
+                None,
+            )?;
+            write_sarif(
+                path,
+                &CARGO_AUDIT_TOOL,
+                &audit_results(&String::from_utf8_lossy(&output.stdout))?,
+            )?;
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("Audit check execution failed"));
+            }
+        }
+        None => run_process(
+            "cargo",
+            &audit_args(
+                &["-q", "--color", "always"],
+                args.deny_warnings,
+                &args.ignored_advisories,
+            ),
+            None,
+            None,
+            "Audit check execution failed",
+        )?,
+    }
     endgroup!();
     Ok(())
 }
 
-fn run_format(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+fn run_format(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    nightly_config: Option<&str>,
+    keep_going: bool,
+    changed: Option<&ChangedLines>,
+) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Format Workspace");
-            run_process_for_workspace(
-                "cargo",
-                &["fmt", "--check"],
-                &[],
-                None,
-                None,
+            run_format_check(
+                &workspace_package_args(&fmt_args(&["fmt", "--check"], nightly_config), &[], only),
                 "Workspace format failed",
-                None,
-                None,
+                changed,
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
+            try_for_each_keep_going(members, keep_going, |member| {
                 group!("Format: {}", member.name);
-                run_process_for_package(
-                    "cargo",
-                    &member.name,
-                    &["fmt", "--check", "-p", &member.name],
-                    excluded,
-                    only,
-                    &format!("Format check execution failed for {}", &member.name),
-                    None,
-                    None,
-                )?;
+                if excluded.contains(&member.name)
+                    || (!only.is_empty() && !only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                } else {
+                    run_format_check(
+                        &fmt_args(&["fmt", "--check", "-p", &member.name], nightly_config),
+                        &format!("Format check execution failed for {}", &member.name),
+                        changed,
+                    )?;
+                }
                 endgroup!();
-            }
+                Ok(())
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_format(&t, excluded, only))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                keep_going,
+                |t| run_format(&t, excluded, only, nightly_config, keep_going, changed),
+            )?;
         }
     }
     Ok(())
 }
 
-fn run_lint(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+/// Diff of a file which is not formatted, as printed by `cargo fmt --check`
+#[derive(Debug, PartialEq)]
+struct FormatDiff {
+    file: PathBuf,
+    lines: Vec<String>,
+}
+
+/// Run `cargo fmt --check` and print the diffs of the files to format in a group per crate
+/// along with the number of files to format. With changed lines only the hunks touching them are reported.
+fn run_format_check(
+    cmd_args: &[&str],
+    error_msg: &str,
+    changed: Option<&ChangedLines>,
+) -> anyhow::Result<()> {
+    let output = run_process_with_output("cargo", cmd_args, None, None)?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let diffs = parse_format_diffs(&String::from_utf8_lossy(&output.stdout));
+    // no diff means that cargo fmt itself failed, its error is on the inherited standard error
+    if diffs.is_empty() {
+        return Err(anyhow::anyhow!("{}", error_msg));
+    }
+    let diffs = match changed {
+        Some(changed) => {
+            let diffs = diffs_in_changed_lines(diffs, changed);
+            if diffs.is_empty() {
+                group_info!(
+                    "The formatting issues are outside of the lines changed since {}.",
+                    changed.reference
+                );
+                return Ok(());
+            }
+            diffs
+        }
+        None => diffs,
+    };
+    // groups cannot be nested on CI
+    endgroup!();
+    let mut members = get_workspace_members(WorkspaceMemberType::Crate)?;
+    members.extend(get_workspace_members(WorkspaceMemberType::Example)?);
+    let diffs_per_member = group_diffs_by_member(&diffs, &members);
+    let colored = process_options().color.is_enabled();
+    for (member, diffs) in &diffs_per_member {
+        group!(
+            "Format diff: {} ({} file(s) to format)",
+            member,
+            diffs.len()
+        );
+        diffs
+            .iter()
+            .flat_map(|diff| &diff.lines)
+            .for_each(|line| println!("{}", colorize_diff_line(line, colored)));
+        endgroup!();
+    }
+    Err(anyhow::anyhow!(
+        "{}: {} file(s) to format in {} crate(s), run `cargo xtask fix format` to fix them",
+        error_msg,
+        diffs.len(),
+        diffs_per_member.len()
+    ))
+}
+
+/// Header of the hunks printed by `cargo fmt --check`, the line is the first line of the hunk in the file
+const FORMAT_HUNK_HEADER: &str = r"^Diff in (.+?)(?::(\d+):| at line (\d+):)$";
+
+/// Parse the output of `cargo fmt --check`, every hunk starts with a `Diff in <file>:<line>:` header.
+/// The hunks of a file are merged into a single diff.
+fn parse_format_diffs(output: &str) -> Vec<FormatDiff> {
+    let header_rx = Regex::new(FORMAT_HUNK_HEADER).unwrap();
+    let mut diffs: Vec<FormatDiff> = vec![];
+    let mut current: Option<usize> = None;
+    // rustfmt may color the diff, the lines are colored again when printed
+    for line in remove_ansi_codes(output).lines() {
+        if let Some(captures) = header_rx.captures(line) {
+            let file = PathBuf::from(&captures[1]);
+            let index = match diffs.iter().position(|diff| diff.file == file) {
+                Some(index) => index,
+                None => {
+                    diffs.push(FormatDiff {
+                        file,
+                        lines: vec![],
+                    });
+                    diffs.len() - 1
+                }
+            };
+            current = Some(index);
+        }
+        if let Some(index) = current {
+            diffs[index].lines.push(line.to_string());
+        }
+    }
+    diffs
+}
+
+/// Returns the diffs with only their hunks touching the changed lines, the context and removed lines
+/// of a hunk are the lines of the file it covers. The diffs without such hunks are dropped.
+fn diffs_in_changed_lines(diffs: Vec<FormatDiff>, changed: &ChangedLines) -> Vec<FormatDiff> {
+    let header_rx = Regex::new(FORMAT_HUNK_HEADER).unwrap();
+    diffs
+        .into_iter()
+        .filter_map(|diff| {
+            let mut hunks: Vec<(u64, Vec<String>)> = vec![];
+            for line in diff.lines {
+                match header_rx.captures(&line) {
+                    Some(captures) => {
+                        let start = captures
+                            .get(2)
+                            .or(captures.get(3))
+                            .and_then(|start| start.as_str().parse().ok())
+                            .unwrap_or(1);
+                        hunks.push((start, vec![line]));
+                    }
+                    None => {
+                        if let Some((_, lines)) = hunks.last_mut() {
+                            lines.push(line);
+                        }
+                    }
+                }
+            }
+            let lines: Vec<String> = hunks
+                .into_iter()
+                .filter(|(start, lines)| {
+                    let covered = lines
+                        .iter()
+                        .filter(|line| line.starts_with([' ', '-']))
+                        .count() as u64;
+                    changed.contains(&diff.file, *start, start + covered.max(1) - 1)
+                })
+                .flat_map(|(_, lines)| lines)
+                .collect();
+            (!lines.is_empty()).then_some(FormatDiff {
+                file: diff.file,
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Returns the diffs grouped by the name of the member containing their file, the files outside of the
+/// members are grouped under `workspace`.
+fn group_diffs_by_member<'a>(
+    diffs: &'a [FormatDiff],
+    members: &[WorkspaceMember],
+) -> BTreeMap<String, Vec<&'a FormatDiff>> {
+    let mut groups: BTreeMap<String, Vec<&FormatDiff>> = BTreeMap::new();
+    for diff in diffs {
+        let member = member_containing(&diff.file, members)
+            .map(|member| member.name.clone())
+            .unwrap_or_else(|| "workspace".to_string());
+        groups.entry(member).or_default().push(diff);
+    }
+    groups
+}
+
+/// Color the added lines of a diff in green, the removed lines in red and the headers in bold
+fn colorize_diff_line(line: &str, colored: bool) -> String {
+    let color = match line.chars().next() {
+        _ if !colored => None,
+        Some('+') => Some("32"),
+        Some('-') => Some("31"),
+        Some('D') if line.starts_with("Diff in ") => Some("1"),
+        _ => None,
+    };
+    match color {
+        Some(color) => format!("\x1b[{color}m{line}\x1b[0m"),
+        None => line.to_string(),
+    }
+}
+
+/// Returns the clippy arguments to lint the workspace
+fn lint_workspace_args(keep_going: bool) -> Vec<&'static str> {
+    let mut cmd_args = vec!["clippy", "--workspace", "--no-deps", "--color=always"];
+    if keep_going {
+        cmd_args.push("--keep-going");
+    }
+    cmd_args.extend(["--", "--deny", "warnings"]);
+    cmd_args
+}
+
+fn run_lint(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    keep_going: bool,
+) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Lint Workspace");
             run_process_for_workspace(
                 "cargo",
-                &[
-                    "clippy",
-                    "--no-deps",
-                    "--color=always",
-                    "--",
-                    "--deny",
-                    "warnings",
-                ],
-                &[],
+                &lint_workspace_args(keep_going),
+                excluded,
+                only,
                 None,
                 None,
                 "Workspace lint failed",
@@ -131,14 +508,22 @@ fn run_lint(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Re
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
+            try_for_each_keep_going(members, keep_going, |member| {
                 group!("Lint: {}", member.name);
                 run_process_for_package(
                     "cargo",
@@ -160,25 +545,269 @@ fn run_lint(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Re
                     None,
                 )?;
                 endgroup!();
-            }
+                Ok(())
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_lint(&t, excluded, only))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                keep_going,
+                |t| run_lint(&t, excluded, only, keep_going),
+            )?;
         }
     }
     Ok(())
 }
 
-fn run_typos() -> anyhow::Result<()> {
+/// Lint all the packages of the target in one cargo invocation and report only the lints of the changed lines.
+/// The other errors, such as the compilation errors, are always reported.
+fn run_lint_on_changed_lines(args: &CheckCmdArgs, changed: &ChangedLines) -> anyhow::Result<()> {
+    group!(
+        "Lint: {} (lines changed since {})",
+        args.target,
+        changed.reference
+    );
+    let mut cmd_args = vec!["clippy".to_string(), "--no-deps".to_string()];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if args.keep_going {
+        cmd_args.push("--keep-going".to_string());
+    }
+    // the lints are not denied so that the packages depending on a crate with lint warnings are linted too
+    cmd_args.push("--message-format=json".to_string());
+    let output = run_process_with_output(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    let diagnostics: Vec<Diagnostic> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_diagnostic)
+        .collect();
+    // no diagnostic means that cargo itself failed, its error is on the inherited standard error
+    if !output.status.success() && diagnostics.is_empty() {
+        return Err(anyhow::anyhow!("Lint failed"));
+    }
+    let (reported, skipped) = diagnostics_in_changed_lines(diagnostics, changed, &workspace_root());
+    reported.iter().for_each(|d| print!("{}", d.rendered));
+    if skipped > 0 {
+        group_info!(
+            "{} lint(s) outside of the lines changed since {} are not reported.",
+            skipped,
+            changed.reference
+        );
+    }
+    if !reported.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Lint failed: {} issue(s) in the lines changed since {}",
+            reported.len(),
+            changed.reference
+        ));
+    }
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the diagnostics to report along with the number of skipped lints: the lints are reported
+/// when their primary span touches the changed lines, the other diagnostics are always reported.
+fn diagnostics_in_changed_lines(
+    diagnostics: Vec<Diagnostic>,
+    changed: &ChangedLines,
+    root: &Path,
+) -> (Vec<Diagnostic>, usize) {
+    let (reported, skipped): (Vec<Diagnostic>, Vec<Diagnostic>) =
+        diagnostics.into_iter().partition(|d| {
+            // the compiler errors have an error code such as E0308, the lints have a name
+            let is_lint = d.code.as_deref().is_some_and(|code| {
+                !(code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit()))
+            });
+            !is_lint
+                || d.location.as_ref().is_some_and(|location| {
+                    changed.contains(&root.join(&location.file), location.line, location.end_line)
+                })
+        });
+    (reported, skipped.len())
+}
+
+/// Lint all the packages of the target in one cargo invocation and summarize the diagnostics.
+fn run_lint_with_summary(args: &CheckCmdArgs) -> anyhow::Result<()> {
+    group!("Lint: {}", args.target);
+    let mut cmd_args = vec!["clippy".to_string(), "--no-deps".to_string()];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if args.keep_going {
+        cmd_args.push("--keep-going".to_string());
+    }
+    cmd_args.extend(["--", "--deny", "warnings"].map(String::from));
+    run_process_with_diagnostics(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        args.verbose,
+        "Lint failed",
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Lint all the packages of the target in one cargo invocation and write the findings to a SARIF file.
+fn run_lint_with_sarif(args: &CheckCmdArgs, path: &Path) -> anyhow::Result<()> {
+    group!("Lint: {}", args.target);
+    let mut cmd_args = vec!["clippy".to_string(), "--no-deps".to_string()];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if args.keep_going {
+        cmd_args.push("--keep-going".to_string());
+    }
+    cmd_args.extend(["--message-format=json", "--", "--deny", "warnings"].map(String::from));
+    let output = run_process_with_output(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    let diagnostics: Vec<Diagnostic> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_diagnostic)
+        .collect();
+    diagnostics.iter().for_each(|d| print!("{}", d.rendered));
+    write_sarif(path, &CLIPPY_TOOL, &clippy_results(&diagnostics))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Lint failed"));
+    }
+    endgroup!();
+    Ok(())
+}
+
+fn run_lint_hack(excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+    group!("Lint feature powerset");
+    run_cargo_hack(
+        "clippy",
+        &["--no-deps", "--color=always", "--", "--deny", "warnings"],
+        excluded,
+        only,
+        "Lint failed for some feature sets",
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the arguments of typos to report the typos without fixing them.
+/// typos exits with an error when some are found.
+pub(crate) fn typos_args(config_args: &[String]) -> Vec<String> {
+    let mut args = vec!["--color".to_string(), "always".to_string()];
+    args.extend_from_slice(config_args);
+    args
+}
+
+/// Report the unused features of the selected members and fail with `--deny-unused-features`.
+fn run_unused_features(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    group!("Check Unused Features");
+    let mut crates_with_unused = 0;
+    for member in target_members(target, excluded, only)? {
+        let unused = unused_features(Path::new(&member.path))?;
+        if !unused.is_empty() {
+            crates_with_unused += 1;
+            warn!("{}: {}", member.name, unused.join(", "));
+        }
+    }
+    endgroup!();
+    if crates_with_unused == 0 {
+        info!("No unused feature found.");
+    } else if deny {
+        return Err(anyhow::anyhow!(
+            "Unused features found in {} crate(s).",
+            crates_with_unused
+        ));
+    }
+    Ok(())
+}
+
+/// Verify that `cargo metadata --locked` does not need to update the committed Cargo.lock.
+/// The check is skipped when the workspace has no lockfile committed in git.
+fn run_lockfile_check() -> anyhow::Result<()> {
+    group!("Check Lockfile");
+    let root = workspace_root();
+    if !root.join("Cargo.lock").is_file() || !is_tracked(&root, "Cargo.lock") {
+        info!("No committed Cargo.lock, skipping the lockfile check.");
+        endgroup!();
+        return Ok(());
+    }
+    let output = run_process_with_output(
+        &cargo_program(),
+        &["metadata", "--locked", "--format-version", "1"],
+        None,
+        Some(&root),
+    )?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Cargo.lock is out of date, run a cargo command to update it and commit the updated Cargo.lock."
+        ));
+    }
+    info!("Cargo.lock is up to date.");
+    endgroup!();
+    Ok(())
+}
+
+/// Returns true if the passed file of the directory is tracked by git
+fn is_tracked(dir: &Path, file: &str) -> bool {
+    is_repo()
+        && run_query_with_output("git", &["ls-files", file], Some(dir))
+            .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
+
+/// Check the typos of the whole tree, or only of the files changed since the passed git reference.
+/// Falls back to the whole tree when the changed files cannot be determined.
+fn run_typos(config_args: &[String], changed_since: Option<&str>) -> anyhow::Result<()> {
     if std::env::var("CI").is_err() {
-        ensure_cargo_crate_is_installed("typos-cli", None, Some(TYPOS_VERSION), false)?;
+        ensure_typos_is_installed()?;
     }
     group!("Typos");
+    let mut cmd_args = typos_args(config_args);
+    let changed = changed_since.and_then(|reference| {
+        changed_files(reference)
+            .map(|files| (reference, files))
+            .map_err(|e| {
+                warn!(
+                    "Cannot determine the changed files, checking all of them: {}",
+                    e
+                )
+            })
+            .ok()
+    });
+    if let Some((reference, files)) = changed {
+        // deleted files are reported as changed as well
+        let files: Vec<String> = files
+            .into_iter()
+            .filter(|file| file.is_file())
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect();
+        if files.is_empty() {
+            info!("No file changed since {}.", reference);
+            endgroup!();
+            return Ok(());
+        }
+        // apply the exclusions of the typos config to the explicitly passed files
+        cmd_args.push("--force-exclude".to_string());
+        cmd_args.extend(files);
+    }
     run_process(
         "typos",
-        &["--diff", "--color", "always"],
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
         None,
         None,
         "Typos check execution failed",
@@ -186,3 +815,171 @@ fn run_typos() -> anyhow::Result<()> {
     endgroup!();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{git::parse_changed_lines, process::Location};
+    use rstest::rstest;
+
+    const FMT_OUTPUT: &str = "Diff in /ws/crates/foo/src/lib.rs:3:
+ fn main() {
+-    let x=1;
++    let x = 1;
+ }
+Diff in /ws/crates/foo-derive/src/lib.rs at line 1:
+-use std::fmt;use std::io;
++use std::fmt;
++use std::io;
+Diff in /ws/crates/foo/src/lib.rs:10:
+-fn a(){}
++fn a() {}
+Diff in /ws/build.rs:1:
+-fn main(){}
++fn main() {}
+";
+
+    #[rstest]
+    fn test_parse_format_diffs() {
+        let diffs = parse_format_diffs(FMT_OUTPUT);
+        let files: Vec<&Path> = diffs.iter().map(|diff| diff.file.as_path()).collect();
+        assert_eq!(
+            files,
+            vec![
+                Path::new("/ws/crates/foo/src/lib.rs"),
+                Path::new("/ws/crates/foo-derive/src/lib.rs"),
+                Path::new("/ws/build.rs"),
+            ]
+        );
+        assert_eq!(diffs[0].lines.len(), 8);
+        assert_eq!(diffs[0].lines[5], "Diff in /ws/crates/foo/src/lib.rs:10:");
+        assert!(parse_format_diffs("").is_empty());
+        let colored = parse_format_diffs("Diff in /ws/lib.rs:1:\n\x1b[31m-fn a(){}\n\x1b(B\x1b[m");
+        assert_eq!(colored[0].lines, vec!["Diff in /ws/lib.rs:1:", "-fn a(){}"]);
+    }
+
+    /// Lines 3 of foo/src/lib.rs and 1 to 2 of build.rs are changed
+    const GIT_DIFF: &str = "--- a/crates/foo/src/lib.rs
++++ b/crates/foo/src/lib.rs
+@@ -4 +4 @@
+-    let x=2;
++    let x=1;
+--- a/build.rs
++++ b/build.rs
+@@ -0,0 +1,2 @@
++fn main(){}
++
+";
+
+    #[rstest]
+    fn test_diffs_in_changed_lines() {
+        let changed = parse_changed_lines(Path::new("/ws"), GIT_DIFF);
+        let diffs = diffs_in_changed_lines(parse_format_diffs(FMT_OUTPUT), &changed);
+        let lines: Vec<Vec<&str>> = diffs
+            .iter()
+            .map(|diff| diff.lines.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec![
+                    "Diff in /ws/crates/foo/src/lib.rs:3:",
+                    " fn main() {",
+                    "-    let x=1;",
+                    "+    let x = 1;",
+                    " }",
+                ],
+                vec!["Diff in /ws/build.rs:1:", "-fn main(){}", "+fn main() {}"],
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_diagnostics_in_changed_lines() {
+        let changed = parse_changed_lines(Path::new("/ws"), GIT_DIFF);
+        let diagnostic = |code: Option<&str>, file: &str, line: u64| Diagnostic {
+            crate_name: "foo".to_string(),
+            level: "warning".to_string(),
+            message: format!("{code:?} at {line}"),
+            code: code.map(String::from),
+            location: Some(Location {
+                file: file.to_string(),
+                line,
+                end_line: line,
+                column: None,
+            }),
+            rendered: String::new(),
+        };
+        let (reported, skipped) = diagnostics_in_changed_lines(
+            vec![
+                diagnostic(Some("clippy::needless_return"), "crates/foo/src/lib.rs", 4),
+                diagnostic(Some("unused_variables"), "crates/foo/src/lib.rs", 8),
+                diagnostic(Some("E0308"), "crates/foo/src/lib.rs", 8),
+                diagnostic(None, "crates/foo/src/main.rs", 1),
+            ],
+            &changed,
+            Path::new("/ws"),
+        );
+        let messages: Vec<&str> = reported.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Some(\"clippy::needless_return\") at 4",
+                "Some(\"E0308\") at 8",
+                "None at 1",
+            ]
+        );
+        assert_eq!(skipped, 1);
+    }
+
+    #[rstest]
+    fn test_group_diffs_by_member() {
+        let diffs = parse_format_diffs(FMT_OUTPUT);
+        let members = vec![
+            WorkspaceMember {
+                name: "foo".to_string(),
+                path: "/ws/crates/foo".to_string(),
+            },
+            WorkspaceMember {
+                name: "foo-derive".to_string(),
+                path: "/ws/crates/foo-derive".to_string(),
+            },
+        ];
+        let counts: Vec<(String, usize)> = group_diffs_by_member(&diffs, &members)
+            .into_iter()
+            .map(|(member, diffs)| (member, diffs.len()))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("foo".to_string(), 1),
+                ("foo-derive".to_string(), 1),
+                ("workspace".to_string(), 1),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::added("+    let x = 1;", true, "\x1b[32m+    let x = 1;\x1b[0m")]
+    #[case::removed("-    let x=1;", true, "\x1b[31m-    let x=1;\x1b[0m")]
+    #[case::header("Diff in /ws/lib.rs:3:", true, "\x1b[1mDiff in /ws/lib.rs:3:\x1b[0m")]
+    #[case::context(" fn main() {", true, " fn main() {")]
+    #[case::no_color("+    let x = 1;", false, "+    let x = 1;")]
+    fn test_colorize_diff_line(#[case] line: &str, #[case] colored: bool, #[case] expected: &str) {
+        assert_eq!(colorize_diff_line(line, colored), expected);
+    }
+
+    #[rstest]
+    #[case::fail_fast(false, vec!["clippy", "--workspace", "--no-deps", "--color=always", "--exclude", "a", "--", "--deny", "warnings"])]
+    #[case::keep_going(true, vec!["clippy", "--workspace", "--no-deps", "--color=always", "--keep-going", "--exclude", "a", "--", "--deny", "warnings"])]
+    fn test_lint_workspace_args_forward_excludes(
+        #[case] keep_going: bool,
+        #[case] expected: Vec<&str>,
+    ) {
+        let excluded = vec!["a".to_string()];
+        assert_eq!(
+            workspace_package_args(&lint_workspace_args(keep_going), &excluded, &[]),
+            expected
+        );
+    }
+}
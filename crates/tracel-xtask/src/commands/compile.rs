@@ -2,10 +2,16 @@ use anyhow::Ok;
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_ONLY_ARGS,
+    commands::{
+        add_listed_crates, ensure_only_default_members, profile_args, report_timings,
+        restrict_to_changed_members, target_package_args, timings_args, try_for_each_keep_going,
+    },
     endgroup, group,
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
+        cargo::run_cargo_hack,
+        process::{
+            run_process_for_package, run_process_for_workspace, run_process_with_diagnostics,
+        },
         workspace::{get_workspace_members, WorkspaceMemberType},
     },
 };
@@ -15,25 +21,100 @@ use super::Target;
 #[tracel_xtask_macros::declare_command_args(Target, None)]
 pub struct CompileCmdArgs {}
 
-pub fn handle_command(args: CompileCmdArgs) -> anyhow::Result<()> {
-    if args.target == Target::Workspace && !args.only.is_empty() {
-        warn!("{}", WARN_IGNORED_ONLY_ARGS);
+pub fn handle_command(mut args: CompileCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
+    }
+    let profile = profile_args(args.release, args.profile.as_deref());
+    if args.hack {
+        return run_compile_hack(&args.exclude, &args.only, &profile);
+    }
+    let compile_args = timings_args(&profile, args.timings);
+    if args.summary {
+        run_compile_with_summary(&args, &compile_args)?;
+    } else {
+        run_compile(
+            &args.target,
+            &args.exclude,
+            &args.only,
+            args.keep_going,
+            &compile_args,
+        )?;
+    }
+    if args.timings {
+        report_timings(args.open)?;
     }
-    run_compile(&args.target, &args.exclude, &args.only)
+    Ok(())
+}
+
+fn run_compile_hack(excluded: &[String], only: &[String], profile: &[&str]) -> anyhow::Result<()> {
+    group!("Compile feature powerset");
+    run_cargo_hack(
+        "check",
+        profile,
+        excluded,
+        only,
+        "Compilation failed for some feature sets",
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Compile all the packages of the target in one cargo invocation and summarize the diagnostics.
+fn run_compile_with_summary(args: &CompileCmdArgs, profile: &[&str]) -> anyhow::Result<()> {
+    group!("Compile: {}", args.target);
+    let mut cmd_args = vec!["check".to_string()];
+    cmd_args.extend(target_package_args(
+        &args.target,
+        &args.exclude,
+        &args.only,
+    )?);
+    if args.keep_going {
+        cmd_args.push("--keep-going".to_string());
+    }
+    cmd_args.extend(profile.iter().map(|s| s.to_string()));
+    run_process_with_diagnostics(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        args.verbose,
+        "Compilation failed",
+    )?;
+    endgroup!();
+    Ok(())
 }
 
 pub(crate) fn run_compile(
     target: &Target,
     excluded: &Vec<String>,
     only: &Vec<String>,
+    keep_going: bool,
+    profile: &[&str],
 ) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Compile Workspace");
+            let mut cmd_args = vec!["check", "--workspace"];
+            if keep_going {
+                cmd_args.push("--keep-going");
+            }
+            cmd_args.extend(profile);
             run_process_for_workspace(
                 "cargo",
-                &["check", "--workspace"],
+                &cmd_args,
                 excluded,
+                only,
                 None,
                 None,
                 "Workspace compilation failed",
@@ -42,19 +123,29 @@ pub(crate) fn run_compile(
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
+            try_for_each_keep_going(members, keep_going, |member| {
                 group!("Compile: {}", member.name);
+                let mut cmd_args = vec!["check", "-p", &member.name];
+                cmd_args.extend(profile);
                 run_process_for_package(
                     "cargo",
                     &member.name,
-                    &["check", "-p", &member.name],
+                    &cmd_args,
                     excluded,
                     only,
                     &format!("Compilation failed for {}", &member.name),
@@ -62,12 +153,15 @@ pub(crate) fn run_compile(
                     None,
                 )?;
                 endgroup!();
-            }
+                Ok(())
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_compile(&t, excluded, only))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                keep_going,
+                |t| run_compile(&t, excluded, only, keep_going, profile),
+            )?;
         }
     }
     Ok(())
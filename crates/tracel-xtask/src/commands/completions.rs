@@ -0,0 +1,65 @@
+use std::io::Write;
+
+use crate::{
+    group_info,
+    utils::{explain::explain_write, outcome::record_artifact, workspace::invocation_path},
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct CompletionsCmdArgs {}
+
+pub fn handle_command(args: CompletionsCmdArgs, mut command: clap::Command) -> anyhow::Result<()> {
+    let script = generate_completions(args.shell, &mut command, &args.bin_name);
+    match args.output.as_deref().map(invocation_path) {
+        Some(path) if explain_write(&format!("write {}", path.display())) => {}
+        Some(path) => {
+            std::fs::write(&path, script).map_err(|e| {
+                anyhow::anyhow!("Failed to write completions to {}: {}", path.display(), e)
+            })?;
+            group_info!("Completions written to {}", path.display());
+            record_artifact(&path);
+        }
+        None => std::io::stdout().write_all(&script)?,
+    }
+    Ok(())
+}
+
+/// Generate the completion script of the command tree for the given shell
+fn generate_completions(
+    shell: clap_complete::Shell,
+    command: &mut clap::Command,
+    bin_name: &str,
+) -> Vec<u8> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, command, bin_name, &mut script);
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xtask_command, NoExtraArgs};
+    use clap_complete::Shell;
+    use rstest::rstest;
+
+    #[derive(clap::Subcommand)]
+    enum TestCommand {
+        /// Build the code.
+        Build,
+        /// Run the tests.
+        Test,
+    }
+
+    #[rstest]
+    #[case::bash(Shell::Bash)]
+    #[case::zsh(Shell::Zsh)]
+    #[case::fish(Shell::Fish)]
+    #[case::powershell(Shell::PowerShell)]
+    fn test_generate_completions(#[case] shell: Shell) {
+        let mut command = xtask_command::<TestCommand, NoExtraArgs>();
+        let script = String::from_utf8(generate_completions(shell, &mut command, "xtask")).unwrap();
+        assert!(script.contains("xtask"));
+        assert!(script.contains("build"));
+        assert!(script.contains("env-file"));
+    }
+}
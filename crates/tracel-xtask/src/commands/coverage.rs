@@ -1,12 +1,26 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::Ok;
 use clap::Args;
 
 use crate::{
     endgroup, group,
     utils::{
-        cargo::ensure_cargo_crate_is_installed, process::run_process, rustup::rustup_add_component,
+        cargo::ensure_cargo_crate_is_installed,
+        config::XtaskConfig,
+        explain::explain_write,
+        outcome::record_artifact,
+        process::run_process,
+        rustup::rustup_add_component,
+        workspace::{
+            get_workspace_members, member_containing, target_dir, workspace_root, WorkspaceMember,
+            WorkspaceMemberType,
+        },
     },
-    versions::GRCOV_VERSION,
+    versions::{CARGO_LLVM_COV_VERSION, GRCOV_VERSION},
 };
 
 use super::Profile;
@@ -34,16 +48,91 @@ pub struct GenerateCmdArgs {
         required = false
     )]
     pub ignore: Vec<String>,
+    /// Minimum line coverage percentage of every crate, the command fails if a crate is below it.
+    /// The [coverage] section of xtask.toml can set it along with per crate minimums.
+    #[arg(long, value_name = "PCT", required = false)]
+    pub min_coverage: Option<f64>,
+    /// Coverage tool: grcov reports the .profraw files of the previous instrumented runs, cargo-llvm-cov
+    /// runs the tests of the workspace with the instrumentation and reports them in one step.
+    #[arg(long, value_name = "BACKEND", value_parser = ["grcov", "llvm-cov"], default_value = "grcov")]
+    pub backend: String,
+    /// Write the lcov.info report, this is the default.
+    #[arg(long, conflicts_with = "html")]
+    pub lcov: bool,
+    /// Write the html report to target/coverage/html instead of the lcov.info report.
+    #[arg(long)]
+    pub html: bool,
+    /// Minimum line coverage percentage of all the crates together, the command fails below it.
+    #[arg(long, value_name = "PCT", required = false)]
+    pub fail_under: Option<f64>,
+}
+
+impl GenerateCmdArgs {
+    fn format(&self) -> &'static str {
+        if self.html {
+            "html"
+        } else {
+            "lcov"
+        }
+    }
+}
+
+#[derive(Args, Default, Clone, PartialEq)]
+pub struct MergeCmdArgs {
+    /// Build profile to use.
+    #[arg(short, long, value_enum, default_value_t = Profile::default())]
+    profile: Profile,
+    /// Comma-separated list of excluded crates.
+    #[arg(
+        short = 'i',
+        long,
+        value_name = "PATH,PATH,...",
+        value_delimiter = ',',
+        required = false
+    )]
+    pub ignore: Vec<String>,
+    /// Minimum line coverage percentage of every crate, the command fails if a crate is below it.
+    /// The [coverage] section of xtask.toml can set it along with per crate minimums.
+    #[arg(long, value_name = "PCT", required = false)]
+    pub min_coverage: Option<f64>,
+    /// Format of the merged report.
+    #[arg(long, value_name = "FORMAT", value_parser = ["lcov", "html"], default_value = "lcov")]
+    pub format: String,
+}
+
+/// Environment variables instrumenting the compiled code for coverage, the profiles of all the runs
+/// are written to the profraw directory so that they can be merged.
+pub(crate) fn coverage_env_vars() -> [(&'static str, String); 2] {
+    [
+        ("RUSTFLAGS", "-Cinstrument-coverage".to_string()),
+        (
+            "LLVM_PROFILE_FILE",
+            profraw_dir()
+                .join("burn-%p-%m.profraw")
+                .to_string_lossy()
+                .into_owned(),
+        ),
+    ]
+}
+
+/// Returns the directory where the instrumented binaries write their `.profraw` files
+pub(crate) fn profraw_dir() -> PathBuf {
+    target_dir().join("coverage").join("profraw")
 }
 
 pub fn handle_command(args: CoverageCmdArgs) -> anyhow::Result<()> {
     match args.get_command() {
         CoverageSubCommand::Install => install_grcov(),
+        CoverageSubCommand::Generate(gen_args) if gen_args.backend == "llvm-cov" => {
+            run_llvm_cov(&gen_args)
+        }
         CoverageSubCommand::Generate(gen_args) => run_grcov(&gen_args),
+        CoverageSubCommand::Merge(merge_args) => run_merge(&merge_args),
+        CoverageSubCommand::Clean => run_clean(),
     }
 }
 
-fn install_grcov() -> anyhow::Result<()> {
+pub(crate) fn install_grcov() -> anyhow::Result<()> {
     rustup_add_component("llvm-tools-preview")?;
     if std::env::var("CI").is_err() {
         ensure_cargo_crate_is_installed("grcov", None, Some(GRCOV_VERSION), false)?;
@@ -51,24 +140,658 @@ fn install_grcov() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Run the passed function with the coverage instrumentation enabled for the spawned processes,
+/// the previous values of the environment variables are restored afterwards.
+pub(crate) fn with_coverage_env<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let env_vars = coverage_env_vars();
+    let previous: Vec<_> = env_vars
+        .iter()
+        .map(|(key, _)| (*key, std::env::var_os(key)))
+        .collect();
+    env_vars
+        .iter()
+        .for_each(|(key, value)| unsafe { std::env::set_var(key, value) });
+    let result = f();
+    previous.into_iter().for_each(|(key, value)| unsafe {
+        match value {
+            Some(value) => std::env::set_var(key, value),
+            None => std::env::remove_var(key),
+        }
+    });
+    result
+}
+
 fn run_grcov(generate_args: &GenerateCmdArgs) -> anyhow::Result<()> {
+    let format = generate_args.format();
+    let mut thresholds = CoverageThresholds::load(generate_args.min_coverage)?;
+    thresholds.total = generate_args.fail_under;
+    thresholds.ensure_lcov(format)?;
+    generate_coverage_report(
+        Path::new("."),
+        &generate_args.profile.to_string(),
+        &generate_args.ignore,
+        format,
+    )?;
+    thresholds.check(Path::new(grcov_output(format)))
+}
+
+/// Run the tests of the workspace with cargo-llvm-cov which instruments them and writes the report,
+/// the minimum total coverage is checked by cargo-llvm-cov itself.
+fn run_llvm_cov(generate_args: &GenerateCmdArgs) -> anyhow::Result<()> {
+    let format = generate_args.format();
+    let thresholds = CoverageThresholds::load(generate_args.min_coverage)?;
+    thresholds.ensure_lcov(format)?;
+    rustup_add_component("llvm-tools-preview")?;
+    ensure_cargo_crate_is_installed("cargo-llvm-cov", None, Some(CARGO_LLVM_COV_VERSION), false)?;
+    group!("Cargo llvm-cov");
+    let args = llvm_cov_args(
+        &generate_args.profile,
+        &generate_args.ignore,
+        format,
+        generate_args.fail_under,
+    );
+    run_process(
+        "cargo",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+        "Error executing cargo llvm-cov",
+    )?;
+    info!("Coverage report written to {}", grcov_output(format));
+    record_artifact(Path::new(grcov_output(format)));
+    endgroup!();
+    thresholds.check(Path::new(grcov_output(format)))
+}
+
+/// Returns the arguments of cargo llvm-cov writing the report at the same path as grcov
+fn llvm_cov_args(
+    profile: &Profile,
+    ignore: &[String],
+    format: &str,
+    fail_under: Option<f64>,
+) -> Vec<String> {
+    let mut args: Vec<String> = ["llvm-cov", "--workspace"].map(String::from).to_vec();
+    if *profile == Profile::Release {
+        args.push("--release".to_string());
+    }
+    match format {
+        // cargo llvm-cov writes the html report to the html directory of the output directory
+        "html" => args.extend(["--html", "--output-dir", "target/coverage"].map(String::from)),
+        _ => args.extend(["--lcov", "--output-path", grcov_output(format)].map(String::from)),
+    }
+    if let Some(fail_under) = fail_under {
+        args.extend(["--fail-under-lines".to_string(), fail_under.to_string()]);
+    }
+    if !ignore.is_empty() {
+        let regex = ignore
+            .iter()
+            .map(|glob| glob_to_regex(glob))
+            .collect::<Vec<String>>()
+            .join("|");
+        args.extend(["--ignore-filename-regex".to_string(), regex]);
+    }
+    args
+}
+
+/// Returns the regex matching the paths matched by the passed glob of the grcov `--ignore` option
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c if "\\.+()|[]{}^$".contains(c) => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            c => regex.push(c),
+        }
+    }
+    // the globs of grcov are matched against the relative paths of the sources, the regex of
+    // cargo llvm-cov against the absolute paths
+    match regex.strip_prefix('/') {
+        Some(absolute) => format!("^/{absolute}"),
+        None => format!("(^|/){regex}"),
+    }
+}
+
+/// Generate a single report from the `.profraw` files of all the runs, grcov merges them.
+fn run_merge(merge_args: &MergeCmdArgs) -> anyhow::Result<()> {
+    let dir = profraw_dir();
+    let files = profraw_files(&dir)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No .profraw file found in {}, run the tests with coverage enabled first.",
+            dir.display()
+        ));
+    }
+    let thresholds = CoverageThresholds::load(merge_args.min_coverage)?;
+    thresholds.ensure_lcov(&merge_args.format)?;
+    info!(
+        "Merging {} .profraw file(s) of {}",
+        files.len(),
+        dir.display()
+    );
+    generate_coverage_report(
+        &dir,
+        &merge_args.profile.to_string(),
+        &merge_args.ignore,
+        &merge_args.format,
+    )?;
+    thresholds.check(Path::new(grcov_output(&merge_args.format)))
+}
+
+/// Lines found in the source files of a crate and lines hit by the tests
+#[derive(Debug, Default, PartialEq)]
+struct LineCoverage {
+    hit: usize,
+    found: usize,
+}
+
+impl LineCoverage {
+    fn add(&mut self, other: &LineCoverage) {
+        self.hit += other.hit;
+        self.found += other.found;
+    }
+
+    fn percentage(&self) -> f64 {
+        if self.found == 0 {
+            100.0
+        } else {
+            self.hit as f64 * 100.0 / self.found as f64
+        }
+    }
+}
+
+/// Minimum line coverage of the crates, from `--min-coverage` and the `[coverage]` section of xtask.toml,
+/// and of all the crates together from `--fail-under`
+#[derive(Debug, Default)]
+struct CoverageThresholds {
+    min: Option<f64>,
+    crates: BTreeMap<String, f64>,
+    total: Option<f64>,
+}
+
+impl CoverageThresholds {
+    fn load(min_coverage: Option<f64>) -> anyhow::Result<Self> {
+        let config = XtaskConfig::load()?.coverage;
+        Ok(Self {
+            min: min_coverage.or(config.min),
+            crates: config.crates,
+            total: None,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min.is_none() && self.crates.is_empty() && self.total.is_none()
+    }
+
+    /// The thresholds are checked on the lcov report, the other formats cannot be checked
+    fn ensure_lcov(&self, format: &str) -> anyhow::Result<()> {
+        if !self.is_empty() && format != "lcov" {
+            return Err(anyhow::anyhow!(
+                "The coverage thresholds are checked on the lcov report, they cannot be used with the {} format.",
+                format
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the minimum coverage of the crate, its own minimum takes precedence
+    fn threshold(&self, crate_name: &str) -> Option<f64> {
+        self.crates.get(crate_name).copied().or(self.min)
+    }
+
+    /// Log the line coverage of each crate of the lcov report and fail if any is below its threshold
+    fn check(&self, report: &Path) -> anyhow::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(report)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", report.display(), e))?;
+        let mut members = get_workspace_members(WorkspaceMemberType::Crate)?;
+        members.extend(get_workspace_members(WorkspaceMemberType::Example)?);
+        let coverages = coverage_per_member(&parse_lcov(&contents), &members, &workspace_root());
+        group!("Coverage thresholds");
+        for (name, coverage) in &coverages {
+            let threshold = self
+                .threshold(name)
+                .map(|min| format!(" (min {min}%)"))
+                .unwrap_or_default();
+            info!(
+                "{:>6.2}%  {}/{} lines  {}{}",
+                coverage.percentage(),
+                coverage.hit,
+                coverage.found,
+                name,
+                threshold
+            );
+        }
+        endgroup!();
+        let shortfalls = self.shortfalls(&coverages);
+        if shortfalls.is_empty() {
+            info!("All the crates meet their coverage threshold.");
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "{} crate(s) below their coverage threshold:\n  {}",
+            shortfalls.len(),
+            shortfalls.join("\n  ")
+        ))
+    }
+
+    /// Returns the shortfall of each crate whose coverage is below its threshold, then the one of
+    /// all the crates together
+    fn shortfalls(&self, coverages: &BTreeMap<String, LineCoverage>) -> Vec<String> {
+        let shortfall = |name: &str, coverage: &LineCoverage, min: f64| {
+            let percentage = coverage.percentage();
+            (percentage < min).then(|| {
+                format!(
+                    "{name}: {percentage:.2}% is {:.2} points below the minimum of {min}%",
+                    min - percentage
+                )
+            })
+        };
+        let mut shortfalls: Vec<String> = coverages
+            .iter()
+            .filter_map(|(name, coverage)| shortfall(name, coverage, self.threshold(name)?))
+            .collect();
+        if let Some(min) = self.total {
+            let mut total = LineCoverage::default();
+            coverages.values().for_each(|coverage| total.add(coverage));
+            shortfalls.extend(shortfall("total", &total, min));
+        }
+        shortfalls
+    }
+}
+
+/// Parse an lcov report and returns the line coverage of each of its source files.
+/// The `LF` and `LH` summaries of a record are used, the `DA` lines are counted when they are missing.
+fn parse_lcov(contents: &str) -> Vec<(PathBuf, LineCoverage)> {
+    let mut files = vec![];
+    let mut file: Option<PathBuf> = None;
+    let mut summary: Option<LineCoverage> = None;
+    let mut lines = LineCoverage::default();
+    for line in contents.lines().map(str::trim) {
+        if let Some(path) = line.strip_prefix("SF:") {
+            file = Some(PathBuf::from(path));
+            summary = None;
+            lines = LineCoverage::default();
+        } else if let Some(found) = line.strip_prefix("LF:") {
+            summary.get_or_insert_with(LineCoverage::default).found =
+                found.parse().unwrap_or_default();
+        } else if let Some(hit) = line.strip_prefix("LH:") {
+            summary.get_or_insert_with(LineCoverage::default).hit = hit.parse().unwrap_or_default();
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            lines.found += 1;
+            let count = data.split(',').nth(1).and_then(|c| c.parse::<u64>().ok());
+            if count.is_some_and(|c| c > 0) {
+                lines.hit += 1;
+            }
+        } else if line == "end_of_record" {
+            if let Some(path) = file.take() {
+                files.push((path, summary.take().unwrap_or(std::mem::take(&mut lines))));
+            }
+        }
+    }
+    files
+}
+
+/// Returns the line coverage of each member from the coverage of its files, the relative paths of the
+/// report are relative to the passed root and the files outside of the members are skipped.
+fn coverage_per_member(
+    files: &[(PathBuf, LineCoverage)],
+    members: &[WorkspaceMember],
+    root: &Path,
+) -> BTreeMap<String, LineCoverage> {
+    let mut coverages: BTreeMap<String, LineCoverage> = BTreeMap::new();
+    for (path, coverage) in files {
+        if let Some(member) = member_containing(&root.join(path), members) {
+            coverages
+                .entry(member.name.clone())
+                .or_default()
+                .add(coverage);
+        }
+    }
+    coverages
+}
+
+/// Returns the `.profraw` files of the directory, none if it does not exist
+fn profraw_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+    {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "profraw") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the `.profraw` files found recursively in the directory, the hidden directories and the
+/// passed target directory are skipped.
+fn stray_profraw_files(dir: &Path, target_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = profraw_files(dir)?;
+    for entry in std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+    {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_dir() && !hidden && path != target_dir {
+            files.extend(stray_profraw_files(&path, target_dir)?);
+        }
+    }
+    Ok(files)
+}
+
+fn remove_files(files: &[PathBuf]) -> anyhow::Result<()> {
+    for file in files {
+        std::fs::remove_file(file)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", file.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Remove the `.profraw` files left by the previous coverage runs so that they are not merged again
+pub(crate) fn clean_profraw_files() -> anyhow::Result<()> {
+    let dir = profraw_dir();
+    let files = profraw_files(&dir)?;
+    if explain_write(&format!(
+        "remove {} .profraw file(s) of {}",
+        files.len(),
+        dir.display()
+    )) {
+        return Ok(());
+    }
+    remove_files(&files)?;
+    info!(
+        "Removed {} stale .profraw file(s) of {}",
+        files.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+/// Remove the `.profraw` files of the profraw directory as well as the ones written across the workspace
+/// by the instrumented runs which did not set `LLVM_PROFILE_FILE`, which default to the current directory.
+fn run_clean() -> anyhow::Result<()> {
+    let target_dir = target_dir();
+    let mut files = profraw_files(&profraw_dir())?;
+    files.extend(stray_profraw_files(&workspace_root(), &target_dir)?);
+    if explain_write(&format!("remove {} .profraw file(s)", files.len())) {
+        return Ok(());
+    }
+    remove_files(&files)?;
+    info!("Removed {} .profraw file(s)", files.len());
+    Ok(())
+}
+
+/// Generate the coverage report of the binaries in `target/<profile_dir>` in the lcov or html format
+/// from the `.profraw` files found under the passed input path.
+pub(crate) fn generate_coverage_report(
+    input: &Path,
+    profile_dir: &str,
+    ignore: &[String],
+    format: &str,
+) -> anyhow::Result<()> {
     group!("Grcov");
-    let binary_path = format!("./target/{}/", generate_args.profile);
+    let args = grcov_args(input, profile_dir, ignore, format);
+    run_process(
+        "grcov",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+        "Error executing grcov",
+    )?;
+    info!("Coverage report written to {}", grcov_output(format));
+    record_artifact(Path::new(grcov_output(format)));
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the path of the report generated by grcov for the given format
+fn grcov_output(format: &str) -> &'static str {
+    match format {
+        "html" => "target/coverage/html",
+        _ => "lcov.info",
+    }
+}
+
+fn grcov_args(input: &Path, profile_dir: &str, ignore: &[String], format: &str) -> Vec<String> {
+    let binary_path = format!("./target/{}/", profile_dir);
+    let input = input.to_string_lossy();
     #[rustfmt::skip]
     let mut args = vec![
-        ".",
+        &input,
         "--binary-path", &binary_path,
         "-s", ".",
-        "-t", "lcov",
-        "-o", "lcov.info",
+        "-t", format,
+        "-o", grcov_output(format),
         "--branch",
         "--ignore-not-existing",
-    ];
-    generate_args
-        .ignore
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect::<Vec<String>>();
+    ignore
         .iter()
-        .for_each(|i| args.extend(vec!["--ignore", i]));
-    run_process("grcov", &args, None, None, "Error executing grcov")?;
-    endgroup!();
-    Ok(())
+        .for_each(|i| args.extend(vec!["--ignore".to_string(), i.clone()]));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const LCOV: &str = "TN:
+SF:crates/foo/src/lib.rs
+DA:1,3
+DA:2,0
+LF:2
+LH:1
+end_of_record
+SF:crates/foo/src/utils.rs
+DA:1,1
+DA:2,1
+DA:3,0
+DA:4,1
+end_of_record
+SF:/ws/crates/bar/src/lib.rs
+LF:10
+LH:9
+end_of_record
+SF:/rustc/library/core/src/option.rs
+LF:5
+LH:0
+end_of_record
+";
+
+    fn members() -> Vec<WorkspaceMember> {
+        ["foo", "bar"]
+            .iter()
+            .map(|name| WorkspaceMember {
+                name: name.to_string(),
+                path: format!("/ws/crates/{name}"),
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn test_parse_lcov() {
+        let files = parse_lcov(LCOV);
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].1, LineCoverage { hit: 1, found: 2 });
+        assert_eq!(files[1].1, LineCoverage { hit: 3, found: 4 });
+        assert_eq!(files[2].0, PathBuf::from("/ws/crates/bar/src/lib.rs"));
+    }
+
+    #[rstest]
+    fn test_coverage_per_member() {
+        let coverages = coverage_per_member(&parse_lcov(LCOV), &members(), Path::new("/ws"));
+        assert_eq!(
+            coverages,
+            BTreeMap::from([
+                ("bar".to_string(), LineCoverage { hit: 9, found: 10 }),
+                ("foo".to_string(), LineCoverage { hit: 4, found: 6 }),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case::no_threshold(None, vec![], None, vec![])]
+    #[case::global_threshold(Some(80.0), vec![], None, vec!["foo: 66.67% is 13.33 points below the minimum of 80%"])]
+    #[case::crate_override(Some(80.0), vec![("foo", 60.0)], None, vec![])]
+    #[case::crate_threshold_only(None, vec![("bar", 95.0)], None, vec!["bar: 90.00% is 5.00 points below the minimum of 95%"])]
+    #[case::total(None, vec![], Some(85.0), vec!["total: 81.25% is 3.75 points below the minimum of 85%"])]
+    #[case::total_met(None, vec![], Some(80.0), vec![])]
+    fn test_coverage_shortfalls(
+        #[case] min: Option<f64>,
+        #[case] crates: Vec<(&str, f64)>,
+        #[case] total: Option<f64>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let thresholds = CoverageThresholds {
+            min,
+            crates: crates
+                .into_iter()
+                .map(|(name, min)| (name.to_string(), min))
+                .collect(),
+            total,
+        };
+        let coverages = coverage_per_member(&parse_lcov(LCOV), &members(), Path::new("/ws"));
+        assert_eq!(thresholds.shortfalls(&coverages), expected);
+    }
+
+    #[rstest]
+    fn test_profraw_files() {
+        let dir = std::env::temp_dir().join(format!("xtask-profraw-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        ["burn-2-b.profraw", "burn-1-a.profraw", "lcov.info"]
+            .iter()
+            .for_each(|name| std::fs::write(dir.join(name), "").unwrap());
+        let files = profraw_files(&dir);
+        let missing = profraw_files(&dir.join("missing"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            files.unwrap(),
+            vec![dir.join("burn-1-a.profraw"), dir.join("burn-2-b.profraw")]
+        );
+        assert!(missing.unwrap().is_empty());
+    }
+
+    #[rstest]
+    fn test_stray_profraw_files() {
+        let dir = std::env::temp_dir().join(format!("xtask-stray-profraw-{}", std::process::id()));
+        ["crates/foo", ".git", "target/debug"]
+            .iter()
+            .for_each(|sub| std::fs::create_dir_all(dir.join(sub)).unwrap());
+        [
+            "default_1.profraw",
+            "crates/foo/default_2.profraw",
+            "crates/foo/lib.rs",
+            ".git/default_3.profraw",
+            "target/debug/default_4.profraw",
+        ]
+        .iter()
+        .for_each(|name| std::fs::write(dir.join(name), "").unwrap());
+        let mut files = stray_profraw_files(&dir, &dir.join("target")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                dir.join("crates/foo/default_2.profraw"),
+                dir.join("default_1.profraw")
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::lcov(
+        Profile::Debug,
+        "lcov",
+        None,
+        "llvm-cov --workspace --lcov --output-path lcov.info"
+    )]
+    #[case::html(
+        Profile::Debug,
+        "html",
+        None,
+        "llvm-cov --workspace --html --output-dir target/coverage"
+    )]
+    #[case::release_fail_under(
+        Profile::Release,
+        "lcov",
+        Some(72.5),
+        "llvm-cov --workspace --release --lcov --output-path lcov.info --fail-under-lines 72.5"
+    )]
+    fn test_llvm_cov_args(
+        #[case] profile: Profile,
+        #[case] format: &str,
+        #[case] fail_under: Option<f64>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            llvm_cov_args(&profile, &[], format, fail_under).join(" "),
+            expected
+        );
+        let ignored = llvm_cov_args(
+            &profile,
+            &["/*".to_string(), "xtask/*".to_string()],
+            format,
+            None,
+        );
+        assert_eq!(
+            ignored[ignored.len() - 2..],
+            ["--ignore-filename-regex", "^/[^/]*|(^|/)xtask/[^/]*"]
+        );
+    }
+
+    #[rstest]
+    #[case::absolute("/*", "^/[^/]*")]
+    #[case::relative("examples/**", "(^|/)examples/.*")]
+    #[case::escaped("src/lib.rs", "(^|/)src/lib\\.rs")]
+    #[case::single_char("crate?/*", "(^|/)crate[^/]/[^/]*")]
+    fn test_glob_to_regex(#[case] glob: &str, #[case] expected: &str) {
+        assert_eq!(glob_to_regex(glob), expected);
+    }
+
+    #[rstest]
+    #[case::lcov("lcov", "lcov.info")]
+    #[case::html("html", "target/coverage/html")]
+    fn test_grcov_args(#[case] format: &str, #[case] output: &str) {
+        let args = grcov_args(Path::new("."), "debug", &["target/*".to_string()], format);
+        assert_eq!(
+            args,
+            vec![
+                ".",
+                "--binary-path",
+                "./target/debug/",
+                "-s",
+                ".",
+                "-t",
+                format,
+                "-o",
+                output,
+                "--branch",
+                "--ignore-not-existing",
+                "--ignore",
+                "target/*",
+            ]
+        );
+    }
 }
@@ -1,32 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::Ok;
 use strum::IntoEnumIterator;
 
 use crate::{
-    endgroup, group,
-    utils::{cargo::ensure_cargo_crate_is_installed, process::run_process},
+    commands::ensure_nightly_toolchain,
+    endgroup, group, group_info,
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        process::{run_process, run_process_with_output},
+        prompt::ask_once,
+        workspace::{get_workspace_members, invocation_path, WorkspaceMemberType},
+    },
+    versions::{CARGO_MACHETE_VERSION, CARGO_UDEPS_VERSION},
 };
 
+/// Configuration files looked up by cargo-deny when no config is passed
+const DENY_CONFIG_FILES: &[&str] = &["deny.toml", ".deny.toml", ".cargo/deny.toml"];
+
 #[tracel_xtask_macros::declare_command_args(None, DependenciesSubCommand)]
 pub struct DependenciesCmdArgs {}
 
 pub fn handle_command(args: DependenciesCmdArgs) -> anyhow::Result<()> {
     match args.get_command() {
-        DependenciesSubCommand::Deny => run_cargo_deny(),
-        DependenciesSubCommand::Unused => run_cargo_machete(),
+        DependenciesSubCommand::Deny => run_cargo_deny(args.config.as_deref()),
+        DependenciesSubCommand::Unused => match args.backend.as_str() {
+            "udeps" => run_cargo_udeps(&args.exclude),
+            _ => run_cargo_machete(&args.exclude),
+        },
         DependenciesSubCommand::All => DependenciesSubCommand::iter()
             .filter(|c| *c != DependenciesSubCommand::All)
-            .try_for_each(|c| handle_command(DependenciesCmdArgs { command: Some(c) })),
+            .try_for_each(|c| {
+                handle_command(DependenciesCmdArgs {
+                    command: Some(c),
+                    config: args.config.clone(),
+                    backend: args.backend.clone(),
+                    exclude: args.exclude.clone(),
+                })
+            }),
     }
 }
 
 /// Run cargo-deny
-fn run_cargo_deny() -> anyhow::Result<()> {
+fn run_cargo_deny(config: Option<&Path>) -> anyhow::Result<()> {
     ensure_cargo_crate_is_installed("cargo-deny", None, None, false)?;
+    let config = config
+        .map(invocation_path)
+        .or_else(|| {
+            DENY_CONFIG_FILES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+        })
+        .unwrap_or_else(|| PathBuf::from(DENY_CONFIG_FILES[0]));
+    let config = if config.exists() {
+        config
+    } else {
+        bootstrap_deny_config(config)?
+    };
     // Run cargo deny
     group!("Cargo: run deny checks");
+    let config = config.to_string_lossy();
     run_process(
         "cargo",
-        &["deny", "check"],
+        &["deny", "--config", &config, "check"],
         None,
         None,
         "Some dependencies don't meet the requirements!",
@@ -35,19 +75,159 @@ fn run_cargo_deny() -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Run cargo-machete
-fn run_cargo_machete() -> anyhow::Result<()> {
-    ensure_cargo_crate_is_installed("cargo-machete", None, None, false)?;
-    // Run cargo machete
+/// Propose to generate a default cargo-deny configuration at the passed path
+fn bootstrap_deny_config(path: PathBuf) -> anyhow::Result<PathBuf> {
+    let prompt = format!(
+        "The cargo-deny configuration file '{}' does not exist.\nA default one will be generated with 'cargo deny init'.",
+        path.display()
+    );
+    if !ask_once(&prompt) {
+        return Err(anyhow::anyhow!(
+            "A cargo-deny configuration file is required, use --config to point at an existing one."
+        ));
+    }
+    group!("Cargo: generate deny configuration");
+    let path_str = path.to_string_lossy();
+    run_process(
+        "cargo",
+        &["deny", "--config", &path_str, "init"],
+        None,
+        None,
+        "Failed to generate the cargo-deny configuration",
+    )?;
+    endgroup!();
+    Ok(path)
+}
+
+/// Run cargo-machete on the workspace, or on the directories of the members which are not excluded,
+/// and log the unused dependencies grouped by crate.
+fn run_cargo_machete(excluded: &[String]) -> anyhow::Result<()> {
+    ensure_cargo_crate_is_installed("cargo-machete", None, Some(CARGO_MACHETE_VERSION), false)?;
+    let mut args = vec!["machete".to_string()];
+    if !excluded.is_empty() {
+        let mut members = get_workspace_members(WorkspaceMemberType::Crate)?;
+        members.extend(get_workspace_members(WorkspaceMemberType::Example)?);
+        args.extend(
+            members
+                .into_iter()
+                .filter(|member| !excluded.contains(&member.name))
+                .map(|member| member.path),
+        );
+    }
     group!("Cargo: run unused dependencies checks");
+    let output = run_process_with_output(
+        "cargo",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    endgroup!();
+    let unused = parse_machete_output(&String::from_utf8_lossy(&output.stdout));
+    for (crate_name, dependencies) in &unused {
+        group!("Unused dependencies: {}", crate_name);
+        dependencies.iter().for_each(|dep| group_info!("{}", dep));
+        endgroup!();
+    }
+    if !unused.is_empty() {
+        let count: usize = unused.values().map(Vec::len).sum();
+        return Err(anyhow::anyhow!(
+            "Found {} unused dependencies in {} crate(s)",
+            count,
+            unused.len()
+        ));
+    }
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "cargo-machete failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    info!("No unused dependency found.");
+    Ok(())
+}
+
+/// Returns the unused dependencies reported by cargo-machete by crate, the crates are listed as
+/// `<crate> -- <path>/Cargo.toml:` followed by their unused dependencies indented with a tab.
+fn parse_machete_output(output: &str) -> BTreeMap<String, Vec<String>> {
+    let mut unused: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if let Some((crate_name, _)) = line
+            .strip_suffix(':')
+            .and_then(|header| header.split_once(" -- "))
+        {
+            current = Some(crate_name.trim().to_string());
+        } else if line.starts_with('\t') || line.starts_with("  ") {
+            if let Some(crate_name) = &current {
+                unused
+                    .entry(crate_name.clone())
+                    .or_default()
+                    .push(line.trim().to_string());
+            }
+        } else {
+            current = None;
+        }
+    }
+    unused
+}
+
+/// Run cargo-udeps on the workspace with the nightly toolchain, it builds the crates to find the unused ones
+fn run_cargo_udeps(excluded: &[String]) -> anyhow::Result<()> {
+    ensure_nightly_toolchain("cargo-udeps", "dependencies unused --backend udeps", &[])?;
+    ensure_cargo_crate_is_installed("cargo-udeps", None, Some(CARGO_UDEPS_VERSION), true)?;
+    let mut args = vec![
+        "+nightly".to_string(),
+        "udeps".to_string(),
+        "--workspace".to_string(),
+        "--all-targets".to_string(),
+    ];
+    excluded
+        .iter()
+        .for_each(|name| args.extend(["--exclude".to_string(), name.clone()]));
+    group!("Cargo: run unused dependencies checks with cargo-udeps");
     run_process(
         "cargo",
-        &["machete"],
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
         None,
         None,
         "Unused dependencies found!",
     )?;
     endgroup!();
-
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_parse_machete_output() {
+        let output = "Analyzing dependencies of crates in this directory...
+cargo-machete found the following unused dependencies in this directory:
+my-crate -- ./crates/my-crate/Cargo.toml:
+\tlog
+\tregex
+my-other-crate -- ./crates/my-other-crate/Cargo.toml:
+\tserde
+
+If you believe cargo-machete has detected an unused dependency incorrectly,
+you can add the dependency to the list of dependencies to ignore in the
+`[package.metadata.cargo-machete]` section of the appropriate Cargo.toml.
+";
+        assert_eq!(
+            parse_machete_output(output),
+            BTreeMap::from([
+                (
+                    "my-crate".to_string(),
+                    vec!["log".to_string(), "regex".to_string()]
+                ),
+                ("my-other-crate".to_string(), vec!["serde".to_string()]),
+            ])
+        );
+        assert!(parse_machete_output(
+            "cargo-machete didn't find any unused dependencies in this directory. Good job!"
+        )
+        .is_empty());
+    }
+}
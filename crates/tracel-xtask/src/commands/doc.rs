@@ -1,42 +1,96 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::Ok;
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_ONLY_ARGS,
-    endgroup, group,
+    commands::{
+        add_listed_crates, ensure_nightly_toolchain, ensure_only_default_members,
+        restrict_to_changed_members, target_members, try_for_each_keep_going,
+    },
+    endgroup, group, group_error,
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
+        cargo::ensure_cargo_crate_is_installed,
+        config::XtaskConfig,
+        process::{
+            run_process, run_process_for_package, run_process_for_workspace,
+            run_process_with_output,
+        },
         workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
     },
+    versions::CARGO_DEADLINKS_VERSION,
 };
 
+const DEAD_LINKS_PAGE_PREFIX: &str = "Found invalid urls in ";
+
 use super::Target;
 
 #[tracel_xtask_macros::declare_command_args(Target, DocSubCommand)]
 pub struct DocCmdArgs {}
 
-pub fn handle_command(args: DocCmdArgs) -> anyhow::Result<()> {
-    if args.target == Target::Workspace && !args.only.is_empty() {
-        warn!("{}", WARN_IGNORED_ONLY_ARGS);
+pub fn handle_command(mut args: DocCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
     }
     match args.get_command() {
-        DocSubCommand::Build => run_documentation_build(&args.target, &args.exclude, &args.only),
+        DocSubCommand::Build => {
+            run_documentation_build(&args.target, &args.exclude, &args.only, args.private)
+        }
         DocSubCommand::Tests => run_documentation(&args.target, &args.exclude, &args.only),
+        DocSubCommand::CheckLinks => run_check_links(
+            &args.target,
+            &args.exclude,
+            &args.only,
+            &args.allowed_links,
+            args.private,
+        ),
+        DocSubCommand::Coverage => {
+            run_documentation_coverage(&args.target, &args.exclude, &args.only, args.min)
+        }
+        DocSubCommand::CheckMissing => {
+            run_check_missing(&args.target, &args.exclude, &args.only, args.private)
+        }
+    }
+}
+
+/// Returns the arguments of cargo doc documenting the private items as well if requested
+fn doc_args<'a>(base_args: &[&'a str], private: bool) -> Vec<&'a str> {
+    let mut args = base_args.to_vec();
+    if private {
+        args.push("--document-private-items");
     }
+    args
 }
 
 fn run_documentation_build(
     target: &Target,
     excluded: &Vec<String>,
     only: &Vec<String>,
+    private: bool,
 ) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Build Workspace documentation");
             run_process_for_workspace(
                 "cargo",
-                &["doc", "--workspace", "--no-deps", "--color=always"],
+                &doc_args(
+                    &["doc", "--workspace", "--no-deps", "--color=always"],
+                    private,
+                ),
                 excluded,
+                only,
                 None,
                 None,
                 "Workspace documentation build failed",
@@ -45,11 +99,19 @@ fn run_documentation_build(
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
             for member in members {
@@ -57,7 +119,10 @@ fn run_documentation_build(
                 run_process_for_package(
                     "cargo",
                     &member.name,
-                    &["doc", "-p", &member.name, "--no-deps", "--color=always"],
+                    &doc_args(
+                        &["doc", "-p", &member.name, "--no-deps", "--color=always"],
+                        private,
+                    ),
                     excluded,
                     only,
                     &format!("Format check execution failed for {}", &member.name),
@@ -69,13 +134,281 @@ fn run_documentation_build(
         }
         Target::AllPackages => {
             Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_documentation_build(&t, excluded, only))?;
+                .filter(|t| matches!(t, Target::Crates | Target::Examples))
+                .try_for_each(|t| run_documentation_build(&t, excluded, only, private))?;
+        }
+    }
+    Ok(())
+}
+
+fn run_check_links(
+    target: &Target,
+    excluded: &Vec<String>,
+    only: &Vec<String>,
+    allowed_links: &[String],
+    private: bool,
+) -> anyhow::Result<()> {
+    run_documentation_build(target, excluded, only, private)?;
+    group!("Check documentation links");
+    ensure_cargo_crate_is_installed(
+        "cargo-deadlinks",
+        None,
+        Some(CARGO_DEADLINKS_VERSION),
+        false,
+    )?;
+    let output = run_process_with_output(
+        "cargo",
+        &[
+            "deadlinks",
+            "--dir",
+            "target/doc",
+            "--check-http",
+            "--no-build",
+        ],
+        None,
+        None,
+    )?;
+    let pages = parse_dead_links(&String::from_utf8_lossy(&output.stdout));
+    if !output.status.success() && pages.is_empty() {
+        return Err(anyhow::anyhow!("Failed to check the documentation links"));
+    }
+    let dead_links = filter_allowed_links(pages, allowed_links);
+    if !dead_links.is_empty() {
+        let count: usize = dead_links.values().map(Vec::len).sum();
+        for (page, links) in &dead_links {
+            group_error!("{}:", page);
+            links.iter().for_each(|link| group_error!("  {}", link));
         }
+        return Err(anyhow::anyhow!(
+            "Found {} dead link(s) in {} page(s)",
+            count,
+            dead_links.len()
+        ));
     }
+    endgroup!();
     Ok(())
 }
 
+/// Document each member with the missing_docs lint denied so that any undocumented public item fails.
+/// The members listed in the `missing_docs_exclude` of the `[doc]` section of xtask.toml are skipped and
+/// all the failing members are reported.
+fn run_check_missing(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    private: bool,
+) -> anyhow::Result<()> {
+    let mut excluded = excluded.to_vec();
+    excluded.extend(XtaskConfig::load()?.doc.missing_docs_exclude);
+    let rustdocflags = missing_docs_flags(std::env::var("RUSTDOCFLAGS").ok().as_deref());
+    try_for_each_keep_going(target_members(target, &excluded, only)?, true, |member| {
+        group!("Check Missing Docs: {}", member.name);
+        run_process(
+            "cargo",
+            &doc_args(
+                &["doc", "-p", &member.name, "--no-deps", "--color=always"],
+                private,
+            ),
+            Some(HashMap::from([("RUSTDOCFLAGS", rustdocflags.as_str())])),
+            None,
+            &format!("Public items of {} are not documented", member.name),
+        )?;
+        endgroup!();
+        Ok(())
+    })
+}
+
+/// Returns the RUSTDOCFLAGS denying the missing_docs lint in addition to the passed flags
+fn missing_docs_flags(rustdocflags: Option<&str>) -> String {
+    match rustdocflags
+        .map(str::trim)
+        .filter(|flags| !flags.is_empty())
+    {
+        Some(flags) => format!("{flags} -D missing_docs"),
+        None => "-D missing_docs".to_string(),
+    }
+}
+
+/// Parse the output of cargo-deadlinks and returns the dead links grouped by source page
+fn parse_dead_links(output: &str) -> BTreeMap<String, Vec<String>> {
+    let mut pages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut current_page = None;
+    for line in output.lines() {
+        if let Some(page) = line.strip_prefix(DEAD_LINKS_PAGE_PREFIX) {
+            let page = page.trim_end_matches(':').to_string();
+            pages.entry(page.clone()).or_default();
+            current_page = Some(page);
+        } else if let (Some(page), Some(link)) = (&current_page, line.strip_prefix('\t')) {
+            pages
+                .entry(page.clone())
+                .or_default()
+                .push(link.to_string());
+        }
+    }
+    pages
+}
+
+/// Remove the dead links mentioning one of the allowed URLs as well as the pages without dead links
+fn filter_allowed_links(
+    pages: BTreeMap<String, Vec<String>>,
+    allowed_links: &[String],
+) -> BTreeMap<String, Vec<String>> {
+    pages
+        .into_iter()
+        .filter_map(|(page, links)| {
+            let links: Vec<String> = links
+                .into_iter()
+                .filter(|link| !allowed_links.iter().any(|allowed| link.contains(allowed)))
+                .collect();
+            (!links.is_empty()).then_some((page, links))
+        })
+        .collect()
+}
+
+/// Number of documented items over the total number of items reported by rustdoc
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DocCoverage {
+    documented: u64,
+    total: u64,
+}
+
+impl DocCoverage {
+    fn add(&mut self, other: DocCoverage) {
+        self.documented += other.documented;
+        self.total += other.total;
+    }
+
+    /// Percentage of documented items, a crate without items is fully documented
+    fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.documented as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+fn run_documentation_coverage(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    min: Option<f64>,
+) -> anyhow::Result<()> {
+    ensure_nightly_toolchain("Documentation coverage", "doc coverage", &[])?;
+    let members = match target {
+        Target::Workspace | Target::AllPackages | Target::Crates => {
+            get_workspace_members(WorkspaceMemberType::Crate)?
+        }
+        Target::PublishableCrates => get_workspace_members(WorkspaceMemberType::PublishableCrate)?,
+        Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+    };
+    let mut coverages = vec![];
+    for member in members {
+        if excluded.contains(&member.name) || (!only.is_empty() && !only.contains(&member.name)) {
+            continue;
+        }
+        group!("Documentation coverage: {}", member.name);
+        let output = run_process_with_output(
+            "cargo",
+            &[
+                "+nightly",
+                "rustdoc",
+                "-p",
+                &member.name,
+                "--lib",
+                "--",
+                "-Z",
+                "unstable-options",
+                "--show-coverage",
+                "--output-format",
+                "json",
+            ],
+            None,
+            None,
+        )?;
+        if output.status.success() {
+            coverages.push((
+                member.name.clone(),
+                parse_doc_coverage(&String::from_utf8_lossy(&output.stdout))?,
+            ));
+        } else {
+            // rustdoc only documents libraries, binaries are skipped
+            info!(
+                "No library to report the documentation coverage of for '{}'.",
+                member.name
+            );
+        }
+        endgroup!();
+    }
+    let total = coverages
+        .iter()
+        .fold(DocCoverage::default(), |mut total, (_, coverage)| {
+            total.add(*coverage);
+            total
+        });
+    info!("Documentation coverage summary:");
+    doc_coverage_summary_lines(&coverages, total)
+        .iter()
+        .for_each(|line| info!("  {}", line));
+    match min {
+        Some(min) if total.percentage() < min => Err(anyhow::anyhow!(
+            "Documentation coverage {:.1}% is below the minimum of {:.1}%",
+            total.percentage(),
+            min
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parse the JSON coverage report of rustdoc and sum the items of all the files
+fn parse_doc_coverage(output: &str) -> anyhow::Result<DocCoverage> {
+    let json = output
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .ok_or_else(|| anyhow::anyhow!("No documentation coverage report found"))?;
+    let report: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the documentation coverage: {}", e))?;
+    let mut coverage = DocCoverage::default();
+    for file in report
+        .as_object()
+        .into_iter()
+        .flat_map(|files| files.values())
+    {
+        coverage.add(DocCoverage {
+            documented: file["with_docs"].as_u64().unwrap_or(0),
+            total: file["total"].as_u64().unwrap_or(0),
+        });
+    }
+    Ok(coverage)
+}
+
+/// Returns the lines of the coverage table with one row per crate and the total
+fn doc_coverage_summary_lines(
+    coverages: &[(String, DocCoverage)],
+    total: DocCoverage,
+) -> Vec<String> {
+    let width = coverages
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["total".len()])
+        .max()
+        .unwrap_or(0);
+    coverages
+        .iter()
+        .map(|(name, coverage)| (name.as_str(), *coverage))
+        .chain([("total", total)])
+        .map(|(name, coverage)| {
+            format!(
+                "{:<width$}  {:>6.1}%  ({}/{})",
+                name,
+                coverage.percentage(),
+                coverage.documented,
+                coverage.total
+            )
+        })
+        .collect()
+}
+
 pub(crate) fn run_documentation(
     target: &Target,
     excluded: &[String],
@@ -88,6 +421,7 @@ pub(crate) fn run_documentation(
                 "cargo",
                 &["test", "--workspace", "--doc", "--color", "always"],
                 excluded,
+                only,
                 Some(r"Doc-tests (\w+)"),
                 Some("Doc Tests"),
                 "Workspace documentation test failed",
@@ -96,11 +430,19 @@ pub(crate) fn run_documentation(
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
             for member in members {
@@ -109,7 +451,7 @@ pub(crate) fn run_documentation(
         }
         Target::AllPackages => {
             Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .filter(|t| matches!(t, Target::Crates | Target::Examples))
                 .try_for_each(|t| run_documentation(&t, excluded, only))?;
         }
     }
@@ -141,3 +483,112 @@ fn run_doc_test(
     endgroup!();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const DEAD_LINKS_OUTPUT: &str = "Found invalid urls in foo/index.html:
+\tLinked file at path foo/missing.html does not exist!
+\tError fetching https://flaky.example.com/x: Dns Failed
+Found invalid urls in bar/struct.Bar.html:
+\tError fetching https://flaky.example.com/y: Dns Failed
+";
+
+    #[rstest]
+    fn test_parse_dead_links() {
+        let pages = parse_dead_links(DEAD_LINKS_OUTPUT);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages["foo/index.html"],
+            vec![
+                "Linked file at path foo/missing.html does not exist!",
+                "Error fetching https://flaky.example.com/x: Dns Failed",
+            ]
+        );
+        assert_eq!(
+            pages["bar/struct.Bar.html"],
+            vec!["Error fetching https://flaky.example.com/y: Dns Failed"]
+        );
+    }
+
+    #[rstest]
+    #[case::nothing_allowed(vec![], 2, 3)]
+    #[case::flaky_allowed(vec!["https://flaky.example.com"], 1, 1)]
+    #[case::everything_allowed(vec!["https://flaky.example.com", "foo/missing.html"], 0, 0)]
+    fn test_filter_allowed_links(
+        #[case] allowed: Vec<&str>,
+        #[case] expected_pages: usize,
+        #[case] expected_links: usize,
+    ) {
+        let allowed: Vec<String> = allowed.into_iter().map(String::from).collect();
+        let dead_links = filter_allowed_links(parse_dead_links(DEAD_LINKS_OUTPUT), &allowed);
+        assert_eq!(dead_links.len(), expected_pages);
+        assert_eq!(
+            dead_links.values().map(Vec::len).sum::<usize>(),
+            expected_links
+        );
+    }
+
+    #[rstest]
+    #[case::single_file(
+        r#"{"src/lib.rs":{"total":7,"with_docs":3,"total_examples":6,"with_examples":0}}"#,
+        DocCoverage { documented: 3, total: 7 }
+    )]
+    #[case::several_files(
+        r#"{"src/lib.rs":{"total":4,"with_docs":4},"src/utils.rs":{"total":6,"with_docs":1}}"#,
+        DocCoverage { documented: 5, total: 10 }
+    )]
+    #[case::no_items("{}", DocCoverage { documented: 0, total: 0 })]
+    fn test_parse_doc_coverage(#[case] output: &str, #[case] expected: DocCoverage) {
+        assert_eq!(parse_doc_coverage(output).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::no_flags(None, "-D missing_docs")]
+    #[case::empty_flags(Some(" "), "-D missing_docs")]
+    #[case::existing_flags(Some("--cfg docsrs"), "--cfg docsrs -D missing_docs")]
+    fn test_missing_docs_flags(#[case] rustdocflags: Option<&str>, #[case] expected: &str) {
+        assert_eq!(missing_docs_flags(rustdocflags), expected);
+    }
+
+    #[rstest]
+    #[case::no_items(DocCoverage { documented: 0, total: 0 }, 100.0)]
+    #[case::half(DocCoverage { documented: 5, total: 10 }, 50.0)]
+    fn test_doc_coverage_percentage(#[case] coverage: DocCoverage, #[case] expected: f64) {
+        assert_eq!(coverage.percentage(), expected);
+    }
+
+    #[rstest]
+    fn test_doc_coverage_summary_lines() {
+        let coverages = vec![
+            (
+                "my-crate".to_string(),
+                DocCoverage {
+                    documented: 1,
+                    total: 4,
+                },
+            ),
+            (
+                "other".to_string(),
+                DocCoverage {
+                    documented: 3,
+                    total: 4,
+                },
+            ),
+        ];
+        let total = DocCoverage {
+            documented: 4,
+            total: 8,
+        };
+        assert_eq!(
+            doc_coverage_summary_lines(&coverages, total),
+            vec![
+                "my-crate    25.0%  (1/4)",
+                "other       75.0%  (3/4)",
+                "total       50.0%  (4/8)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Ok;
+
+use crate::{
+    endgroup, group,
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        process::run_process,
+        workspace::{find_member_target, get_workspace_members, WorkspaceMemberType},
+    },
+    versions::CARGO_EXPAND_VERSION,
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct ExpandCmdArgs {}
+
+pub fn handle_command(args: ExpandCmdArgs) -> anyhow::Result<()> {
+    let package = match (&args.package, &args.bin, &args.test) {
+        (Some(package), _, _) => {
+            ensure_workspace_member(package)?;
+            package.clone()
+        }
+        (None, Some(bin), _) => find_member_target("bin", bin)?.name,
+        (None, None, Some(test)) => find_member_target("test", test)?.name,
+        (None, None, None) => {
+            return Err(anyhow::anyhow!(
+                "A package must be selected with --package when expanding the library."
+            ))
+        }
+    };
+    ensure_cargo_crate_is_installed("cargo-expand", None, Some(CARGO_EXPAND_VERSION), false)?;
+    group!("Expand: {}", package);
+    let mut cmd_args = vec!["expand", "-p", &package];
+    if let Some(bin) = &args.bin {
+        cmd_args.extend(["--bin", bin]);
+    } else if let Some(test) = &args.test {
+        cmd_args.extend(["--test", test]);
+    } else {
+        cmd_args.push("--lib");
+    }
+    if let Some(item) = &args.item {
+        cmd_args.push(item);
+    }
+    run_process(
+        "cargo",
+        &cmd_args,
+        None,
+        None,
+        &format!("Failed to expand the macros of '{}'", package),
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+fn ensure_workspace_member(package: &str) -> anyhow::Result<()> {
+    let members: Vec<String> = get_workspace_members(WorkspaceMemberType::Crate)?
+        .into_iter()
+        .chain(get_workspace_members(WorkspaceMemberType::Example)?)
+        .map(|m| m.name)
+        .collect();
+    if members.iter().any(|m| m == package) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "'{}' is not a member of the workspace. Available members: {}",
+            package,
+            members.join(", ")
+        ))
+    }
+}
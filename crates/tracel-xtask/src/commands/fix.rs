@@ -1,16 +1,22 @@
+use std::path::PathBuf;
+
 use anyhow::{Ok, Result};
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS,
+    commands::{
+        add_listed_crates, ensure_only_default_members, ensure_typos_is_installed, fmt_args,
+        members_confirmation_prompt, nightly_fmt_config, restrict_to_changed_members,
+        target_member_names, typos_config_args, WARN_IGNORED_EXCLUDE_ARGS,
+    },
     endgroup, group,
     utils::{
         cargo::ensure_cargo_crate_is_installed,
+        git::{stage_files, staged_files, unstaged_files},
         process::{run_process, run_process_for_package, run_process_for_workspace},
         prompt::ask_once,
-        workspace::{get_workspace_members, WorkspaceMemberType},
+        workspace::{get_workspace_members, member_names_containing_files, WorkspaceMemberType},
     },
-    versions::TYPOS_VERSION,
 };
 
 use super::Target;
@@ -18,21 +24,72 @@ use super::Target;
 #[tracel_xtask_macros::declare_command_args(Target, FixSubCommand)]
 pub struct FixCmdArgs {}
 
-pub fn handle_command(args: FixCmdArgs, mut answer: Option<bool>) -> anyhow::Result<()> {
+pub fn handle_command(mut args: FixCmdArgs, mut answer: Option<bool>) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if args.staged {
+        return fix_staged(&args);
+    }
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return Ok(());
+    }
     if answer.is_none() {
-        if args.target == Target::Workspace && (!args.exclude.is_empty() || !args.only.is_empty()) {
-            warn!("{}", WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS);
+        // cargo fmt does not support --exclude
+        if args.target == Target::Workspace
+            && !args.exclude.is_empty()
+            && matches!(
+                args.get_command(),
+                FixSubCommand::Format | FixSubCommand::All
+            )
+        {
+            warn!("{}", WARN_IGNORED_EXCLUDE_ARGS);
         }
-        answer = Some(ask_once(
-            "This will run the check with autofix mode enabled.",
-        ));
+        let members = match args.get_command() {
+            // these subcommands are run on the files of the repository
+            FixSubCommand::Audit | FixSubCommand::Typos => vec![],
+            // the excluded crates of the workspace are formatted as well
+            FixSubCommand::Format | FixSubCommand::All if args.target == Target::Workspace => {
+                target_member_names(&args.target, &[], &args.only)?
+            }
+            _ => target_member_names(&args.target, &args.exclude, &args.only)?,
+        };
+        let prompt = if members.is_empty() {
+            "This will run the check with autofix mode enabled.".to_string()
+        } else {
+            members_confirmation_prompt(
+                "This will run the check with autofix mode enabled.",
+                &members,
+            )
+        };
+        answer = Some(ask_once(&prompt));
     };
     if answer.unwrap() {
         match args.get_command() {
             FixSubCommand::Audit => run_audit(),
-            FixSubCommand::Format => run_format(&args.target, &args.exclude, &args.only),
+            FixSubCommand::Format => {
+                let nightly_config = nightly_fmt_config(args.nightly_fmt)?;
+                run_format(
+                    &args.target,
+                    &args.exclude,
+                    &args.only,
+                    nightly_config.as_deref(),
+                )
+            }
             FixSubCommand::Lint => run_lint(&args.target, &args.exclude, &args.only),
-            FixSubCommand::Typos => run_typos(),
+            FixSubCommand::Typos => run_typos(
+                &typos_config_args(args.typos_config.as_deref(), &args.exclude_paths),
+                &[],
+            ),
             FixSubCommand::All => FixSubCommand::iter()
                 .filter(|c| *c != FixSubCommand::All)
                 .try_for_each(|c| {
@@ -42,6 +99,14 @@ pub fn handle_command(args: FixCmdArgs, mut answer: Option<bool>) -> anyhow::Res
                             target: args.target.clone(),
                             exclude: args.exclude.clone(),
                             only: args.only.clone(),
+                            changed_only: false,
+                            changed_since: None,
+                            exclude_from: None,
+                            only_from: None,
+                            nightly_fmt: args.nightly_fmt,
+                            typos_config: args.typos_config.clone(),
+                            exclude_paths: args.exclude_paths.clone(),
+                            staged: false,
                         },
                         answer,
                     )
@@ -52,6 +117,96 @@ pub fn handle_command(args: FixCmdArgs, mut answer: Option<bool>) -> anyhow::Res
     }
 }
 
+/// Fix the staged files then stage the fixes, the format and lint fixes are run on the members
+/// containing the staged files. No confirmation is asked as only the staged files are staged again.
+fn fix_staged(args: &FixCmdArgs) -> anyhow::Result<()> {
+    let staged = staged_files()?;
+    if staged.is_empty() {
+        info!("No staged file to fix.");
+        return Ok(());
+    }
+    let unstaged = unstaged_files()?;
+    let members = member_names_containing_files(&staged)?;
+    let commands: Vec<FixSubCommand> = match args.get_command() {
+        FixSubCommand::All => FixSubCommand::iter()
+            .filter(|c| *c != FixSubCommand::All)
+            .collect(),
+        command => vec![command],
+    };
+    for command in commands {
+        match command {
+            FixSubCommand::Audit => {
+                if staged.iter().any(|file| file.ends_with("Cargo.lock")) {
+                    run_audit()?;
+                } else {
+                    info!("Skipping the audit fix, Cargo.lock is not staged.");
+                }
+            }
+            FixSubCommand::Format | FixSubCommand::Lint if members.is_empty() => {
+                info!(
+                    "Skipping the {} fix, no staged file belongs to a workspace member.",
+                    command
+                );
+            }
+            FixSubCommand::Format => {
+                let nightly_config = nightly_fmt_config(args.nightly_fmt)?;
+                run_format(
+                    &Target::Workspace,
+                    &vec![],
+                    &members,
+                    nightly_config.as_deref(),
+                )?;
+            }
+            FixSubCommand::Lint => run_lint(&Target::Workspace, &args.exclude, &members)?,
+            FixSubCommand::Typos => run_typos(
+                &typos_config_args(args.typos_config.as_deref(), &args.exclude_paths),
+                &staged,
+            )?,
+            FixSubCommand::All => {}
+        }
+    }
+    let (restaged, partially_staged) = files_to_restage(&staged, &unstaged, &unstaged_files()?);
+    if !partially_staged.is_empty() {
+        warn!(
+            "The files with unstaged changes are not staged again, review their fixes before committing: {}",
+            display_paths(&partially_staged)
+        );
+    }
+    if restaged.is_empty() {
+        info!("The fixes did not change any staged file.");
+        return Ok(());
+    }
+    stage_files(&restaged)?;
+    info!("Staged the fixes of {}", display_paths(&restaged));
+    Ok(())
+}
+
+/// Returns the staged files changed by the fixes and the staged files which already had unstaged
+/// changes, staging the latter would also stage the changes which were left out of the commit.
+fn files_to_restage(
+    staged: &[PathBuf],
+    unstaged_before: &[PathBuf],
+    unstaged_after: &[PathBuf],
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (partially_staged, fully_staged): (Vec<&PathBuf>, Vec<&PathBuf>) = staged
+        .iter()
+        .partition(|file| unstaged_before.contains(file));
+    let restaged = fully_staged
+        .into_iter()
+        .filter(|file| unstaged_after.contains(file))
+        .cloned()
+        .collect();
+    (restaged, partially_staged.into_iter().cloned().collect())
+}
+
+fn display_paths(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 pub(crate) fn run_audit() -> anyhow::Result<()> {
     ensure_cargo_crate_is_installed("cargo-audit", Some("fix"), None, false)?;
     group!("Audit Rust Dependencies");
@@ -66,14 +221,20 @@ pub(crate) fn run_audit() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn run_format(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> Result<()> {
+fn run_format(
+    target: &Target,
+    excluded: &Vec<String>,
+    only: &Vec<String>,
+    nightly_config: Option<&str>,
+) -> Result<()> {
     match target {
         Target::Workspace => {
             group!("Format Workspace");
             run_process_for_workspace(
                 "cargo",
-                &["fmt"],
+                &fmt_args(&["fmt"], nightly_config),
                 &[],
+                only,
                 None,
                 None,
                 "Workspace compilation failed",
@@ -82,18 +243,26 @@ fn run_format(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> Re
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
             for member in members {
                 group!("Format: {}", member.name);
                 run_process_for_package(
                     "cargo",
                     &member.name,
-                    &["fmt", "-p", &member.name],
+                    &fmt_args(&["fmt", "-p", &member.name], nightly_config),
                     excluded,
                     only,
                     &format!("Format check execution failed for {}", &member.name),
@@ -105,8 +274,8 @@ fn run_format(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> Re
         }
         Target::AllPackages => {
             Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_format(&t, excluded, only))?;
+                .filter(|t| matches!(t, Target::Crates | Target::Examples))
+                .try_for_each(|t| run_format(&t, excluded, only, nightly_config))?;
         }
     }
     Ok(())
@@ -120,6 +289,7 @@ fn run_lint(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> anyh
                 "cargo",
                 &[
                     "clippy",
+                    "--workspace",
                     "--no-deps",
                     "--fix",
                     "--allow-dirty",
@@ -129,7 +299,8 @@ fn run_lint(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> anyh
                     "--deny",
                     "warnings",
                 ],
-                &[],
+                excluded,
+                only,
                 None,
                 None,
                 "Workspace lint failed",
@@ -138,11 +309,19 @@ fn run_lint(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> anyh
             )?;
             endgroup!();
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
             for member in members {
                 group!("Lint: {}", member.name);
@@ -173,19 +352,33 @@ fn run_lint(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> anyh
         }
         Target::AllPackages => {
             Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .filter(|t| matches!(t, Target::Crates | Target::Examples))
                 .try_for_each(|t| run_lint(&t, excluded, only))?;
         }
     }
     Ok(())
 }
 
-pub(crate) fn run_typos() -> anyhow::Result<()> {
-    ensure_cargo_crate_is_installed("typos-cli", None, Some(TYPOS_VERSION), false)?;
+/// Returns the arguments of typos to fix the typos in place.
+pub(crate) fn typos_args(config_args: &[String]) -> Vec<&str> {
+    let mut args = vec!["--write-changes", "--color", "always"];
+    args.extend(config_args.iter().map(String::as_str));
+    args
+}
+
+/// Fix the typos of the whole tree, or only of the passed files
+pub(crate) fn run_typos(config_args: &[String], files: &[PathBuf]) -> anyhow::Result<()> {
+    ensure_typos_is_installed()?;
     group!("Typos");
+    let mut cmd_args: Vec<&str> = typos_args(config_args);
+    if !files.is_empty() {
+        // apply the exclusions of the typos config to the explicitly passed files
+        cmd_args.push("--force-exclude");
+        cmd_args.extend(files.iter().filter_map(|file| file.to_str()));
+    }
     run_process(
         "typos",
-        &["--write-changes", "--color", "always"],
+        &cmd_args,
         None,
         None,
         "Some typos have been found and cannot be fixed.",
@@ -193,3 +386,35 @@ pub(crate) fn run_typos() -> anyhow::Result<()> {
     endgroup!();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn paths(files: &[&str]) -> Vec<PathBuf> {
+        files.iter().map(PathBuf::from).collect()
+    }
+
+    #[rstest]
+    #[case::fixed(vec!["a.rs", "b.rs"], vec![], vec!["a.rs"], vec!["a.rs"], vec![])]
+    #[case::no_change(vec!["a.rs", "b.rs"], vec![], vec![], vec![], vec![])]
+    #[case::unstaged_file_fixed(vec!["a.rs"], vec![], vec!["a.rs", "c.rs"], vec!["a.rs"], vec![])]
+    #[case::partially_staged(vec!["a.rs", "b.rs"], vec!["b.rs"], vec!["a.rs", "b.rs"], vec!["a.rs"], vec!["b.rs"])]
+    fn test_files_to_restage(
+        #[case] staged: Vec<&str>,
+        #[case] unstaged_before: Vec<&str>,
+        #[case] unstaged_after: Vec<&str>,
+        #[case] restaged: Vec<&str>,
+        #[case] partially_staged: Vec<&str>,
+    ) {
+        assert_eq!(
+            files_to_restage(
+                &paths(&staged),
+                &paths(&unstaged_before),
+                &paths(&unstaged_after)
+            ),
+            (paths(&restaged), paths(&partially_staged))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Ok;
+
+use crate::{
+    endgroup, group,
+    utils::{git::uncommitted_files, process::run_process, snapshot::check_generated_output},
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct GenerateCmdArgs {}
+
+/// A code generator run by the `generate` command, for instance to compile protobufs or to generate bindings.
+/// Generators are registered with `register_generator` before calling `init_xtask`.
+pub trait CodeGenerator: Send + Sync {
+    /// Name of the generator, used in the logs and to select the generators with --generator
+    fn name(&self) -> &str;
+    /// Generate the code in the workspace
+    fn generate(&self) -> anyhow::Result<()>;
+    /// Files and directories written by the generator. When all the generators declare them,
+    /// `--check` compares their contents and restores them instead of relying on the git status.
+    fn outputs(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+}
+
+/// Generator running an external program, for instance `protoc` or `bindgen`
+pub struct CommandGenerator {
+    name: String,
+    program: String,
+    args: Vec<String>,
+    path: Option<PathBuf>,
+    outputs: Vec<PathBuf>,
+}
+
+impl CommandGenerator {
+    pub fn new(name: &str, program: &str, args: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            path: None,
+            outputs: vec![],
+        }
+    }
+
+    /// Declare the files and directories written by the program
+    pub fn with_outputs(mut self, outputs: &[&str]) -> Self {
+        self.outputs = outputs.iter().map(PathBuf::from).collect();
+        self
+    }
+
+    /// Run the program in the passed directory instead of the workspace root
+    pub fn in_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+impl CodeGenerator for CommandGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&self) -> anyhow::Result<()> {
+        run_process(
+            &self.program,
+            &self.args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            None,
+            self.path.as_deref(),
+            &format!("Code generator '{}' failed", self.name),
+        )
+    }
+
+    fn outputs(&self) -> Vec<PathBuf> {
+        self.outputs.clone()
+    }
+}
+
+static GENERATORS: Mutex<Vec<Arc<dyn CodeGenerator>>> = Mutex::new(vec![]);
+
+/// Register a code generator run by the `generate` command, the generators run in registration order.
+pub fn register_generator(generator: impl CodeGenerator + 'static) {
+    GENERATORS
+        .lock()
+        .expect("generators lock should not be poisoned")
+        .push(Arc::new(generator));
+}
+
+pub fn handle_command(args: GenerateCmdArgs) -> anyhow::Result<()> {
+    let registered: Vec<Arc<dyn CodeGenerator>> = GENERATORS
+        .lock()
+        .expect("generators lock should not be poisoned")
+        .clone();
+    if let Some(unknown) = args
+        .generators
+        .iter()
+        .find(|name| !registered.iter().any(|g| g.name() == name.as_str()))
+    {
+        return Err(anyhow::anyhow!("Unknown code generator '{}'", unknown));
+    }
+    let generators: Vec<Arc<dyn CodeGenerator>> = registered
+        .into_iter()
+        .filter(|g| args.generators.is_empty() || args.generators.iter().any(|n| n == g.name()))
+        .collect();
+    if generators.is_empty() {
+        info!("No code generator to run, they are registered with register_generator.");
+        return Ok(());
+    }
+    if args.check && generators.iter().all(|g| !g.outputs().is_empty()) {
+        let outputs: Vec<PathBuf> = generators.iter().flat_map(|g| g.outputs()).collect();
+        check_generated_output(&outputs, "generate", || run_generators(&generators))?;
+        info!("The generated code is up to date.");
+        return Ok(());
+    }
+    let before = uncommitted_files()?;
+    if args.check && !before.is_empty() {
+        warn!(
+            "The git tree has uncommitted changes, the files already modified are not checked: {}",
+            before.join(", ")
+        );
+    }
+    run_generators(&generators)?;
+    let changed = changed_files(&before, &uncommitted_files()?);
+    if changed.is_empty() {
+        info!("The generated code is up to date.");
+        return Ok(());
+    }
+    info!("Changed files:");
+    changed.iter().for_each(|file| info!("  {}", file));
+    if args.check {
+        return Err(anyhow::anyhow!(
+            "The generated code is stale, run the generate command and commit the {} changed file(s).",
+            changed.len()
+        ));
+    }
+    Ok(())
+}
+
+fn run_generators(generators: &[Arc<dyn CodeGenerator>]) -> anyhow::Result<()> {
+    for generator in generators {
+        group!("Generate: {}", generator.name());
+        generator.generate()?;
+        endgroup!();
+    }
+    Ok(())
+}
+
+/// Returns the files modified by the generators, the files already modified before are ignored
+fn changed_files(before: &[String], after: &[String]) -> Vec<String> {
+    let before: BTreeSet<&String> = before.iter().collect();
+    after
+        .iter()
+        .filter(|file| !before.contains(file))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::nothing_changed(vec!["notes.txt"], vec!["notes.txt"], vec![])]
+    #[case::generated_files(
+        vec!["notes.txt"],
+        vec!["notes.txt", "src/proto.rs", "src/bindings.rs"],
+        vec!["src/proto.rs", "src/bindings.rs"]
+    )]
+    fn test_changed_files(
+        #[case] before: Vec<&str>,
+        #[case] after: Vec<&str>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let before: Vec<String> = before.into_iter().map(String::from).collect();
+        let after: Vec<String> = after.into_iter().map(String::from).collect();
+        assert_eq!(changed_files(&before, &after), expected);
+    }
+
+    #[rstest]
+    fn test_command_generator() {
+        let generator = CommandGenerator::new("version", "cargo", &["--version"]);
+        assert_eq!(generator.name(), "version");
+        assert!(generator.generate().is_ok());
+    }
+}
@@ -0,0 +1,201 @@
+use std::{collections::BTreeSet, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    endgroup, group, group_info,
+    utils::{
+        cargo::is_tool_available,
+        explain::explain_write,
+        outcome::record_artifact,
+        process::run_process,
+        workspace::{invocation_path, metadata},
+    },
+};
+
+/// Fill colors of the nodes
+const PUBLISHABLE_COLOR: &str = "lightblue";
+const INTERNAL_COLOR: &str = "lightgray";
+const EXTERNAL_COLOR: &str = "white";
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct GraphCmdArgs {}
+
+pub fn handle_command(args: GraphCmdArgs) -> anyhow::Result<()> {
+    let dot = dependency_graph_dot(&metadata()?, args.include_external);
+    match args.output.as_deref().map(invocation_path) {
+        Some(path) if path.extension().is_some_and(|ext| ext == "svg") => render_svg(&dot, &path),
+        Some(path) => write_dot(&dot, &path),
+        None => {
+            println!("{dot}");
+            Ok(())
+        }
+    }
+}
+
+fn write_dot(dot: &str, path: &Path) -> anyhow::Result<()> {
+    if explain_write(&format!("write {}", path.display())) {
+        return Ok(());
+    }
+    std::fs::write(path, dot)
+        .map_err(|e| anyhow::anyhow!("Failed to write graph to {}: {}", path.display(), e))?;
+    group_info!("Dependency graph written to {}", path.display());
+    record_artifact(path);
+    Ok(())
+}
+
+/// Write the DOT file next to the SVG file and render it with Graphviz
+fn render_svg(dot: &str, path: &Path) -> anyhow::Result<()> {
+    if !is_tool_available("dot") {
+        return Err(anyhow::anyhow!(
+            "Rendering SVG requires the dot command of Graphviz (https://graphviz.org/download/)."
+        ));
+    }
+    let dot_path = path.with_extension("dot");
+    write_dot(dot, &dot_path)?;
+    group!("Graphviz: render {}", path.display());
+    run_process(
+        "dot",
+        &[
+            "-Tsvg",
+            &dot_path.to_string_lossy(),
+            "-o",
+            &path.to_string_lossy(),
+        ],
+        None,
+        None,
+        "Failed to render the dependency graph",
+    )?;
+    record_artifact(path);
+    endgroup!();
+    Ok(())
+}
+
+/// Returns true if the package can be published to a registry
+fn is_publishable(package: &Value) -> bool {
+    // publish is null when unrestricted and the list of allowed registries otherwise
+    package["publish"]
+        .as_array()
+        .map_or(true, |registries| !registries.is_empty())
+}
+
+/// Returns the Graphviz DOT of the dependencies between the workspace members, dev-dependencies excluded.
+/// Nodes are colored by publishable and internal members, external dependencies are only included with
+/// `include_external`.
+fn dependency_graph_dot(metadata: &Value, include_external: bool) -> String {
+    let ids: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let members: Vec<&Value> = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|p| p["id"].as_str().is_some_and(|id| ids.contains(id)))
+        .collect();
+    let names: BTreeSet<&str> = members.iter().filter_map(|p| p["name"].as_str()).collect();
+    let mut nodes = vec![];
+    let mut external = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+    for package in &members {
+        let Some(name) = package["name"].as_str() else {
+            continue;
+        };
+        let color = if is_publishable(package) {
+            PUBLISHABLE_COLOR
+        } else {
+            INTERNAL_COLOR
+        };
+        nodes.push(format!("    \"{name}\" [fillcolor={color}];"));
+        for dependency in package["dependencies"].as_array().into_iter().flatten() {
+            let Some(dependency_name) = dependency["name"].as_str() else {
+                continue;
+            };
+            if dependency["kind"] == "dev" {
+                continue;
+            }
+            let is_member = dependency["path"].is_string() && names.contains(dependency_name);
+            if !is_member {
+                if !include_external {
+                    continue;
+                }
+                external.insert(dependency_name);
+            }
+            edges.insert(format!("    \"{name}\" -> \"{dependency_name}\";"));
+        }
+    }
+    nodes.extend(external.iter().map(|name| {
+        format!("    \"{name}\" [fillcolor={EXTERNAL_COLOR}, style=\"filled,dashed\"];")
+    }));
+    let mut lines = vec![
+        "digraph workspace {".to_string(),
+        "    node [shape=box, style=filled];".to_string(),
+    ];
+    lines.extend(nodes);
+    lines.extend(edges);
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Workspace where the publishable `core` is used by the internal `app` which also depends on
+    /// `serde` and has a dev-dependency on `rstest`.
+    fn metadata() -> Value {
+        serde_json::json!({
+            "packages": [
+                {
+                    "name": "core",
+                    "id": "path+file:///ws/crates/core#0.1.0",
+                    "publish": null,
+                    "dependencies": [],
+                },
+                {
+                    "name": "app",
+                    "id": "path+file:///ws/crates/app#0.1.0",
+                    "publish": [],
+                    "dependencies": [
+                        {"name": "core", "path": "/ws/crates/core", "kind": null},
+                        {"name": "serde", "source": "registry+https://github.com/rust-lang/crates.io-index", "kind": null},
+                        {"name": "rstest", "source": "registry+https://github.com/rust-lang/crates.io-index", "kind": "dev"},
+                    ],
+                },
+            ],
+            "workspace_members": [
+                "path+file:///ws/crates/core#0.1.0",
+                "path+file:///ws/crates/app#0.1.0",
+            ],
+        })
+    }
+
+    #[rstest]
+    fn test_dependency_graph_dot_members_only() {
+        assert_eq!(
+            dependency_graph_dot(&metadata(), false),
+            r#"digraph workspace {
+    node [shape=box, style=filled];
+    "core" [fillcolor=lightblue];
+    "app" [fillcolor=lightgray];
+    "app" -> "core";
+}"#
+        );
+    }
+
+    #[rstest]
+    fn test_dependency_graph_dot_with_external() {
+        assert_eq!(
+            dependency_graph_dot(&metadata(), true),
+            r#"digraph workspace {
+    node [shape=box, style=filled];
+    "core" [fillcolor=lightblue];
+    "app" [fillcolor=lightgray];
+    "serde" [fillcolor=white, style="filled,dashed"];
+    "app" -> "core";
+    "app" -> "serde";
+}"#
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::utils::workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct ListTargetsCmdArgs {}
+
+pub fn handle_command(args: ListTargetsCmdArgs) -> anyhow::Result<()> {
+    let categories = [
+        ("crates", get_workspace_members(WorkspaceMemberType::Crate)?),
+        (
+            "publishable-crates",
+            get_workspace_members(WorkspaceMemberType::PublishableCrate)?,
+        ),
+        (
+            "examples",
+            get_workspace_members(WorkspaceMemberType::Example)?,
+        ),
+    ];
+    let output = match args.format.as_str() {
+        "json" => format_json(&categories),
+        _ => format_plain(&categories),
+    };
+    println!("{output}");
+    Ok(())
+}
+
+/// Format the members as a single line JSON object with one array of members per category
+fn format_json(categories: &[(&str, Vec<WorkspaceMember>)]) -> String {
+    let object: serde_json::Map<String, serde_json::Value> = categories
+        .iter()
+        .map(|(category, members)| {
+            let members = members
+                .iter()
+                .map(|m| serde_json::json!({"name": m.name, "path": m.path}))
+                .collect();
+            (category.to_string(), serde_json::Value::Array(members))
+        })
+        .collect();
+    serde_json::Value::Object(object).to_string()
+}
+
+/// Format the members as a table with one section per category
+fn format_plain(categories: &[(&str, Vec<WorkspaceMember>)]) -> String {
+    let width = categories
+        .iter()
+        .flat_map(|(_, members)| members.iter().map(|m| m.name.len()))
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![];
+    for (category, members) in categories {
+        lines.push(format!("{} ({}):", category, members.len()));
+        lines.extend(
+            members
+                .iter()
+                .map(|m| format!("  {:width$}  {}", m.name, m.path, width = width)),
+        );
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn categories() -> Vec<(&'static str, Vec<WorkspaceMember>)> {
+        vec![
+            (
+                "crates",
+                vec![
+                    WorkspaceMember {
+                        name: "core".to_string(),
+                        path: "/ws/crates/core".to_string(),
+                    },
+                    WorkspaceMember {
+                        name: "server".to_string(),
+                        path: "/ws/crates/server".to_string(),
+                    },
+                ],
+            ),
+            ("examples", vec![]),
+        ]
+    }
+
+    #[rstest]
+    fn test_format_json() {
+        assert_eq!(
+            format_json(&categories()),
+            r#"{"crates":[{"name":"core","path":"/ws/crates/core"},{"name":"server","path":"/ws/crates/server"}],"examples":[]}"#
+        );
+    }
+
+    #[rstest]
+    fn test_format_plain() {
+        assert_eq!(
+            format_plain(&categories()),
+            "crates (2):\n  core    /ws/crates/core\n  server  /ws/crates/server\nexamples (0):"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    group_info,
+    utils::{
+        explain::explain_write,
+        outcome::record_artifact,
+        workspace::{invocation_path, workspace_root},
+    },
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct ManCmdArgs {}
+
+pub fn handle_command(args: ManCmdArgs, command: clap::Command) -> anyhow::Result<()> {
+    let output_dir = args
+        .output_dir
+        .as_deref()
+        .map(invocation_path)
+        .unwrap_or_else(|| workspace_root().join("target").join("man"));
+    if explain_write(&format!("write the man pages to {}", output_dir.display())) {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&output_dir).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create man pages directory {}: {}",
+            output_dir.display(),
+            e
+        )
+    })?;
+    let pages = generate_man_pages(command, &args.bin_name, &output_dir)?;
+    group_info!(
+        "{} man pages written to {}",
+        pages.len(),
+        output_dir.display()
+    );
+    pages.iter().for_each(|page| record_artifact(page));
+    Ok(())
+}
+
+/// Write one man page for the command and one for each of its subcommands, hidden commands are skipped.
+/// Returns the paths of the written pages.
+fn generate_man_pages(
+    command: clap::Command,
+    bin_name: &str,
+    output_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    fn generate(
+        command: clap::Command,
+        output_dir: &Path,
+        pages: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        for subcommand in command.get_subcommands().filter(|c| !c.is_hide_set()) {
+            generate(subcommand.clone(), output_dir, pages)?;
+        }
+        let page = clap_mangen::Man::new(command)
+            .generate_to(output_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to write man page: {}", e))?;
+        pages.push(page);
+        Ok(())
+    }
+
+    // building the command sets the full names of the subcommands, for instance xtask-check-lint
+    let mut command = command
+        .bin_name(bin_name)
+        .display_name(bin_name)
+        .disable_help_subcommand(true);
+    command.build();
+    let mut pages = vec![];
+    generate(command, output_dir, &mut pages)?;
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xtask_command, NoExtraArgs};
+    use rstest::rstest;
+
+    #[derive(clap::Subcommand)]
+    enum TestCommand {
+        /// Build the code.
+        Build,
+        /// Run the tests.
+        Test {
+            #[command(subcommand)]
+            command: TestSubCommand,
+        },
+        /// Internal command.
+        #[command(hide = true)]
+        Internal,
+    }
+
+    #[derive(clap::Subcommand)]
+    enum TestSubCommand {
+        /// Run the unit tests.
+        Unit,
+    }
+
+    #[rstest]
+    fn test_generate_man_pages() {
+        let output_dir = std::env::temp_dir().join(format!("xtask-man-{}", std::process::id()));
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let command = xtask_command::<TestCommand, NoExtraArgs>();
+        let pages = generate_man_pages(command, "xtask", &output_dir).unwrap();
+        let mut names: Vec<String> = pages
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "xtask-build.1",
+                "xtask-test-unit.1",
+                "xtask-test.1",
+                "xtask.1"
+            ]
+        );
+        let page = std::fs::read_to_string(output_dir.join("xtask-test.1")).unwrap();
+        assert!(page.contains("Run the tests"));
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -1,26 +1,51 @@
+pub mod bench;
 pub mod build;
 pub mod bump;
 pub mod check;
 pub mod compile;
+pub mod completions;
 pub mod coverage;
 pub mod dependencies;
 pub mod doc;
+pub mod expand;
 pub mod fix;
+pub mod generate;
+pub mod graph;
+pub mod list_targets;
+pub mod man;
+pub mod profile;
 pub mod publish;
+pub mod size;
 pub mod test;
 pub mod validate;
 pub mod vulnerabilities;
 
 // use crate::declare_target;
+use std::path::Path;
+
 use clap::ValueEnum;
 use strum::{Display, EnumIter, EnumString};
 
+use crate::logging::{group_depth, restore_group_depth};
+use crate::utils::{
+    cargo::{ensure_cargo_crate_is_installed, tool_status, ToolStatus},
+    config::XtaskConfig,
+    git::changed_files,
+    process::{is_interrupted, open_in_browser, process_options},
+    prompt::ask_once,
+    rustup::{is_toolchain_installed, rustup_install_toolchain},
+    workspace::{
+        get_workspace_members, invocation_path, members_affected_by_files, target_dir,
+        workspace_root, WorkspaceMember, WorkspaceMemberType,
+    },
+};
+use crate::versions::TYPOS_VERSION;
+use crate::{endgroup, group};
+
 pub const CARGO_NIGHTLY_MSG: &str = "You must use 'cargo +nightly' to run nightly checks.
 Install a nightly toolchain with 'rustup toolchain install nightly'.";
-pub const WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS: &str =
-    "--target workspace ignores the arguments --exclude and --only. Use --target all-packages instead.";
-pub const WARN_IGNORED_ONLY_ARGS: &str =
-    "--target workspace ignores the arguments --only. Use --target all-packages instead.";
+pub const WARN_IGNORED_EXCLUDE_ARGS: &str =
+    "--target workspace ignores the argument --exclude for this command. Use --target all-packages instead.";
 
 #[tracel_xtask_macros::declare_targets]
 pub enum Target {}
@@ -33,3 +58,622 @@ pub enum Profile {
     Debug,
     Release,
 }
+
+/// Returns the `--only` members of a command run on a custom target declaring its members.
+/// The members of the target are restricted to the `--only` members when there are some.
+pub fn target_members_only(
+    target: &impl std::fmt::Display,
+    members: &[&str],
+    only: Vec<String>,
+) -> anyhow::Result<Vec<String>> {
+    if members.is_empty() {
+        return Ok(only);
+    }
+    let selected: Vec<String> = members
+        .iter()
+        .filter(|m| only.is_empty() || only.iter().any(|o| o == *m))
+        .map(|m| m.to_string())
+        .collect();
+    if selected.is_empty() {
+        return Err(anyhow::anyhow!(
+            "None of the --only crates are members of the {} target: {}",
+            target,
+            members.join(", ")
+        ));
+    }
+    Ok(selected)
+}
+
+/// Add the crates listed in the `--exclude-from` and `--only-from` files to the `--exclude` and `--only`
+/// crates. The files list a crate name or glob per line, the lines starting with `#` are comments and
+/// the globs are expanded to the matching workspace members.
+pub fn add_listed_crates(
+    exclude: &mut Vec<String>,
+    exclude_from: Option<&Path>,
+    only: &mut Vec<String>,
+    only_from: Option<&Path>,
+) -> anyhow::Result<()> {
+    for (crates, path) in [(exclude, exclude_from), (only, only_from)] {
+        let Some(path) = path.map(invocation_path) else {
+            continue;
+        };
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow::anyhow!("Failed to read the crates list {}: {}", path.display(), e)
+        })?;
+        let patterns = parse_crates_list(&contents);
+        let mut members = vec![];
+        if patterns.iter().any(|p| is_glob(p)) {
+            for w_type in [WorkspaceMemberType::Crate, WorkspaceMemberType::Example] {
+                members.extend(get_workspace_members(w_type)?.into_iter().map(|m| m.name));
+            }
+        }
+        for name in expand_crate_globs(&patterns, &members) {
+            if !crates.contains(&name) {
+                crates.push(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fail when an `--only` crate is outside of `workspace.default-members` as these members are skipped
+/// unless `--all-members` is passed. The publishable crates target always selects all of them.
+pub fn ensure_only_default_members(target: &Target, only: &[String]) -> anyhow::Result<()> {
+    if *target == Target::PublishableCrates {
+        return Ok(());
+    }
+    let skipped = non_default_only(only, &process_options().non_default_members);
+    if skipped.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "The --only crates {} are not part of workspace.default-members, pass --all-members to include them.",
+        skipped.join(", ")
+    ))
+}
+
+/// Returns the `--only` crates which are in the passed non-default members
+fn non_default_only<'a>(only: &'a [String], non_default: &[String]) -> Vec<&'a str> {
+    only.iter()
+        .filter(|name| non_default.contains(name))
+        .map(|name| name.as_str())
+        .collect()
+}
+
+/// Returns the crate names and globs of a crates list, without the comments and the blank lines
+fn parse_crates_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Replace the globs by the names of the members they match, a glob matching no member is dropped with a warning
+fn expand_crate_globs(patterns: &[String], members: &[String]) -> Vec<String> {
+    let mut names = vec![];
+    for pattern in patterns {
+        if !is_glob(pattern) {
+            names.push(pattern.clone());
+            continue;
+        }
+        let regex = format!(
+            "^{}$",
+            regex::escape(pattern)
+                .replace("\\*", ".*")
+                .replace("\\?", ".")
+        );
+        let regex = regex::Regex::new(&regex).expect("escaped glob should be a valid regex");
+        let matched: Vec<&String> = members.iter().filter(|m| regex.is_match(m)).collect();
+        if matched.is_empty() {
+            warn!(
+                "The crates glob '{}' does not match any workspace member.",
+                pattern
+            );
+        }
+        names.extend(matched.into_iter().cloned());
+    }
+    names
+}
+
+/// Returns the git reference to compare against when only the changes should be checked.
+pub(crate) fn changed_reference(changed_only: bool, changed_since: Option<&str>) -> Option<&str> {
+    changed_since.or(changed_only.then_some("HEAD"))
+}
+
+/// Restrict the packages to the workspace members affected by the changes since the passed git
+/// reference, or since HEAD when only `changed_only` is set. The workspace target is replaced by
+/// all-packages so that the restriction applies.
+/// Returns false when no member is affected, in which case there is nothing to run.
+pub(crate) fn restrict_to_changed_members(
+    target: &mut Target,
+    only: &mut Vec<String>,
+    changed_only: bool,
+    changed_since: Option<&str>,
+) -> bool {
+    let Some(reference) = changed_reference(changed_only, changed_since) else {
+        return true;
+    };
+    let affected =
+        match changed_files(reference).and_then(|files| members_affected_by_files(&files)) {
+            Ok(members) => members,
+            Err(e) => {
+                warn!(
+                    "Cannot determine the changed members, running on all of them: {}",
+                    e
+                );
+                return true;
+            }
+        };
+    let mut names: Vec<String> = affected.into_iter().map(|m| m.name).collect();
+    if !only.is_empty() {
+        names.retain(|name| only.contains(name));
+    }
+    if names.is_empty() {
+        info!(
+            "No workspace member affected by the changes since {}.",
+            reference
+        );
+        return false;
+    }
+    info!(
+        "Workspace members affected by the changes since {}: {}",
+        reference,
+        names.join(", ")
+    );
+    if *target == Target::Workspace {
+        *target = Target::AllPackages;
+    }
+    *only = names;
+    true
+}
+
+/// Returns the cargo arguments selecting the packages of the target in a single cargo invocation.
+/// Like `workspace_package_args` the workspace target selects each crate of only with `-p` if any.
+pub(crate) fn target_package_args(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let mut args = vec![];
+    match target {
+        Target::Workspace if only.is_empty() => {
+            args.push("--workspace".to_string());
+            for package in excluded {
+                args.extend(["--exclude".to_string(), package.clone()]);
+            }
+        }
+        Target::Workspace => only
+            .iter()
+            .filter(|package| !excluded.contains(package))
+            .for_each(|package| args.extend(["-p".to_string(), package.clone()])),
+        Target::Crates | Target::Examples | Target::AllPackages | Target::PublishableCrates => {
+            target_member_names(target, excluded, only)?
+                .into_iter()
+                .for_each(|name| args.extend(["-p".to_string(), name]));
+        }
+    }
+    Ok(args)
+}
+
+/// Returns the names of the members selected by the target and the `--exclude` and `--only` arguments.
+pub(crate) fn target_member_names(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+) -> anyhow::Result<Vec<String>> {
+    Ok(target_members(target, excluded, only)?
+        .into_iter()
+        .map(|m| m.name)
+        .collect())
+}
+
+/// Returns the members selected by the target and the `--exclude` and `--only` arguments.
+pub(crate) fn target_members(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let mut members = vec![];
+    if matches!(
+        target,
+        Target::Workspace | Target::Crates | Target::AllPackages
+    ) {
+        members.extend(get_workspace_members(WorkspaceMemberType::Crate)?);
+    }
+    if *target == Target::PublishableCrates {
+        members.extend(get_workspace_members(
+            WorkspaceMemberType::PublishableCrate,
+        )?);
+    }
+    if matches!(
+        target,
+        Target::Workspace | Target::Examples | Target::AllPackages
+    ) {
+        members.extend(get_workspace_members(WorkspaceMemberType::Example)?);
+    }
+    Ok(members
+        .into_iter()
+        .filter(|m| !excluded.contains(&m.name) && (only.is_empty() || only.contains(&m.name)))
+        .collect())
+}
+
+/// Maximum number of members listed in a confirmation prompt
+const MAX_LISTED_MEMBERS: usize = 10;
+
+/// Returns the confirmation prompt of a command listing the members it modifies.
+/// Only the first members of a long list are listed, followed by the count of the others.
+pub(crate) fn members_confirmation_prompt(message: &str, members: &[String]) -> String {
+    let mut prompt = match members.len() {
+        0 => return format!("{}\nNo crate is selected.", message),
+        1 => format!("{}\nThe following crate will be modified:", message),
+        count => format!(
+            "{}\nThe following {} crates will be modified:",
+            message, count
+        ),
+    };
+    members
+        .iter()
+        .take(MAX_LISTED_MEMBERS)
+        .for_each(|name| prompt.push_str(&format!("\n  - {}", name)));
+    if members.len() > MAX_LISTED_MEMBERS {
+        prompt.push_str(&format!(
+            "\n  ... and {} more",
+            members.len() - MAX_LISTED_MEMBERS
+        ));
+    }
+    prompt
+}
+
+/// Returns the cargo arguments selecting the build profile.
+pub(crate) fn profile_args(release: bool, profile: Option<&str>) -> Vec<&str> {
+    match (release, profile) {
+        (_, Some(profile)) => vec!["--profile", profile],
+        (true, None) => vec!["--release"],
+        (false, None) => vec![],
+    }
+}
+
+/// Returns the cargo arguments of the profile along with the `--timings` argument when the timings are recorded.
+/// The JSON timings output requires a nightly toolchain so only the HTML report is generated.
+pub(crate) fn timings_args<'a>(profile: &[&'a str], timings: bool) -> Vec<&'a str> {
+    let mut args = profile.to_vec();
+    if timings {
+        args.push("--timings");
+    }
+    args
+}
+
+/// Log the path of the HTML report of the last `--timings` cargo invocation and open it if requested.
+pub(crate) fn report_timings(open: bool) -> anyhow::Result<()> {
+    let report = target_dir().join("cargo-timings").join("cargo-timing.html");
+    group!("Timings");
+    if !report.is_file() {
+        endgroup!();
+        return Err(anyhow::anyhow!(
+            "Cannot find the timings report at {}",
+            report.display()
+        ));
+    }
+    info!("Timings report: {}", report.display());
+    if open {
+        open_in_browser(&report)?;
+    }
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the rustfmt --config argument when formatting with nightly is requested.
+/// Propose to install the nightly toolchain if it is missing.
+pub(crate) fn nightly_fmt_config(nightly: bool) -> anyhow::Result<Option<String>> {
+    if !nightly {
+        return Ok(None);
+    }
+    ensure_nightly_toolchain(
+        "Formatting with unstable rustfmt options",
+        "--nightly-fmt",
+        &["rustfmt"],
+    )?;
+    let config = XtaskConfig::load()?;
+    Ok(Some(config.format.rustfmt_config_arg()))
+}
+
+/// Propose to install the nightly toolchain with the passed components if it is missing.
+pub(crate) fn ensure_nightly_toolchain(
+    purpose: &str,
+    requirer: &str,
+    components: &[&str],
+) -> anyhow::Result<()> {
+    if is_toolchain_installed("nightly") {
+        return Ok(());
+    }
+    if ask_once(&format!("{purpose} requires the nightly toolchain which is not installed.\nIt will be installed with rustup.")) {
+        rustup_install_toolchain("nightly", components)
+    } else {
+        Err(anyhow::anyhow!(
+            "Nightly toolchain is required for {}.",
+            requirer
+        ))
+    }
+}
+
+/// Returns the arguments of a cargo fmt command, using the nightly toolchain and the
+/// unstable rustfmt options if a config is passed.
+pub(crate) fn fmt_args<'a>(args: &[&'a str], nightly_config: Option<&'a str>) -> Vec<&'a str> {
+    match nightly_config {
+        Some(config) => {
+            let mut fmt_args = vec!["+nightly"];
+            fmt_args.extend(args);
+            if !config.is_empty() {
+                fmt_args.extend(["--", "--config", config]);
+            }
+            fmt_args
+        }
+        None => args.to_vec(),
+    }
+}
+
+/// Configuration files of typos looked up at the root of the workspace when no config is passed
+const TYPOS_CONFIG_FILES: &[&str] = &["typos.toml", "_typos.toml", ".typos.toml"];
+
+/// Returns the typos arguments selecting the config file and excluding the passed globs.
+pub(crate) fn typos_config_args(config: Option<&Path>, exclude_paths: &[String]) -> Vec<String> {
+    let mut args = vec![];
+    let config = config.map(invocation_path).or_else(|| {
+        TYPOS_CONFIG_FILES
+            .iter()
+            .map(|file| workspace_root().join(file))
+            .find(|path| path.exists())
+    });
+    if let Some(config) = config {
+        args.extend([
+            "--config".to_string(),
+            config.to_string_lossy().into_owned(),
+        ]);
+    }
+    exclude_paths
+        .iter()
+        .for_each(|glob| args.extend(["--exclude".to_string(), glob.clone()]));
+    args
+}
+
+/// Install typos unless a suitable version is already available in the PATH, for instance
+/// when it has been installed with the package manager of the system.
+pub(crate) fn ensure_typos_is_installed() -> anyhow::Result<()> {
+    match tool_status("typos", Some(TYPOS_VERSION)) {
+        ToolStatus::Available(_) => return Ok(()),
+        ToolStatus::Outdated { path, version } => warn!(
+            "{} version {} is older than the pinned version {}.",
+            path.display(),
+            version.as_deref().unwrap_or("unknown"),
+            TYPOS_VERSION
+        ),
+        ToolStatus::Missing => {}
+    }
+    ensure_cargo_crate_is_installed("typos-cli", None, Some(TYPOS_VERSION), false)
+}
+
+/// Call the passed function for each item and stop at the first error unless keep_going is true.
+/// With keep_going all the items are processed and the errors are aggregated into a single one.
+pub(crate) fn try_for_each_keep_going<T>(
+    items: impl IntoIterator<Item = T>,
+    keep_going: bool,
+    mut f: impl FnMut(T) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if !keep_going {
+        return items.into_iter().try_for_each(f);
+    }
+    let mut errors = vec![];
+    let depth = group_depth();
+    for item in items {
+        let result = f(item);
+        // the groups left open by a failure would indent the next items
+        restore_group_depth(depth);
+        if let Err(e) = result {
+            if is_interrupted() {
+                return Err(e);
+            }
+            // the alternate format includes the causes such as the failed command line
+            errors.push(format!("{:#}", e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "{} error(s) occurred:\n  {}",
+            errors.len(),
+            errors.join("\n  ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::fail_fast(false, "Test failed for 'b'")]
+    #[case::keep_going(
+        true,
+        "2 error(s) occurred:\n  Test failed for 'b': exit code 101\n  Test failed for 'c': exit code 101"
+    )]
+    fn test_try_for_each_keep_going(#[case] keep_going: bool, #[case] expected: &str) {
+        let mut visited = vec![];
+        let err = try_for_each_keep_going(["a", "b", "c"], keep_going, |name| {
+            visited.push(name);
+            if name == "a" {
+                return Ok(());
+            }
+            Err(anyhow::anyhow!("exit code 101").context(format!("Test failed for '{name}'")))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), expected);
+        assert_eq!(visited.len(), if keep_going { 3 } else { 2 });
+    }
+
+    #[rstest]
+    #[case::no_member(0, "Fix.\nNo crate is selected.")]
+    #[case::one_member(1, "Fix.\nThe following crate will be modified:\n  - crate-0")]
+    #[case::few_members(
+        2,
+        "Fix.\nThe following 2 crates will be modified:\n  - crate-0\n  - crate-1"
+    )]
+    fn test_members_confirmation_prompt(#[case] count: usize, #[case] expected: &str) {
+        let members: Vec<String> = (0..count).map(|i| format!("crate-{i}")).collect();
+        assert_eq!(members_confirmation_prompt("Fix.", &members), expected);
+    }
+
+    #[rstest]
+    fn test_members_confirmation_prompt_is_truncated() {
+        let members: Vec<String> = (0..12).map(|i| format!("crate-{i}")).collect();
+        let prompt = members_confirmation_prompt("Fix.", &members);
+        assert!(
+            prompt.starts_with("Fix.\nThe following 12 crates will be modified:\n  - crate-0\n")
+        );
+        assert!(prompt.ends_with("  - crate-9\n  ... and 2 more"));
+        assert!(!prompt.contains("crate-10"));
+    }
+
+    #[rstest]
+    #[case::no_members(&[], vec!["a"], Ok(vec!["a"]))]
+    #[case::members(&["web", "ui"], vec![], Ok(vec!["web", "ui"]))]
+    #[case::members_restricted_by_only(&["web", "ui"], vec!["ui", "core"], Ok(vec!["ui"]))]
+    #[case::no_member_in_only(&["web", "ui"], vec!["core"], Err("None of the --only crates are members of the frontend target: web, ui"))]
+    fn test_target_members_only(
+        #[case] members: &[&str],
+        #[case] only: Vec<&str>,
+        #[case] expected: Result<Vec<&str>, &str>,
+    ) {
+        let only = only.into_iter().map(String::from).collect();
+        let result = target_members_only(&"frontend", members, only).map_err(|e| e.to_string());
+        assert_eq!(
+            result,
+            expected
+                .map(|m| m.into_iter().map(String::from).collect())
+                .map_err(String::from)
+        );
+    }
+
+    #[rstest]
+    fn test_typos_config_args() {
+        assert_eq!(
+            typos_config_args(
+                Some(Path::new("config/typos.toml")),
+                &["*.generated.rs".to_string(), "assets/**".to_string()]
+            ),
+            vec![
+                "--config",
+                "config/typos.toml",
+                "--exclude",
+                "*.generated.rs",
+                "--exclude",
+                "assets/**"
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_typos_writes_changes_only_when_fixing() {
+        let config_args = typos_config_args(Some(Path::new("typos.toml")), &[]);
+        let check_args = check::typos_args(&config_args);
+        assert!(!check_args.iter().any(|arg| arg == "--write-changes"));
+        // --diff exits successfully even when typos are found
+        assert!(!check_args.iter().any(|arg| arg == "--diff"));
+        assert!(fix::typos_args(&config_args).contains(&"--write-changes"));
+    }
+
+    #[rstest]
+    fn test_audit_args() {
+        let ignored = vec!["RUSTSEC-2020-0071".to_string()];
+        assert_eq!(
+            check::audit_args(&["-q"], true, &ignored),
+            vec![
+                "audit",
+                "-q",
+                "--deny",
+                "warnings",
+                "--ignore",
+                "RUSTSEC-2020-0071"
+            ]
+        );
+        assert_eq!(check::audit_args(&["-q"], false, &[]), vec!["audit", "-q"]);
+    }
+
+    #[rstest]
+    #[case::workspace(vec![], vec![], "--workspace")]
+    #[case::workspace_exclude(vec!["b"], vec![], "--workspace --exclude b")]
+    #[case::workspace_only(vec![], vec!["a", "b"], "-p a -p b")]
+    #[case::workspace_only_exclude(vec!["b"], vec!["a", "b"], "-p a")]
+    fn test_target_package_args_workspace(
+        #[case] excluded: Vec<&str>,
+        #[case] only: Vec<&str>,
+        #[case] expected: &str,
+    ) {
+        let excluded: Vec<String> = excluded.into_iter().map(String::from).collect();
+        let only: Vec<String> = only.into_iter().map(String::from).collect();
+        assert_eq!(
+            target_package_args(&Target::Workspace, &excluded, &only)
+                .unwrap()
+                .join(" "),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::name(vec!["xtask"], vec!["xtask"])]
+    #[case::star(vec!["burn-*"], vec!["burn-core", "burn-cuda"])]
+    #[case::question_mark(vec!["burn-cud?"], vec!["burn-cuda"])]
+    #[case::unmatched_glob(vec!["tracel-*", "xtask"], vec!["xtask"])]
+    fn test_expand_crate_globs(#[case] patterns: Vec<&str>, #[case] expected: Vec<&str>) {
+        let members: Vec<String> = ["burn-core", "burn-cuda", "xtask"]
+            .map(String::from)
+            .to_vec();
+        let patterns: Vec<String> = patterns.into_iter().map(String::from).collect();
+        assert_eq!(expand_crate_globs(&patterns, &members), expected);
+    }
+
+    #[rstest]
+    #[case::default_members(vec!["a", "b"], vec![])]
+    #[case::non_default_member(vec!["a", "c"], vec!["c"])]
+    #[case::no_only(vec![], vec![])]
+    fn test_non_default_only(#[case] only: Vec<&str>, #[case] expected: Vec<&str>) {
+        let only: Vec<String> = only.into_iter().map(String::from).collect();
+        let non_default = vec!["c".to_string(), "d".to_string()];
+        assert_eq!(non_default_only(&only, &non_default), expected);
+    }
+
+    #[rstest]
+    fn test_add_listed_crates() {
+        let path =
+            std::env::temp_dir().join(format!("xtask-exclude-from-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "# slow crates\nburn-cuda\n\n  burn-wgpu  \n# xtask\nburn-core\n",
+        )
+        .unwrap();
+        let mut exclude = vec!["burn-core".to_string()];
+        let mut only = vec![];
+        let result = add_listed_crates(&mut exclude, Some(&path), &mut only, None);
+        let missing = add_listed_crates(
+            &mut vec![],
+            None,
+            &mut vec![],
+            Some(&path.with_extension("missing")),
+        );
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(exclude, vec!["burn-core", "burn-cuda", "burn-wgpu"]);
+        assert!(only.is_empty());
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed to read the crates list"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{path::Path, process::Command};
+
+use anyhow::Ok;
+
+use crate::{
+    endgroup, group,
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        outcome::record_artifact,
+        process::run_process,
+        workspace::{find_member_target, invocation_path, workspace_root},
+    },
+    versions::FLAMEGRAPH_VERSION,
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct ProfileCmdArgs {}
+
+pub fn handle_command(args: ProfileCmdArgs) -> anyhow::Result<()> {
+    let (kind, name) = match (&args.bin, &args.example, &args.bench) {
+        (Some(bin), _, _) => ("bin", bin),
+        (_, Some(example), _) => ("example", example),
+        (_, _, Some(bench)) => ("bench", bench),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "One of --bin, --example or --bench is required."
+            ))
+        }
+    };
+    let member = find_member_target(kind, name)?;
+    warn_if_perf_is_missing();
+    ensure_cargo_crate_is_installed("flamegraph", None, Some(FLAMEGRAPH_VERSION), false)?;
+    group!("Profile {}: {}", kind, name);
+    let output = args
+        .output
+        .as_deref()
+        .map(invocation_path)
+        .unwrap_or_else(|| workspace_root().join("flamegraph.svg"))
+        .to_string_lossy()
+        .to_string();
+    let kind_arg = format!("--{kind}");
+    let mut cmd_args = vec![
+        "flamegraph",
+        "-p",
+        &member.name,
+        &kind_arg,
+        name,
+        "--output",
+        &output,
+    ];
+    if !args.args.is_empty() {
+        cmd_args.push("--");
+        cmd_args.extend(args.args.iter().map(String::as_str));
+    }
+    run_process(
+        "cargo",
+        &cmd_args,
+        None,
+        None,
+        &format!("Failed to profile {} '{}'", kind, name),
+    )?;
+    endgroup!();
+    info!("Flamegraph written to {}", output);
+    record_artifact(Path::new(&output));
+    Ok(())
+}
+
+/// cargo-flamegraph relies on perf on Linux
+fn warn_if_perf_is_missing() {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    let available = Command::new("perf")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !available {
+        warn!("'perf' is not available, cargo-flamegraph requires it on Linux. Install it with the linux-tools package of your distribution.");
+    }
+}
@@ -1,30 +1,37 @@
-use std::{env, process::Command, str};
+use std::{collections::HashMap, env};
 
 use anyhow::{anyhow, Ok};
+use serde_json::Value;
 
 use crate::{
     endgroup, group,
-    utils::{cargo::parse_cargo_search_output, process::run_process},
+    utils::{
+        cargo::parse_cargo_search_output,
+        git::{current_branch, uncommitted_files},
+        process::{run_process, run_query_with_output},
+        workspace::metadata,
+    },
 };
 
-// Crates.io API token
-const CRATES_IO_API_TOKEN: &str = "CRATES_IO_API_TOKEN";
-
 #[tracel_xtask_macros::declare_command_args(None, None)]
 pub struct PublishCmdArgs {
-    /// The name of the crate to publish on crates.io
+    /// The name of the crate to publish on crates.io or on the registry passed with --registry
     name: String,
 }
 
 pub fn handle_command(args: PublishCmdArgs) -> anyhow::Result<()> {
     let crate_name = args.name;
+    let registry = args.registry.as_deref();
+    let registry_name = registry.unwrap_or("crates.io");
 
-    group!("Publishing crate '{}'...", &crate_name);
+    group!("Publishing crate '{}' to {}...", &crate_name, registry_name);
+    check_publish_ready(std::slice::from_ref(&crate_name))?;
+    check_git_tree(args.allow_dirty, &args.branch)?;
     // Retrieve local version for crate
     let local_version = local_version(&crate_name)?;
     info!("Local version: {local_version}");
     // Retrieve remote version for crate if it exists
-    match remote_version(&crate_name)? {
+    match remote_version(&crate_name, registry)? {
         Some(remote_version) => {
             info!("Found remote version: {remote_version}");
             // Early return if we don't need to publish the crate
@@ -33,10 +40,17 @@ pub fn handle_command(args: PublishCmdArgs) -> anyhow::Result<()> {
                 return Ok(());
             }
         }
-        None => info!("This is the first version to be published on crates.io!"),
+        None => info!("This is the first version to be published on {registry_name}!"),
+    }
+    let token = args.token.or_else(|| env::var(&args.token_env).ok());
+    if token.is_none() {
+        info!(
+            "No token passed with --token or set in {}, using the credentials of the cargo config.",
+            args.token_env
+        );
     }
     // Publish the crate
-    publish(crate_name)?;
+    publish(crate_name, args.allow_dirty, registry, token.as_deref())?;
     endgroup!();
 
     Ok(())
@@ -45,35 +59,43 @@ pub fn handle_command(args: PublishCmdArgs) -> anyhow::Result<()> {
 // Obtain local crate version
 fn local_version(crate_name: &str) -> anyhow::Result<String> {
     // Obtain local crate version contained in cargo pkgid data
-    let cargo_pkgid_output = Command::new("cargo")
-        .args(["pkgid", "-p", crate_name])
-        .output()
-        .map_err(|e| anyhow!("Failed to execute cargo pkgid: {}", e))?;
-    // Convert cargo pkgid output into a str
-    let cargo_pkgid_str = str::from_utf8(&cargo_pkgid_output.stdout)
-        .expect("Failed to convert pkgid output into a str");
-    // Extract only the local crate version from str
+    let cargo_pkgid_output = run_query_with_output("cargo", &["pkgid", "-p", crate_name], None)?;
+    if !cargo_pkgid_output.status.success() {
+        return Err(anyhow!(
+            "Failed to get the local version of '{}': {}",
+            crate_name,
+            String::from_utf8_lossy(&cargo_pkgid_output.stderr).trim()
+        ));
+    }
+    // Extract only the local crate version from the pkgid output
+    let cargo_pkgid_str = String::from_utf8_lossy(&cargo_pkgid_output.stdout);
     let (_, local_version) = cargo_pkgid_str
         .split_once('#')
-        .expect("Failed to get local crate version");
+        .ok_or_else(|| anyhow!("Failed to get the local version of '{}'", crate_name))?;
     Ok(local_version.trim_end().to_string())
 }
 
-// Obtain the crate version from crates.io
-fn remote_version(crate_name: &str) -> anyhow::Result<Option<String>> {
+// Obtain the crate version from crates.io or the passed registry
+fn remote_version(crate_name: &str, registry: Option<&str>) -> anyhow::Result<Option<String>> {
     // Obtain remote crate version contained in cargo search data
-    let cargo_search_output = Command::new("cargo")
-        .args(["search", crate_name, "--limit", "1"])
-        .output()
-        .map_err(|e| anyhow!("Failed to execute cargo search: {}", e))?;
+    let mut args = vec!["search", crate_name, "--limit", "1"];
+    args.extend(registry_args(registry));
+    let cargo_search_output = run_query_with_output("cargo", &args, None)?;
+    if !cargo_search_output.status.success() {
+        return Err(anyhow!(
+            "Failed to search '{}' on {}: {}",
+            crate_name,
+            registry.unwrap_or("crates.io"),
+            String::from_utf8_lossy(&cargo_search_output.stderr).trim()
+        ));
+    }
     // Cargo search returns an empty string in case of a crate not present on crates.io
     if !cargo_search_output.stdout.is_empty() {
-        let output_str = str::from_utf8(&cargo_search_output.stdout).unwrap();
-        // Convert cargo search output into a str
+        let output_str = String::from_utf8_lossy(&cargo_search_output.stdout);
         // as cargo search does not support exact match only we need to make sure that the
         // result returned by cargo search is indeed the crate that we are looking for and not
         // a crate whose name contains the name of the crate we are looking for.
-        if let Some((name, version)) = parse_cargo_search_output(output_str) {
+        if let Some((name, version)) = parse_cargo_search_output(&output_str) {
             if name == crate_name {
                 return Ok(Some(version.to_string()));
             }
@@ -82,26 +104,171 @@ fn remote_version(crate_name: &str) -> anyhow::Result<Option<String>> {
     Ok(None)
 }
 
-fn publish(crate_name: String) -> anyhow::Result<()> {
-    // Perform dry-run to ensure everything is good for publishing
+/// Ensure the manifests of the passed crates have the fields required by crates.io, it fails listing
+/// the missing fields of each crate. A missing repository is only reported with a warning.
+pub(crate) fn check_publish_ready(crates: &[String]) -> anyhow::Result<()> {
+    let metadata = metadata()?;
+    let mut errors = vec![];
+    for name in crates {
+        let Some(package) = metadata["packages"].as_array().and_then(|packages| {
+            packages
+                .iter()
+                .find(|p| p["name"].as_str() == Some(name) && p["source"].is_null())
+        }) else {
+            return Err(anyhow!("The crate '{}' is not a workspace member", name));
+        };
+        let missing = missing_publish_fields(package);
+        if !missing.is_empty() {
+            errors.push(format!("{}: missing {}", name, missing.join(", ")));
+        }
+        if is_blank(&package["repository"]) {
+            warn!(
+                "The crate '{}' has no repository field, crates.io recommends it.",
+                name
+            );
+        }
+    }
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "{} crate(s) miss fields required by crates.io to be published:\n  {}",
+            errors.len(),
+            errors.join("\n  ")
+        ));
+    }
+    info!(
+        "The manifests of {} crate(s) have the fields required to be published.",
+        crates.len()
+    );
+    Ok(())
+}
+
+/// Returns the fields required by crates.io which are missing from the package of the cargo metadata,
+/// `license-file` can replace `license`.
+fn missing_publish_fields(package: &Value) -> Vec<&'static str> {
+    let mut missing = vec![];
+    if is_blank(&package["description"]) {
+        missing.push("description");
+    }
+    if is_blank(&package["license"]) && is_blank(&package["license_file"]) {
+        missing.push("license or license-file");
+    }
+    missing
+}
+
+fn is_blank(value: &Value) -> bool {
+    !value.as_str().is_some_and(|s| !s.trim().is_empty())
+}
+
+// Ensure the published sources are committed and warn when not on the release branch
+fn check_git_tree(allow_dirty: bool, release_branch: &str) -> anyhow::Result<()> {
+    let files = uncommitted_files()?;
+    if !files.is_empty() {
+        if !allow_dirty {
+            return Err(anyhow!(
+                "The git tree has uncommitted changes, commit them or pass --allow-dirty to publish anyway:\n  {}",
+                files.join("\n  ")
+            ));
+        }
+        warn!("Publishing with uncommitted changes as --allow-dirty is set.");
+    }
+    let branch = current_branch()?;
+    if branch != release_branch {
+        warn!(
+            "Publishing from the branch '{}' instead of the release branch '{}'.",
+            branch, release_branch
+        );
+    }
+    Ok(())
+}
+
+fn registry_args(registry: Option<&str>) -> Vec<&str> {
+    registry
+        .map(|registry| vec!["--registry", registry])
+        .unwrap_or_default()
+}
+
+/// Returns the environment variable from which cargo reads the token of the passed registry
+fn registry_token_env(registry: Option<&str>) -> String {
+    match registry {
+        None | Some("crates-io") => "CARGO_REGISTRY_TOKEN".to_string(),
+        Some(name) => format!(
+            "CARGO_REGISTRIES_{}_TOKEN",
+            name.to_uppercase().replace('-', "_")
+        ),
+    }
+}
+
+fn publish(
+    crate_name: String,
+    allow_dirty: bool,
+    registry: Option<&str>,
+    token: Option<&str>,
+) -> anyhow::Result<()> {
+    // the token is passed to cargo in its environment so that it is not visible in the arguments
+    let token_env = registry_token_env(registry);
+    let envs = token.map(|token| HashMap::from([(token_env.as_str(), token)]));
+    let mut dry_run_args = vec!["publish", "-p", &crate_name, "--dry-run"];
+    if allow_dirty {
+        dry_run_args.push("--allow-dirty");
+    }
+    dry_run_args.extend(registry_args(registry));
+    // Perform dry-run to ensure everything is good for publishing, the registries requiring
+    // authentication reject an unauthenticated dry run
     run_process(
         "cargo",
-        &["publish", "-p", &crate_name, "--dry-run"],
-        None,
+        &dry_run_args,
+        envs.clone(),
         None,
         &format!("Publish dry run failed for crate '{}'.", &crate_name),
     )?;
 
-    let crates_io_token =
-        env::var(CRATES_IO_API_TOKEN).expect("Failed to retrieve the crates.io API token");
     // Actually publish the crate
-    let status = Command::new("cargo")
-        .env("CRATES_IO_API_TOKEN", crates_io_token.clone())
-        .args(["publish", "-p", &crate_name, "--token", &crates_io_token])
-        .status()
-        .map_err(|e| anyhow!("Failed to execute cargo publish: {}", e))?;
-    if !status.success() {
-        return Err(anyhow!("Publish failed for crate '{}'.", &crate_name));
+    let mut args = vec!["publish", "-p", &crate_name];
+    if allow_dirty {
+        args.push("--allow-dirty");
+    }
+    args.extend(registry_args(registry));
+    run_process(
+        "cargo",
+        &args,
+        envs,
+        None,
+        &format!("Publish failed for crate '{}'.", &crate_name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::crates_io(None, "CARGO_REGISTRY_TOKEN")]
+    #[case::crates_io_name(Some("crates-io"), "CARGO_REGISTRY_TOKEN")]
+    #[case::registry(Some("my-registry"), "CARGO_REGISTRIES_MY_REGISTRY_TOKEN")]
+    fn test_registry_token_env(#[case] registry: Option<&str>, #[case] expected: &str) {
+        assert_eq!(registry_token_env(registry), expected);
+    }
+
+    #[rstest]
+    #[case::complete(
+        r#"{"description": "A crate", "license": "MIT", "license_file": null}"#,
+        vec![]
+    )]
+    #[case::license_file(
+        r#"{"description": "A crate", "license": null, "license_file": "LICENSE"}"#,
+        vec![]
+    )]
+    #[case::missing_all(
+        r#"{"description": null, "license": null, "license_file": null}"#,
+        vec!["description", "license or license-file"]
+    )]
+    #[case::blank_description(
+        r#"{"description": " ", "license": "MIT", "license_file": null}"#,
+        vec!["description"]
+    )]
+    fn test_missing_publish_fields(#[case] package: &str, #[case] expected: Vec<&str>) {
+        let package: Value = serde_json::from_str(package).unwrap();
+        assert_eq!(missing_publish_fields(&package), expected);
     }
-    Ok(())
 }
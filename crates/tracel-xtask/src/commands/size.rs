@@ -0,0 +1,212 @@
+use anyhow::Ok;
+use serde::Deserialize;
+
+use crate::{
+    endgroup, group,
+    utils::{
+        cargo::ensure_cargo_crate_is_installed, process::run_process_with_output,
+        workspace::find_member_target,
+    },
+    versions::CARGO_BLOAT_VERSION,
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct SizeCmdArgs {}
+
+#[derive(Debug, Deserialize)]
+struct BloatReport {
+    #[serde(rename = "file-size")]
+    file_size: u64,
+    #[serde(rename = "text-section-size")]
+    text_section_size: u64,
+    #[serde(default)]
+    functions: Vec<BloatFunction>,
+    #[serde(default)]
+    crates: Vec<BloatCrate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BloatFunction {
+    #[serde(rename = "crate")]
+    crate_name: Option<String>,
+    name: String,
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BloatCrate {
+    name: String,
+    size: u64,
+}
+
+pub fn handle_command(args: SizeCmdArgs) -> anyhow::Result<()> {
+    let (kind, name) = match (&args.bin, &args.example) {
+        (Some(bin), _) => ("bin", bin),
+        (_, Some(example)) => ("example", example),
+        _ => return Err(anyhow::anyhow!("One of --bin or --example is required.")),
+    };
+    let threshold = args.threshold.as_deref().map(parse_size).transpose()?;
+    let member = find_member_target(kind, name)?;
+    ensure_cargo_crate_is_installed("cargo-bloat", None, Some(CARGO_BLOAT_VERSION), false)?;
+    group!("Size {}: {}", kind, name);
+    let kind_arg = format!("--{kind}");
+    let count = args.count.to_string();
+    let mut cmd_args = vec![
+        "bloat",
+        "-p",
+        &member.name,
+        &kind_arg,
+        name,
+        "--message-format",
+        "json",
+        "-n",
+        &count,
+    ];
+    if args.release {
+        cmd_args.push("--release");
+    }
+    if args.crates {
+        cmd_args.push("--crates");
+    }
+    let output = run_process_with_output("cargo", &cmd_args, None, None)?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to analyze the size of {} '{}'",
+            kind,
+            name
+        ));
+    }
+    let report: BloatReport = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the cargo-bloat report: {}", e))?;
+    report_lines(&report)
+        .iter()
+        .for_each(|line| info!("{}", line));
+    endgroup!();
+    if let Some(threshold) = threshold {
+        if report.file_size > threshold {
+            return Err(anyhow::anyhow!(
+                "The size of {} '{}' is {} which exceeds the threshold of {}",
+                kind,
+                name,
+                format_size(report.file_size),
+                format_size(threshold)
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn report_lines(report: &BloatReport) -> Vec<String> {
+    let mut lines = vec![format!(
+        "File size: {}, .text section size: {}",
+        format_size(report.file_size),
+        format_size(report.text_section_size)
+    )];
+    if !report.crates.is_empty() {
+        lines.push("Largest crates:".to_string());
+        lines.extend(
+            report
+                .crates
+                .iter()
+                .map(|c| format!("  {:>10}  {}", format_size(c.size), c.name)),
+        );
+    }
+    if !report.functions.is_empty() {
+        lines.push("Largest functions:".to_string());
+        lines.extend(report.functions.iter().map(|f| {
+            format!(
+                "  {:>10}  {}  {}",
+                format_size(f.size),
+                f.crate_name.as_deref().unwrap_or("[Unknown]"),
+                f.name
+            )
+        }));
+    }
+    lines
+}
+
+/// Parse a size in bytes with an optional K, M or G suffix (powers of 1024)
+fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let size = size.trim();
+    let upper = size.to_uppercase();
+    let digits = upper
+        .trim_end_matches('B')
+        .trim_end_matches('I')
+        .trim_end_matches(['K', 'M', 'G']);
+    let multiplier = match &upper[digits.len()..] {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow::anyhow!("Invalid size: {}", size)),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .map(|value| value * multiplier)
+        .map_err(|_| anyhow::anyhow!("Invalid size: {}", size))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::bytes("1500", 1500)]
+    #[case::bytes_suffix("1500B", 1500)]
+    #[case::kilo("4K", 4 * 1024)]
+    #[case::mega("5MB", 5 * 1024 * 1024)]
+    #[case::mega_binary("5MiB", 5 * 1024 * 1024)]
+    #[case::giga_lowercase("1g", 1024 * 1024 * 1024)]
+    fn test_parse_size(#[case] size: &str, #[case] expected: u64) {
+        assert_eq!(parse_size(size).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::unknown_unit("5T")]
+    #[case::not_a_number("MB")]
+    fn test_parse_size_invalid(#[case] size: &str) {
+        assert!(parse_size(size).is_err());
+    }
+
+    #[rstest]
+    #[case::bytes(512, "512 B")]
+    #[case::kilo(1536, "1.50 KiB")]
+    #[case::mega(17201504, "16.40 MiB")]
+    fn test_format_size(#[case] bytes: u64, #[case] expected: &str) {
+        assert_eq!(format_size(bytes), expected);
+    }
+
+    #[rstest]
+    fn test_report_lines() {
+        let report: BloatReport = serde_json::from_str(
+            r#"{"file-size":2048,"text-section-size":1024,"crates":[{"name":"std","size":512}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            report_lines(&report),
+            vec![
+                "File size: 2.00 KiB, .text section size: 1.00 KiB",
+                "Largest crates:",
+                "       512 B  std",
+            ]
+        );
+    }
+}
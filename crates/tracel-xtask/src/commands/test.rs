@@ -1,13 +1,30 @@
+use std::collections::BTreeSet;
+
 use anyhow::Result;
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_ONLY_ARGS,
-    endgroup, group,
+    commands::{
+        add_listed_crates,
+        coverage::{
+            clean_profraw_files, generate_coverage_report, install_grcov, profraw_dir,
+            with_coverage_env,
+        },
+        ensure_only_default_members, profile_args, restrict_to_changed_members,
+        target_member_names, try_for_each_keep_going,
+    },
+    current_execution_environment, endgroup, group, group_info, is_coverage_enabled,
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
+        cargo::{ensure_cargo_crate_is_installed, run_cargo_hack},
+        process::{
+            remove_ansi_codes, run_process_for_package, run_process_for_workspace,
+            run_process_with_tee, workspace_package_args,
+        },
+        sccache::warn_if_sccache_with_coverage,
         workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
     },
+    versions::WASM_PACK_VERSION,
+    ExecutionEnvironment,
 };
 
 use super::Target;
@@ -15,27 +32,190 @@ use super::Target;
 #[tracel_xtask_macros::declare_command_args(Target, TestSubCommand)]
 pub struct TestCmdArgs {}
 
-pub fn handle_command(args: TestCmdArgs) -> anyhow::Result<()> {
-    if args.target == Target::Workspace && !args.only.is_empty() {
-        warn!("{}", WARN_IGNORED_ONLY_ARGS);
+/// Cargo test flags of the unit tests: the tests of the libraries and of the binaries
+const UNIT_TEST_FLAGS: [&str; 2] = ["--lib", "--bins"];
+/// Cargo test flags of the integration tests: the test targets of the `tests` directories only,
+/// `--tests` would also run the unit tests as the libraries and binaries are test targets too
+const INTEGRATION_TEST_FLAGS: [&str; 2] = ["--test", "*"];
+/// Cargo test flags of the documentation tests
+const DOC_TEST_FLAGS: [&str; 1] = ["--doc"];
+
+const WORKSPACE_UNIT_TEST_ARGS: [&str; 6] = [
+    "test",
+    "--workspace",
+    "--lib",
+    "--bins",
+    "--color",
+    "always",
+];
+const WORKSPACE_INTEGRATION_TEST_ARGS: [&str; 6] =
+    ["test", "--workspace", "--test", "*", "--color", "always"];
+const WORKSPACE_DOC_TEST_ARGS: [&str; 5] = ["test", "--workspace", "--doc", "--color", "always"];
+
+pub fn handle_command(mut args: TestCmdArgs) -> anyhow::Result<()> {
+    add_listed_crates(
+        &mut args.exclude,
+        args.exclude_from.as_deref(),
+        &mut args.only,
+        args.only_from.as_deref(),
+    )?;
+    ensure_only_default_members(&args.target, &args.only)?;
+    if !restrict_to_changed_members(
+        &mut args.target,
+        &mut args.only,
+        args.changed_only,
+        args.changed_since.as_deref(),
+    ) {
+        return anyhow::Ok(());
+    }
+    if let Some(shard) = args.shard.take() {
+        if !restrict_to_shard(&mut args.target, &mut args.only, &args.exclude, &shard)? {
+            return anyhow::Ok(());
+        }
+    }
+    if current_execution_environment() == ExecutionEnvironment::WasmBrowser {
+        return run_wasm(&args);
+    }
+    if args.clean_first {
+        if args.coverage || is_coverage_enabled() {
+            clean_profraw_files()?;
+        } else {
+            warn!("Ignoring '--clean-first' because the coverage is not enabled.");
+        }
+    }
+    if args.coverage {
+        return run_with_coverage(args);
     }
     match args.get_command() {
         TestSubCommand::Unit => run_unit(&args.target, &args),
         TestSubCommand::Integration => run_integration(&args.target, &args),
-        TestSubCommand::All => TestSubCommand::iter()
-            .filter(|c| *c != TestSubCommand::All)
-            .try_for_each(|c| {
+        TestSubCommand::Doc => run_doc(&args.target, &args),
+        TestSubCommand::All => {
+            if args.no_doc {
+                info!("Skipping the documentation tests because of '--no-doc'.");
+            } else {
+                info!("Running the documentation tests, use '--no-doc' to skip them.");
+            }
+            try_for_each_keep_going(all_test_subcommands(args.no_doc), args.no_fail_fast, |c| {
                 handle_command(TestCmdArgs {
                     command: Some(c),
                     target: args.target.clone(),
                     exclude: args.exclude.clone(),
                     only: args.only.clone(),
+                    changed_only: false,
+                    changed_since: None,
+                    exclude_from: None,
+                    only_from: None,
                     threads: args.threads,
                     jobs: args.jobs,
                     features: args.features.clone(),
                     no_default_features: args.no_default_features,
+                    hack: args.hack,
+                    retries: args.retries,
+                    no_fail_fast: args.no_fail_fast,
+                    release: args.release,
+                    profile: args.profile.clone(),
+                    browser: args.browser.clone(),
+                    coverage: false,
+                    coverage_format: args.coverage_format.clone(),
+                    clean_first: false,
+                    shard: None,
+                    doc: false,
+                    no_doc: false,
                 })
-            }),
+            })
+        }
+    }
+}
+
+/// Returns the tests run by the all subcommand, the documentation tests are skipped with `--no-doc`
+fn all_test_subcommands(no_doc: bool) -> Vec<TestSubCommand> {
+    TestSubCommand::iter()
+        .filter(|c| *c != TestSubCommand::All)
+        .filter(|c| !(no_doc && *c == TestSubCommand::Doc))
+        .collect()
+}
+
+/// Parse a shard written as `<index>/<total>`, the index starts at 1
+fn parse_shard(shard: &str) -> Result<(usize, usize)> {
+    let parsed = shard
+        .split_once('/')
+        .and_then(|(index, total)| Some((index.trim().parse().ok()?, total.trim().parse().ok()?)));
+    match parsed {
+        Some((index, total)) if index >= 1 && index <= total => Ok((index, total)),
+        _ => Err(anyhow::anyhow!(
+            "Invalid shard '{}', expected <index>/<total> with 1 <= index <= total, for instance 1/4",
+            shard
+        )),
+    }
+}
+
+/// Returns the members of the shard, the members sorted by name are dealt to the shards in turn
+/// so that a shard always gets the same members for a given list of members.
+fn shard_members(mut members: Vec<String>, index: usize, total: usize) -> Vec<String> {
+    members.sort();
+    members
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| i % total == index - 1)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Restrict the members to the ones of the passed shard, the workspace target is replaced by
+/// all-packages so that the restriction applies.
+/// Returns false when the shard has no member, in which case there is nothing to run.
+fn restrict_to_shard(
+    target: &mut Target,
+    only: &mut Vec<String>,
+    excluded: &[String],
+    shard: &str,
+) -> Result<bool> {
+    let (index, total) = parse_shard(shard)?;
+    let members = shard_members(target_member_names(target, excluded, only)?, index, total);
+    if members.is_empty() {
+        info!("No workspace member to test in shard {}/{}.", index, total);
+        return Ok(false);
+    }
+    info!(
+        "Workspace members tested by shard {}/{}: {}",
+        index,
+        total,
+        members.join(", ")
+    );
+    if *target == Target::Workspace {
+        *target = Target::AllPackages;
+    }
+    *only = members;
+    Ok(true)
+}
+
+/// Run the tests instrumented for code coverage then generate the coverage report.
+fn run_with_coverage(args: TestCmdArgs) -> Result<()> {
+    install_grcov()?;
+    warn_if_sccache_with_coverage();
+    let profile_dir = profile_target_dir(args.release, args.profile.as_deref()).to_string();
+    let format = args.coverage_format.clone();
+    with_coverage_env(|| {
+        handle_command(TestCmdArgs {
+            coverage: false,
+            clean_first: false,
+            changed_only: false,
+            changed_since: None,
+            exclude_from: None,
+            only_from: None,
+            ..args
+        })
+    })?;
+    generate_coverage_report(&profraw_dir(), &profile_dir, &[], &format)
+}
+
+/// Returns the directory in `target` of the artifacts built with the given profile
+fn profile_target_dir(release: bool, profile: Option<&str>) -> &str {
+    match (release, profile) {
+        (true, _) | (false, Some("release" | "bench")) => "release",
+        (false, None | Some("dev" | "test")) => "debug",
+        (false, Some(profile)) => profile,
     }
 }
 
@@ -52,6 +232,14 @@ fn push_optional_args(cmd_args: &mut Vec<String>, args: &TestCmdArgs) {
     if args.no_default_features {
         cmd_args.push("--no-default-features".to_string());
     }
+    if args.no_fail_fast {
+        cmd_args.push("--no-fail-fast".to_string());
+    }
+    cmd_args.extend(
+        profile_args(args.release, args.profile.as_deref())
+            .into_iter()
+            .map(String::from),
+    );
     // test harness options
     cmd_args.extend(vec!["--".to_string(), "--color=always".to_string()]);
     if let Some(threads) = &args.threads {
@@ -59,28 +247,173 @@ fn push_optional_args(cmd_args: &mut Vec<String>, args: &TestCmdArgs) {
     };
 }
 
+fn run_hack(cmd_args: &[&str], args: &TestCmdArgs, error_msg: &str) -> Result<()> {
+    let mut cmd_args = cmd_args
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+    push_optional_args(&mut cmd_args, args);
+    run_cargo_hack(
+        "test",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        &args.exclude,
+        &args.only,
+        error_msg,
+    )
+}
+
+/// Run a cargo test command then run again the failed tests up to `args.retries` times.
+/// The command is run for the workspace when no package is passed.
+/// Returns the cargo arguments to test a member with the flags selecting the kind of tests
+fn member_test_args(flags: &[&str], member: &str) -> Vec<String> {
+    ["test"]
+        .iter()
+        .chain(flags)
+        .chain(&["-p", member, "--color", "always"])
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Returns the cargo arguments to test the workspace with the optional arguments of the command,
+/// the excluded and only packages are added by the workspace runners.
+fn workspace_test_args(base_args: &[&str], args: &TestCmdArgs) -> Vec<String> {
+    let mut cmd_args: Vec<String> = base_args.iter().map(|s| s.to_string()).collect();
+    push_optional_args(&mut cmd_args, args);
+    cmd_args
+}
+
+fn run_with_retries(
+    cmd_args: &[String],
+    args: &TestCmdArgs,
+    package: Option<&String>,
+    error_msg: &str,
+    ignore_log: Option<&str>,
+    ignore_msg: Option<&str>,
+) -> Result<()> {
+    let mut cmd_args = cmd_args.to_vec();
+    match package {
+        Some(package) => {
+            if args.exclude.contains(package)
+                || (!args.only.is_empty() && !args.only.contains(package))
+            {
+                group_info!("Skip '{}' because it has been excluded!", package);
+                return anyhow::Ok(());
+            }
+        }
+        None => {
+            let workspace_args = cmd_args.iter().map(String::as_str).collect::<Vec<&str>>();
+            cmd_args = workspace_package_args(&workspace_args, &args.exclude, &args.only)
+                .into_iter()
+                .map(String::from)
+                .collect();
+        }
+    }
+    let output = run_process_with_tee(
+        "cargo",
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+    )?;
+    if output.status.success() {
+        return anyhow::Ok(());
+    }
+    if let (Some(log), Some(msg)) = (ignore_log, ignore_msg) {
+        if String::from_utf8_lossy(&output.stderr).contains(log) {
+            info!("{}", msg);
+            return anyhow::Ok(());
+        }
+    }
+    let initially_failed = parse_failed_tests(&String::from_utf8_lossy(&output.stdout));
+    // not a test failure, it could be a compilation error for instance
+    if initially_failed.is_empty() {
+        return Err(anyhow::anyhow!("{}", error_msg));
+    }
+    let mut failed = initially_failed.clone();
+    for attempt in 1..=args.retries {
+        info!(
+            "Retrying {} failed test(s) (attempt {}/{})",
+            failed.len(),
+            attempt,
+            args.retries
+        );
+        let mut retry_args = cmd_args.clone();
+        retry_args.push("--exact".to_string());
+        retry_args.extend(failed.iter().cloned());
+        let output = run_process_with_tee(
+            "cargo",
+            &retry_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            None,
+            None,
+        )?;
+        if output.status.success() {
+            failed.clear();
+            break;
+        }
+        let still_failing = parse_failed_tests(&String::from_utf8_lossy(&output.stdout));
+        if still_failing.is_empty() {
+            return Err(anyhow::anyhow!("{}", error_msg));
+        }
+        failed = still_failing;
+    }
+    let passed_on_retry = initially_failed.len() - failed.len();
+    if passed_on_retry > 0 {
+        warn!("{} test(s) passed only on retry", passed_on_retry);
+    }
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} after {} retries, failing tests: {}",
+            error_msg,
+            args.retries,
+            failed.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    anyhow::Ok(())
+}
+
+/// Returns the names of the failed tests reported by libtest
+fn parse_failed_tests(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .map(remove_ansi_codes)
+        .filter_map(|line| {
+            let name = line.strip_prefix("test ")?.strip_suffix(" ... FAILED")?;
+            let name = name.strip_suffix(" - should panic").unwrap_or(name);
+            Some(name.to_string())
+        })
+        .collect()
+}
+
 pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
+    if args.hack {
+        group!("Unit Tests: feature powerset");
+        run_hack(
+            &["--lib", "--bins", "--color", "always"],
+            args,
+            "Unit Tests failed for some feature sets",
+        )?;
+        endgroup!();
+        return anyhow::Ok(());
+    }
     match target {
         Target::Workspace => {
             info!("Workspace Unit Tests");
-            let mut cmd_args = vec![
-                "test",
-                "--workspace",
-                "--lib",
-                "--bins",
-                "--examples",
-                "--color",
-                "always",
-            ]
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>();
-            push_optional_args(&mut cmd_args, args);
+            let cmd_args = workspace_test_args(&WORKSPACE_UNIT_TEST_ARGS, args);
+            if args.retries > 0 {
+                return run_with_retries(
+                    &cmd_args,
+                    args,
+                    None,
+                    "Workspace Unit Tests failed",
+                    Some("no library targets found"),
+                    Some("No library found to test for in workspace."),
+                );
+            }
             // let cmd_args: Vec<&str> = cmd_args.iter().map(String::as_str).collect();
             run_process_for_workspace(
                 "cargo",
                 &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
                 &args.exclude,
+                &args.only,
                 Some(r".*target/[^/]+/deps/([^-\s]+)"),
                 Some("Unit Tests"),
                 "Workspace Unit Tests failed",
@@ -88,21 +421,31 @@ pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
                 Some("No library found to test for in workspace."),
             )?;
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
-                run_unit_test(&member, args)?;
-            }
+            try_for_each_keep_going(members, args.no_fail_fast, |member| {
+                run_unit_test(&member, args)
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_unit(&t, args))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                args.no_fail_fast,
+                |t| run_unit(&t, args),
+            )?;
         }
     }
     anyhow::Ok(())
@@ -110,49 +453,68 @@ pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
 
 fn run_unit_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<(), anyhow::Error> {
     group!("Unit Tests: {}", member.name);
-    let mut cmd_args = vec![
-        "test",
-        "--lib",
-        "--bins",
-        "--examples",
-        "-p",
-        &member.name,
-        "--color=always",
-    ]
-    .into_iter()
-    .map(|s| s.to_string())
-    .collect::<Vec<String>>();
+    let mut cmd_args = member_test_args(&UNIT_TEST_FLAGS, &member.name);
     push_optional_args(&mut cmd_args, args);
-    run_process_for_package(
-        "cargo",
-        &member.name,
-        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
-        &args.exclude,
-        &args.only,
-        &format!("Failed to execute unit test for '{}'", &member.name),
-        Some("no library targets found"),
-        Some(&format!(
-            "No library found to test for in the crate '{}'.",
-            &member.name
-        )),
-    )?;
+    let error_msg = format!("Failed to execute unit test for '{}'", &member.name);
+    let ignore_msg = format!(
+        "No library found to test for in the crate '{}'.",
+        &member.name
+    );
+    if args.retries > 0 {
+        run_with_retries(
+            &cmd_args,
+            args,
+            Some(&member.name),
+            &error_msg,
+            Some("no library targets found"),
+            Some(&ignore_msg),
+        )?;
+    } else {
+        run_process_for_package(
+            "cargo",
+            &member.name,
+            &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            &args.exclude,
+            &args.only,
+            &error_msg,
+            Some("no library targets found"),
+            Some(&ignore_msg),
+        )?;
+    }
     endgroup!();
     anyhow::Ok(())
 }
 
 pub fn run_integration(target: &Target, args: &TestCmdArgs) -> anyhow::Result<()> {
+    if args.hack {
+        group!("Integration Tests: feature powerset");
+        run_hack(
+            &["--test", "*", "--color", "always"],
+            args,
+            "Integration Tests failed for some feature sets",
+        )?;
+        endgroup!();
+        return anyhow::Ok(());
+    }
     match target {
         Target::Workspace => {
             info!("Workspace Integration Tests");
-            let mut cmd_args = vec!["test", "--workspace", "--test", "*", "--color", "always"]
-                .into_iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-            push_optional_args(&mut cmd_args, args);
+            let cmd_args = workspace_test_args(&WORKSPACE_INTEGRATION_TEST_ARGS, args);
+            if args.retries > 0 {
+                return run_with_retries(
+                    &cmd_args,
+                    args,
+                    None,
+                    "Workspace Integration Tests failed",
+                    Some("no test target matches pattern"),
+                    Some("No tests found matching the pattern `test_*` in workspace."),
+                );
+            }
             run_process_for_workspace(
                 "cargo",
                 &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
                 &args.exclude,
+                &args.only,
                 Some(r".*target/[^/]+/deps/([^-\s]+)"),
                 Some("Integration Tests"),
                 "Workspace Integration Tests failed",
@@ -160,21 +522,31 @@ pub fn run_integration(target: &Target, args: &TestCmdArgs) -> anyhow::Result<()
                 Some("No tests found matching the pattern `test_*` in workspace."),
             )?;
         }
-        Target::Crates | Target::Examples => {
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
             };
 
-            for member in members {
-                run_integration_test(&member, args)?;
-            }
+            try_for_each_keep_going(members, args.no_fail_fast, |member| {
+                run_integration_test(&member, args)
+            })?;
         }
         Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_integration(&t, args))?;
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                args.no_fail_fast,
+                |t| run_integration(&t, args),
+            )?;
         }
     }
     anyhow::Ok(())
@@ -182,18 +554,171 @@ pub fn run_integration(target: &Target, args: &TestCmdArgs) -> anyhow::Result<()
 
 fn run_integration_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<()> {
     group!("Integration Tests: {}", &member.name);
+    let mut cmd_args = member_test_args(&INTEGRATION_TEST_FLAGS, &member.name);
+    push_optional_args(&mut cmd_args, args);
+    let error_msg = format!("Failed to execute integration test for '{}'", &member.name);
+    let ignore_msg = format!(
+        "No tests found matching the pattern `test_*` for '{}'.",
+        &member.name
+    );
+    if args.retries > 0 {
+        run_with_retries(
+            &cmd_args,
+            args,
+            Some(&member.name),
+            &error_msg,
+            Some("no test target matches pattern"),
+            Some(&ignore_msg),
+        )?;
+    } else {
+        run_process_for_package(
+            "cargo",
+            &member.name,
+            &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            &args.exclude,
+            &args.only,
+            &error_msg,
+            Some("no test target matches pattern"),
+            Some(&ignore_msg),
+        )?;
+    }
+    endgroup!();
+    anyhow::Ok(())
+}
+
+/// Run the tests of the target members in a headless browser with wasm-pack.
+/// Doctests, binaries and examples cannot run in a browser and are skipped.
+fn run_wasm(args: &TestCmdArgs) -> Result<()> {
+    if args.get_command() == TestSubCommand::Doc {
+        return Err(anyhow::anyhow!(
+            "Doctests are host-only and cannot run in the wasm execution environment."
+        ));
+    }
+    ensure_cargo_crate_is_installed("wasm-pack", None, Some(WASM_PACK_VERSION), false)?;
+    if args.hack || args.retries > 0 {
+        warn!("--hack and --retries are not supported in the wasm execution environment, they are ignored.");
+    }
+    warn!("Doctests, binaries and examples are host-only and are skipped in the wasm execution environment.");
+    let members = match args.target {
+        Target::Workspace | Target::AllPackages => {
+            let mut members = get_workspace_members(WorkspaceMemberType::Crate)?;
+            members.extend(get_workspace_members(WorkspaceMemberType::Example)?);
+            members
+        }
+        Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+        Target::PublishableCrates => get_workspace_members(WorkspaceMemberType::PublishableCrate)?,
+        Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+    };
+    try_for_each_keep_going(members, args.no_fail_fast, |member| {
+        group!("Wasm Tests: {}", member.name);
+        let cmd_args = wasm_pack_args(&member, args);
+        run_process_for_package(
+            "wasm-pack",
+            &member.name,
+            &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            &args.exclude,
+            &args.only,
+            &format!("Failed to execute wasm tests for '{}'", &member.name),
+            Some("no test target matches pattern"),
+            Some(&format!("No tests found for '{}'.", &member.name)),
+        )?;
+        endgroup!();
+        anyhow::Ok(())
+    })
+}
+
+/// Returns the wasm-pack arguments to test the member, the arguments after `--` are passed to cargo test
+fn wasm_pack_args(member: &WorkspaceMember, args: &TestCmdArgs) -> Vec<String> {
     let mut cmd_args = vec![
-        "test",
-        "--test",
-        "*",
-        "-p",
-        &member.name,
-        "--color",
-        "always",
-    ]
-    .into_iter()
-    .map(|s| s.to_string())
-    .collect::<Vec<String>>();
+        "test".to_string(),
+        "--headless".to_string(),
+        format!("--{}", args.browser),
+        member.path.clone(),
+        "--".to_string(),
+    ];
+    match args.get_command() {
+        TestSubCommand::Unit => cmd_args.push("--lib".to_string()),
+        TestSubCommand::Integration => cmd_args.extend(["--test".to_string(), "*".to_string()]),
+        TestSubCommand::All => cmd_args.extend(["--lib".to_string(), "--tests".to_string()]),
+        // rejected by run_wasm
+        TestSubCommand::Doc => {}
+    }
+    if let Some(features) = &args.features {
+        if !features.is_empty() {
+            cmd_args.extend(vec!["--features".to_string(), features.join(",")]);
+        }
+    }
+    if args.no_default_features {
+        cmd_args.push("--no-default-features".to_string());
+    }
+    cmd_args.extend(
+        profile_args(args.release, args.profile.as_deref())
+            .into_iter()
+            .map(String::from),
+    );
+    cmd_args
+}
+
+pub fn run_doc(target: &Target, args: &TestCmdArgs) -> anyhow::Result<()> {
+    if args.hack {
+        group!("Doc Tests: feature powerset");
+        run_hack(
+            &["--doc", "--color", "always"],
+            args,
+            "Doc Tests failed for some feature sets",
+        )?;
+        endgroup!();
+        return anyhow::Ok(());
+    }
+    match target {
+        Target::Workspace => {
+            info!("Workspace Doc Tests");
+            let cmd_args = workspace_test_args(&WORKSPACE_DOC_TEST_ARGS, args);
+            run_process_for_workspace(
+                "cargo",
+                &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+                &args.exclude,
+                &args.only,
+                Some(r"Doc-tests (\w+)"),
+                Some("Doc Tests"),
+                "Workspace Doc Tests failed",
+                None,
+                None,
+            )?;
+        }
+        Target::Crates | Target::Examples | Target::PublishableCrates => {
+            let members = match target {
+                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate)?,
+                Target::PublishableCrates => {
+                    get_workspace_members(WorkspaceMemberType::PublishableCrate)?
+                }
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example)?,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "{} target is not supported by this command.",
+                        target
+                    ))
+                }
+            };
+
+            try_for_each_keep_going(members, args.no_fail_fast, |member| {
+                run_doc_test(&member, args)
+            })?;
+        }
+        Target::AllPackages => {
+            try_for_each_keep_going(
+                Target::iter().filter(|t| matches!(t, Target::Crates | Target::Examples)),
+                args.no_fail_fast,
+                |t| run_doc(&t, args),
+            )?;
+        }
+    }
+    anyhow::Ok(())
+}
+
+fn run_doc_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<()> {
+    group!("Doc Tests: {}", &member.name);
+    let mut cmd_args = member_test_args(&DOC_TEST_FLAGS, &member.name);
     push_optional_args(&mut cmd_args, args);
     run_process_for_package(
         "cargo",
@@ -201,13 +726,138 @@ fn run_integration_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<
         &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
         &args.exclude,
         &args.only,
-        &format!("Failed to execute integration test for '{}'", &member.name),
-        Some("no test target matches pattern"),
+        &format!("Failed to execute doc test for '{}'", &member.name),
+        Some("no library targets found"),
         Some(&format!(
-            "No tests found matching the pattern `test_*` for '{}'.",
+            "No library found to test documentation for in the crate '{}'.",
             &member.name
         )),
     )?;
     endgroup!();
     anyhow::Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::with_doc(false, vec![TestSubCommand::Unit, TestSubCommand::Integration, TestSubCommand::Doc])]
+    #[case::no_doc(true, vec![TestSubCommand::Unit, TestSubCommand::Integration])]
+    fn test_all_test_subcommands(#[case] no_doc: bool, #[case] expected: Vec<TestSubCommand>) {
+        assert!(all_test_subcommands(no_doc) == expected);
+    }
+
+    #[rstest]
+    #[case::first("1/4", Some((1, 4)))]
+    #[case::last("4/4", Some((4, 4)))]
+    #[case::zero_index("0/4", None)]
+    #[case::index_above_total("5/4", None)]
+    #[case::not_a_fraction("2", None)]
+    fn test_parse_shard(#[case] shard: &str, #[case] expected: Option<(usize, usize)>) {
+        assert_eq!(parse_shard(shard).ok(), expected);
+    }
+
+    #[rstest]
+    #[case::first(1, vec!["a", "d"])]
+    #[case::second(2, vec!["b", "e"])]
+    #[case::third(3, vec!["c"])]
+    fn test_shard_members(#[case] index: usize, #[case] expected: Vec<&str>) {
+        let members: Vec<String> = ["e", "c", "a", "d", "b"].map(String::from).to_vec();
+        assert_eq!(shard_members(members, index, 3), expected);
+    }
+
+    #[rstest]
+    #[case::unit(&UNIT_TEST_FLAGS, &["test", "--lib", "--bins", "-p", "my-crate", "--color", "always"])]
+    #[case::integration(&INTEGRATION_TEST_FLAGS, &["test", "--test", "*", "-p", "my-crate", "--color", "always"])]
+    #[case::doc(&DOC_TEST_FLAGS, &["test", "--doc", "-p", "my-crate", "--color", "always"])]
+    fn test_member_test_args(#[case] flags: &[&str], #[case] expected: &[&str]) {
+        assert_eq!(member_test_args(flags, "my-crate"), expected);
+    }
+
+    #[rstest]
+    #[case::default(false, None, "debug")]
+    #[case::release(true, None, "release")]
+    #[case::dev_profile(false, Some("dev"), "debug")]
+    #[case::bench_profile(false, Some("bench"), "release")]
+    #[case::custom_profile(false, Some("ci"), "ci")]
+    fn test_profile_target_dir(
+        #[case] release: bool,
+        #[case] profile: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(profile_target_dir(release, profile), expected);
+    }
+
+    #[rstest]
+    fn test_parse_failed_tests() {
+        let output = "running 4 tests
+test tests::test_ok ... ok
+test tests::test_flaky ... \x1b[31mFAILED\x1b[0m
+test tests::test_panic - should panic ... FAILED
+test tests::test_ignored ... ignored
+
+failures:
+    tests::test_flaky
+    tests::test_panic
+
+test result: FAILED. 1 passed; 2 failed; 1 ignored; 0 measured; 0 filtered out
+test other::test_flaky ... FAILED
+";
+        let failed: Vec<String> = parse_failed_tests(output).into_iter().collect();
+        assert_eq!(
+            failed,
+            vec![
+                "other::test_flaky",
+                "tests::test_flaky",
+                "tests::test_panic"
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::unit(&["unit"], &["--lib"])]
+    #[case::integration(&["--release", "integration"], &["--test", "*", "--release"])]
+    #[case::all(&["--browser", "firefox", "--features", "a,b"], &["--lib", "--tests", "--features", "a,b"])]
+    fn test_wasm_pack_args(#[case] cli_args: &[&str], #[case] expected_cargo_args: &[&str]) {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: TestCmdArgs,
+        }
+        let cli = <Cli as clap::Parser>::parse_from(
+            std::iter::once("test").chain(cli_args.iter().copied()),
+        );
+        let member = WorkspaceMember {
+            name: "my-crate".to_string(),
+            path: "crates/my-crate".to_string(),
+        };
+        let browser = format!("--{}", cli.args.browser);
+        let mut expected = vec!["test", "--headless", &browser, "crates/my-crate", "--"];
+        expected.extend(expected_cargo_args);
+        assert_eq!(wasm_pack_args(&member, &cli.args), expected);
+    }
+
+    #[rstest]
+    #[case::unit(&WORKSPACE_UNIT_TEST_ARGS, &["test", "--workspace", "--lib", "--bins", "--color", "always", "--exclude", "a", "--exclude", "b", "--", "--color=always"])]
+    #[case::integration(&WORKSPACE_INTEGRATION_TEST_ARGS, &["test", "--workspace", "--test", "*", "--color", "always", "--exclude", "a", "--exclude", "b", "--", "--color=always"])]
+    #[case::doc(&WORKSPACE_DOC_TEST_ARGS, &["test", "--workspace", "--doc", "--color", "always", "--exclude", "a", "--exclude", "b", "--", "--color=always"])]
+    fn test_workspace_test_args_forward_excludes(
+        #[case] base_args: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: TestCmdArgs,
+        }
+        let cli = <Cli as clap::Parser>::parse_from(["test", "--exclude", "a,b"]);
+        let cmd_args = workspace_test_args(base_args, &cli.args);
+        let cmd_args: Vec<&str> = cmd_args.iter().map(String::as_str).collect();
+        assert_eq!(
+            workspace_package_args(&cmd_args, &cli.args.exclude, &cli.args.only),
+            expected
+        );
+    }
+}
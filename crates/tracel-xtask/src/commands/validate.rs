@@ -1,3 +1,7 @@
+use std::fmt;
+
+use crate::utils::steps::run_steps;
+
 use super::{
     check::{CheckCmdArgs, CheckSubCommand},
     test::{TestCmdArgs, TestSubCommand},
@@ -7,40 +11,86 @@ use super::{
 #[tracel_xtask_macros::declare_command_args(None, None)]
 struct ValidateCmdArgs {}
 
+/// A step of the validation, reported in the summary at the end
+enum ValidateStep {
+    Check(CheckSubCommand),
+    Test,
+}
+
+impl fmt::Display for ValidateStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateStep::Check(c) => write!(f, "check {}", c),
+            ValidateStep::Test => write!(f, "test all"),
+        }
+    }
+}
+
 pub fn handle_command(args: ValidateCmdArgs) -> anyhow::Result<()> {
     let target = Target::Workspace;
     let exclude = vec![];
     let only = vec![];
 
-    // checks
-    [
+    let steps = [
         CheckSubCommand::Audit,
         CheckSubCommand::Format,
+        CheckSubCommand::Lockfile,
         CheckSubCommand::Lint,
         CheckSubCommand::Typos,
     ]
-    .iter()
-    .try_for_each(|c| {
-        super::check::handle_command(CheckCmdArgs {
+    .into_iter()
+    .map(ValidateStep::Check)
+    .chain([ValidateStep::Test]);
+    run_steps(steps, false, |step| match step {
+        ValidateStep::Check(c) => super::check::handle_command(CheckCmdArgs {
             target: target.clone(),
             exclude: exclude.clone(),
             only: only.clone(),
+            changed_only: false,
+            changed_since: None,
+            exclude_from: None,
+            only_from: None,
             command: Some(c.clone()),
             ignore_audit: args.ignore_audit,
-        })
-    })?;
-
-    // tests
-    super::test::handle_command(TestCmdArgs {
-        target: target.clone(),
-        exclude: exclude.clone(),
-        only: only.clone(),
-        threads: None,
-        jobs: None,
-        command: Some(TestSubCommand::All),
-        features: None,
-        no_default_features: false,
-    })?;
-
-    Ok(())
+            deny_warnings: false,
+            ignored_advisories: vec![],
+            hack: false,
+            nightly_fmt: false,
+            keep_going: false,
+            summary: false,
+            verbose: false,
+            sarif: None,
+            typos_config: None,
+            exclude_paths: vec![],
+            deny_unused_features: false,
+            diff: None,
+            all_lines: false,
+        }),
+        ValidateStep::Test => super::test::handle_command(TestCmdArgs {
+            target: target.clone(),
+            exclude: exclude.clone(),
+            only: only.clone(),
+            changed_only: false,
+            changed_since: None,
+            exclude_from: None,
+            only_from: None,
+            threads: None,
+            jobs: None,
+            command: Some(TestSubCommand::All),
+            features: None,
+            no_default_features: false,
+            hack: false,
+            retries: 0,
+            no_fail_fast: false,
+            release: false,
+            profile: None,
+            browser: "chrome".to_string(),
+            coverage: false,
+            coverage_format: "lcov".to_string(),
+            clean_first: false,
+            shard: None,
+            doc: false,
+            no_doc: false,
+        }),
+    })
 }
@@ -1,5 +1,5 @@
-use anyhow::{anyhow, Ok};
-use std::process::Command as StdCommand;
+use anyhow::Ok;
+use std::collections::HashMap;
 
 use crate::{
     commands::CARGO_NIGHTLY_MSG,
@@ -120,25 +120,16 @@ impl Sanitizer {
             group!("Sanitizer: {}", self.to_string());
             let retriever = RustupTargetRetriever;
             if self.is_target_supported(&retriever) {
-                let envs = vec![
-                    (
-                        "RUSTFLAGS",
-                        format!("{} {}", self.flags(), Sanitizer::DEFAULT_RUSTFLAGS),
-                    ),
-                    ("RUSTDOCFLAGS", self.flags().to_string()),
-                ];
+                let rustflags = format!("{} {}", self.flags(), Sanitizer::DEFAULT_RUSTFLAGS);
+                let envs = HashMap::from([
+                    ("RUSTFLAGS", rustflags.as_str()),
+                    ("RUSTDOCFLAGS", self.flags()),
+                ]);
 
                 let features = self.cargo_features();
                 let mut args = vec!["test", "--", "--color=always", "--no-capture"];
                 args.extend(features);
-                let status = StdCommand::new("cargo")
-                    .args(&args)
-                    .envs(envs)
-                    .status()
-                    .map_err(|e| anyhow!("Failed to execute instrumentalized test: {}", e))?;
-                if !status.success() {
-                    return Err(anyhow!("Sanitizer found issues!"));
-                }
+                run_process("cargo", &args, Some(envs), None, "Sanitizer found issues!")?;
             } else {
                 info!("No supported target found for this sanitizer.");
             }
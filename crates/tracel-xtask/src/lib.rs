@@ -1,5 +1,6 @@
 pub mod commands;
 pub mod logging;
+pub mod runner;
 pub mod utils;
 mod versions;
 
@@ -7,6 +8,8 @@ mod versions;
 pub mod prelude {
     pub use anyhow;
     pub use clap;
+    pub use clap_complete;
+    pub use clap_mangen;
     pub use derive_more;
     pub use env_logger;
     pub use rand;
@@ -22,48 +25,109 @@ pub mod prelude {
     }
 
     pub use crate::commands as base_commands;
+    pub use crate::commands::bench::BenchCmdArgs;
     pub use crate::commands::build::BuildCmdArgs;
     pub use crate::commands::bump::BumpCmdArgs;
     pub use crate::commands::bump::BumpSubCommand;
     pub use crate::commands::check::CheckCmdArgs;
     pub use crate::commands::check::CheckSubCommand;
     pub use crate::commands::compile::CompileCmdArgs;
+    pub use crate::commands::completions::CompletionsCmdArgs;
     pub use crate::commands::coverage::CoverageCmdArgs;
     pub use crate::commands::dependencies::DependenciesCmdArgs;
     pub use crate::commands::dependencies::DependenciesSubCommand;
     pub use crate::commands::doc::DocCmdArgs;
     pub use crate::commands::doc::DocSubCommand;
+    pub use crate::commands::expand::ExpandCmdArgs;
     pub use crate::commands::fix::FixCmdArgs;
     pub use crate::commands::fix::FixSubCommand;
+    pub use crate::commands::generate::register_generator;
+    pub use crate::commands::generate::CodeGenerator;
+    pub use crate::commands::generate::CommandGenerator;
+    pub use crate::commands::generate::GenerateCmdArgs;
+    pub use crate::commands::graph::GraphCmdArgs;
+    pub use crate::commands::list_targets::ListTargetsCmdArgs;
+    pub use crate::commands::man::ManCmdArgs;
+    pub use crate::commands::profile::ProfileCmdArgs;
     pub use crate::commands::publish::PublishCmdArgs;
+    pub use crate::commands::size::SizeCmdArgs;
     pub use crate::commands::test::TestCmdArgs;
     pub use crate::commands::test::TestSubCommand;
     pub use crate::commands::validate::ValidateCmdArgs;
     pub use crate::commands::vulnerabilities::VulnerabilitiesCmdArgs;
     pub use crate::commands::vulnerabilities::VulnerabilitiesSubCommand;
     pub use crate::commands::Target;
+    pub use crate::current_execution_environment;
     pub use crate::endgroup;
     pub use crate::group;
+    pub use crate::group_error;
     pub use crate::group_info;
+    pub use crate::group_warn;
     pub use crate::init_xtask;
+    pub use crate::init_xtask_with;
+    pub use crate::is_coverage_enabled;
+    pub use crate::logging::LoggerOptions;
+    pub use crate::runner::CustomCommand;
+    pub use crate::runner::CustomCommandArgs;
+    pub use crate::runner::XtaskRunner;
     pub use crate::utils::cargo::ensure_cargo_crate_is_installed;
+    pub use crate::utils::cargo::find_tool;
+    pub use crate::utils::cargo::is_tool_available;
+    pub use crate::utils::cargo::tool_status;
+    pub use crate::utils::cargo::tool_version;
+    pub use crate::utils::cargo::ToolStatus;
+    pub use crate::utils::explain::is_explain_enabled;
+    pub use crate::utils::git;
     pub use crate::utils::helpers;
+    pub use crate::utils::hooks::with_hooks;
+    pub use crate::utils::hooks::Hooks;
+    pub use crate::utils::outcome::with_outcome;
+    pub use crate::utils::outcome::CommandOutcome;
     pub use crate::utils::process::random_port;
     pub use crate::utils::process::run_process;
+    pub use crate::utils::process::run_process_buffered;
     pub use crate::utils::process::run_process_for_package;
     pub use crate::utils::process::run_process_for_workspace;
+    pub use crate::utils::progress::is_progress_enabled;
     pub use crate::utils::prompt::ask_once;
+    pub use crate::utils::prompt::ask_once_with_default;
+    pub use crate::utils::prompt::prompt_select;
+    pub use crate::utils::prompt::prompt_text;
     pub use crate::utils::rustup::is_current_toolchain_nightly;
     pub use crate::utils::rustup::rustup_add_component;
     pub use crate::utils::rustup::rustup_add_target;
     pub use crate::utils::rustup::rustup_get_installed_targets;
+    pub use crate::utils::snapshot::check_generated_output;
     pub use crate::utils::time::format_duration;
+    pub use crate::utils::workspace::workspace_root;
+    pub use crate::xtask_command;
+    pub use crate::ColorChoice;
     pub use crate::Environment;
     pub use crate::ExecutionEnvironment;
+    pub use crate::NoExtraArgs;
     pub use crate::XtaskArgs;
 }
 
-use crate::logging::init_logger;
+use std::{io::IsTerminal, sync::OnceLock};
+
+use crate::logging::{
+    disable_groups, enable_json_output, init_logger_with, install_logger, log_to_file,
+    LoggerOptions,
+};
+use crate::utils::cargo::{
+    disable_tool_installation, disable_version_check, ensure_cross_is_installed,
+};
+use crate::utils::env_file::{load_env_file, parse_env_arg};
+use crate::utils::explain::enable_explain;
+use crate::utils::process::{set_process_options, ProcessOptions};
+use crate::utils::progress::disable_progress;
+use crate::utils::prompt::assume_yes;
+use crate::utils::rustup::{rustup_add_target, rustup_get_installed_targets};
+use crate::utils::sccache::{setup_sccache, warn_if_sccache_with_coverage};
+use crate::utils::workspace::{
+    invocation_path, locate_workspace_root, non_default_member_names, set_workspace_root,
+};
+use crate::utils::{config::XtaskConfig, hooks::Hooks};
 
 // does not re-export strum has it is incompatible with strum macros expansions
 use strum::{Display, EnumIter, EnumString};
@@ -83,7 +147,7 @@ pub enum Environment {
     Production,
 }
 
-#[derive(EnumString, EnumIter, Default, Display, Clone, PartialEq, clap::ValueEnum)]
+#[derive(EnumString, EnumIter, Default, Display, Clone, Debug, PartialEq, clap::ValueEnum)]
 #[strum(serialize_all = "lowercase")]
 pub enum ExecutionEnvironment {
     /// Set the execution environment to all
@@ -94,11 +158,69 @@ pub enum ExecutionEnvironment {
     /// Set the execution environment to std (Rust standard library is available).
     #[default]
     Std,
+    #[strum(to_string = "wasm")]
+    #[value(name = "wasm")]
+    /// Set the execution environment to wasm, code is built for wasm32-unknown-unknown and tests run in a browser.
+    WasmBrowser,
+}
+
+impl ExecutionEnvironment {
+    /// Returns the target triple the code is built for in this execution environment, if any
+    pub fn cargo_target(&self) -> Option<&'static str> {
+        match self {
+            ExecutionEnvironment::WasmBrowser => Some(WASM_TARGET),
+            _ => None,
+        }
+    }
+}
+
+/// Target triple of the wasm execution environment
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+static EXECUTION_ENVIRONMENT: OnceLock<ExecutionEnvironment> = OnceLock::new();
+static COVERAGE_ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Returns the execution environment selected with `--execution-environment`.
+/// It is set by `init_xtask` and defaults to `std` before.
+pub fn current_execution_environment() -> ExecutionEnvironment {
+    EXECUTION_ENVIRONMENT.get().cloned().unwrap_or_default()
 }
 
+/// Returns true if code coverage has been enabled with `--enable-coverage`.
+pub fn is_coverage_enabled() -> bool {
+    COVERAGE_ENABLED.get().copied().unwrap_or_default()
+}
+
+#[derive(EnumString, EnumIter, Default, Display, Clone, Debug, PartialEq, clap::ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum ColorChoice {
+    /// Enable colors when the output is a terminal.
+    #[default]
+    Auto,
+    /// Always enable colors.
+    Always,
+    /// Never enable colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns true if the output should be colored
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Placeholder for the additional global options of an xtask binary which does not declare any.
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct NoExtraArgs {}
+
 #[derive(clap::Parser)]
 #[command(author, version, about, long_about = None)]
-pub struct XtaskArgs<C: clap::Subcommand> {
+pub struct XtaskArgs<C: clap::Subcommand, A: clap::Args = NoExtraArgs> {
     /// Enable code coverage for Rust code if available (see coverage command for more info).
     #[arg(short = 'c', long)]
     pub enable_coverage: bool,
@@ -108,29 +230,286 @@ pub struct XtaskArgs<C: clap::Subcommand> {
     /// Set execution environment (for commands that support it).
     #[arg(short = 'E', long, default_value_t = ExecutionEnvironment::default())]
     pub execution_environment: ExecutionEnvironment,
+    /// Set the coloring of the output of xtask and of the executed commands.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    pub color: ColorChoice,
+    /// Pass --locked to all cargo commands (require Cargo.lock to be up to date).
+    #[arg(long)]
+    pub locked: bool,
+    /// Pass --frozen to all cargo commands (require Cargo.lock and cache to be up to date).
+    #[arg(long)]
+    pub frozen: bool,
+    /// Pass --offline to all cargo commands (run without accessing the network).
+    #[arg(long)]
+    pub offline: bool,
+    /// Kill the executed commands after the given number of seconds, 0 means no timeout.
+    /// Killed commands may leave partial artifacts.
+    #[arg(long, value_name = "SECONDS", default_value_t = 0)]
+    pub timeout: u64,
+    /// Program to run in place of cargo, for instance cross or cargo-zigbuild.
+    /// Defaults to the CARGO environment variable and then to cargo.
+    #[arg(long, value_name = "PATH")]
+    pub cargo_path: Option<std::path::PathBuf>,
+    /// Run the cargo build, check and test commands with cross for cross-compilation.
+    /// cross is installed if needed and requires Docker or Podman.
+    #[arg(long, conflicts_with = "cargo_path")]
+    pub use_cross: bool,
+    /// Do not reinstall the tools whose installed version differs from the version pinned by xtask.
+    #[arg(long)]
+    pub no_version_check: bool,
+    /// Fail instead of installing the missing tools, for the runs without network access. Implied by --offline.
+    #[arg(long)]
+    pub offline_tools: bool,
+    /// Also write the log messages of xtask to the given file without colors, the file is truncated.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<std::path::PathBuf>,
+    /// Do not show a spinner while the commands run, it is never shown on CI or when the output is not a terminal.
+    #[arg(long)]
+    pub no_progress: bool,
+    /// Log the groups as plain lines prefixed with their titles instead of sections, for the log aggregators
+    /// which do not support the group framing. No message is removed.
+    #[arg(long)]
+    pub no_group: bool,
+    /// Include the workspace members which are not part of `workspace.default-members`, they are skipped by default
+    /// like cargo does when it is run without --workspace.
+    #[arg(long)]
+    pub all_members: bool,
+    /// Root directory of the cargo workspace, all the paths are resolved from it.
+    /// Defaults to the workspace containing the current directory.
+    #[arg(long, value_name = "PATH")]
+    pub workspace_root: Option<std::path::PathBuf>,
+    /// Answer yes to the confirmation prompts and use the default answer of the other prompts.
+    /// The default answers are also used when stdin is not a terminal.
+    #[arg(short = 'y', long)]
+    pub assume_yes: bool,
+    /// Compile through sccache by setting RUSTC_WRAPPER for the executed commands, sccache is installed if needed.
+    /// Its statistics are printed at the end of the run, which is also done when RUSTC_WRAPPER already runs sccache.
+    #[arg(long)]
+    pub sccache: bool,
+    /// Pass --message-format to the cargo build, check, clippy and test commands, for instance json for editors
+    /// and tools. With a JSON format the log messages go to stderr and the groups are not logged.
+    #[arg(long, value_name = "FMT")]
+    pub message_format: Option<String>,
+    /// Print the steps of the command and the commands they would execute, with the resolved targets and
+    /// members, without running them. The commands inspecting the workspace and the installed tools still run.
+    #[arg(long)]
+    pub explain: bool,
+    /// Load the environment variables of the executed commands from a dotenv file.
+    /// Defaults to the .env file of the workspace root if it exists.
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Option<std::path::PathBuf>,
+    /// Set an environment variable of the executed commands, it overrides the env file. Can be repeated.
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_arg)]
+    pub envs: Vec<(String, String)>,
+    // additional global options declared by the xtask binary
+    #[command(flatten)]
+    pub extra: A,
     #[command(subcommand)]
     pub command: C,
 }
 
 pub fn init_xtask<C: clap::Subcommand>() -> anyhow::Result<XtaskArgs<C>> {
-    init_logger().init();
-    let args = <XtaskArgs<C> as clap::Parser>::parse();
+    init_xtask_with::<C, NoExtraArgs>()
+}
+
+/// Same as `init_xtask` with additional global options declared by the xtask binary.
+/// Their values are available in the `extra` field of the returned arguments.
+pub fn init_xtask_with<C: clap::Subcommand, A: clap::Args>() -> anyhow::Result<XtaskArgs<C, A>> {
+    let args = <XtaskArgs<C, A> as clap::Parser>::parse();
+    initialize(&args)?;
+    Ok(args)
+}
+
+/// Initialize the logger, the workspace root and the global state of xtask from the parsed arguments.
+pub(crate) fn initialize<C: clap::Subcommand, A: clap::Args>(
+    args: &XtaskArgs<C, A>,
+) -> anyhow::Result<()> {
+    let write_style = if args.color.is_enabled() {
+        env_logger::WriteStyle::Always
+    } else {
+        env_logger::WriteStyle::Never
+    };
+    if args.no_group {
+        disable_groups();
+    }
+    let mut logger = init_logger_with(&LoggerOptions::registered());
+    logger.write_style(write_style);
+    // keep stdout for the JSON messages of cargo
+    if args
+        .message_format
+        .as_deref()
+        .is_some_and(is_json_message_format)
+    {
+        enable_json_output();
+        logger.target(env_logger::Target::Stderr);
+    }
+    if let Some(log_file) = &args.log_file {
+        log_to_file(&mut logger, log_file)?;
+    }
+    install_logger(&mut logger)?;
+
+    // the process options are not set yet, the workspace is located with the cargo of --cargo-path
+    let cargo = ProcessOptions {
+        cargo_path: args.cargo_path.clone(),
+        ..Default::default()
+    }
+    .cargo_program();
+    let workspace_root = match &args.workspace_root {
+        Some(root) => root.clone(),
+        None => locate_workspace_root(&std::env::current_dir()?, &cargo)?,
+    };
+    set_workspace_root(&workspace_root)?;
+    Hooks::new()
+        .with_config(&XtaskConfig::load()?.hooks)
+        .register();
 
     group_info!("Execution environment: {}", args.execution_environment);
+    if EXECUTION_ENVIRONMENT
+        .set(args.execution_environment.clone())
+        .is_err()
+        || COVERAGE_ENABLED.set(args.enable_coverage).is_err()
+    {
+        warn!("Xtask has already been initialized.");
+    }
+
+    let non_default_members = if args.all_members {
+        vec![]
+    } else {
+        non_default_member_names(&workspace_root, &cargo)?
+    };
+    if !non_default_members.is_empty() {
+        group_info!(
+            "Skipping the members outside of workspace.default-members, pass --all-members to include them: {}",
+            non_default_members.join(", ")
+        );
+    }
+
+    let mut envs = load_env_file(args.env_file.as_deref().map(invocation_path).as_deref())?;
+    envs.extend(args.envs.iter().cloned());
+    envs.extend(setup_sccache(args.sccache)?);
+
+    set_process_options(ProcessOptions {
+        color: args.color.clone(),
+        locked: args.locked,
+        frozen: args.frozen,
+        offline: args.offline,
+        timeout: (args.timeout > 0).then(|| std::time::Duration::from_secs(args.timeout)),
+        cargo_path: args.cargo_path.clone(),
+        use_cross: args.use_cross,
+        envs,
+        execution_environment: args.execution_environment.clone(),
+        message_format: args.message_format.clone(),
+        non_default_members,
+    });
+
+    if args.no_version_check {
+        disable_version_check();
+    }
+    if args.offline_tools || args.offline {
+        disable_tool_installation();
+    }
+
+    if args.no_progress {
+        disable_progress();
+    }
+
+    if args.explain {
+        enable_explain();
+        group_info!("Explain mode: the commands are printed and not executed.");
+    }
+
+    if args.assume_yes {
+        assume_yes();
+    }
+
+    if args.use_cross {
+        ensure_cross_is_installed()?;
+    }
+
+    if let Some(target) = args.execution_environment.cargo_target() {
+        if !rustup_get_installed_targets()
+            .lines()
+            .any(|t| t.trim() == target)
+        {
+            rustup_add_target(target)?;
+        }
+    }
 
     // initialize code coverage
     if args.enable_coverage {
         group_info!("Enabling coverage support...");
+        warn_if_sccache_with_coverage();
         setup_coverage()?;
     }
 
-    Ok(args)
+    Ok(())
+}
+
+/// Returns true if the cargo message format, possibly a comma separated list, emits JSON messages
+fn is_json_message_format(format: &str) -> bool {
+    format.split(',').any(|f| f.trim().starts_with("json"))
+}
+
+/// Returns the clap command tree of an xtask binary with the given commands and additional global options.
+pub fn xtask_command<C: clap::Subcommand, A: clap::Args>() -> clap::Command {
+    <XtaskArgs<C, A> as clap::CommandFactory>::command()
 }
 
 fn setup_coverage() -> anyhow::Result<()> {
-    unsafe {
-        std::env::set_var("RUSTFLAGS", "-Cinstrument-coverage");
-        std::env::set_var("LLVM_PROFILE_FILE", "burn-%p-%m.profraw");
-    }
+    commands::coverage::coverage_env_vars()
+        .iter()
+        .for_each(|(key, value)| unsafe { std::env::set_var(key, value) });
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rstest::rstest;
+
+    #[derive(clap::Subcommand)]
+    enum TestCommand {
+        Run,
+    }
+
+    #[derive(clap::Args)]
+    struct TestExtraArgs {
+        #[arg(long)]
+        registry: Option<String>,
+        #[arg(long)]
+        dry_run: bool,
+    }
+
+    #[rstest]
+    fn test_parse_extra_args() {
+        let args = XtaskArgs::<TestCommand, TestExtraArgs>::try_parse_from([
+            "xtask",
+            "--registry",
+            "local",
+            "--dry-run",
+            "--locked",
+            "run",
+        ])
+        .unwrap();
+        assert_eq!(args.extra.registry.as_deref(), Some("local"));
+        assert!(args.extra.dry_run);
+        assert!(args.locked);
+        assert!(matches!(args.command, TestCommand::Run));
+    }
+
+    #[rstest]
+    #[case::json("json", true)]
+    #[case::json_variant("json-diagnostic-rendered-ansi", true)]
+    #[case::list("short,json-render-diagnostics", true)]
+    #[case::human("human", false)]
+    fn test_is_json_message_format(#[case] format: &str, #[case] expected: bool) {
+        assert_eq!(is_json_message_format(format), expected);
+    }
+
+    #[rstest]
+    fn test_parse_without_extra_args() {
+        let args = XtaskArgs::<TestCommand>::try_parse_from(["xtask", "run"]).unwrap();
+        assert_eq!(args.extra, NoExtraArgs {});
+    }
+}
@@ -1,14 +1,102 @@
-use std::io::Write;
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::utils::{outcome::record_warning, process::remove_ansi_codes};
+
+/// Options of the logger created by `init_xtask`, to register before it is called:
+///
+/// ```ignore
+/// LoggerOptions::new()
+///     .module_level("hyper", log::LevelFilter::Warn)
+///     .format_timestamp(None)
+///     .register();
+/// let args = init_xtask::<Command>()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoggerOptions {
+    module_levels: Vec<(String, log::LevelFilter)>,
+    timestamp: Option<env_logger::TimestampPrecision>,
+    show_target: bool,
+}
+
+static LOGGER_OPTIONS: Mutex<Option<LoggerOptions>> = Mutex::new(None);
+
+impl Default for LoggerOptions {
+    fn default() -> Self {
+        Self {
+            module_levels: vec![],
+            timestamp: Some(env_logger::TimestampPrecision::Seconds),
+            show_target: true,
+        }
+    }
+}
+
+impl LoggerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of the records of a module and its submodules, for instance to silence a noisy dependency
+    pub fn module_level(mut self, module: &str, level: log::LevelFilter) -> Self {
+        self.module_levels.push((module.to_string(), level));
+        self
+    }
+
+    /// Set the precision of the timestamp of the records, none to hide it. Not used on CI.
+    pub fn format_timestamp(mut self, timestamp: Option<env_logger::TimestampPrecision>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Show the module of the records, it is shown by default. Not used on CI.
+    pub fn show_target(mut self, show_target: bool) -> Self {
+        self.show_target = show_target;
+        self
+    }
+
+    /// Register the options of the logger created by `init_xtask`, they replace the ones registered before
+    pub fn register(self) {
+        *LOGGER_OPTIONS
+            .lock()
+            .expect("logger options lock should not be poisoned") = Some(self);
+    }
+
+    /// Returns the registered options, the default ones if none were registered
+    pub(crate) fn registered() -> Self {
+        LOGGER_OPTIONS
+            .lock()
+            .expect("logger options lock should not be poisoned")
+            .clone()
+            .unwrap_or_default()
+    }
+}
 
 /// Initialise and create a `env_logger::Builder` which follows the
 /// GitHub Actions logging syntax when running on CI.
 pub fn init_logger() -> env_logger::Builder {
+    init_logger_with(&LoggerOptions::default())
+}
+
+/// Same as `init_logger` with the passed module levels and format options
+pub fn init_logger_with(options: &LoggerOptions) -> env_logger::Builder {
     let mut builder = env_logger::Builder::from_default_env();
     builder.target(env_logger::Target::Stdout);
 
     // Find and setup the correct log level
     builder.filter(None, get_log_level());
+    options.module_levels.iter().for_each(|(module, level)| {
+        builder.filter(Some(module), *level);
+    });
     builder.write_style(env_logger::WriteStyle::Always);
+    builder.format_timestamp(options.timestamp);
+    builder.format_target(options.show_target);
 
     // Custom Formatter for Github Actions
     if std::env::var("CI").is_ok() {
@@ -25,6 +113,88 @@ pub fn init_logger() -> env_logger::Builder {
     builder
 }
 
+/// Set the logger built by the passed builder as the global logger, the warnings it logs are also
+/// recorded in the outcome of the command.
+pub(crate) fn install_logger(builder: &mut env_logger::Builder) -> anyhow::Result<()> {
+    let logger = builder.build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(OutcomeLogger(logger)))
+        .map_err(|e| anyhow::anyhow!("Failed to initialize the logger: {}", e))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Logger forwarding the records to env_logger and recording the warnings
+struct OutcomeLogger(env_logger::Logger);
+
+impl log::Log for OutcomeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() == log::Level::Warn && self.0.matches(record) {
+            record_warning(record.args().to_string());
+        }
+        self.0.log(record);
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Keep the standard output for the JSON messages of cargo: the groups are not logged anymore and
+/// the log records must be written to stderr. It is done by `init_xtask` with a JSON `--message-format`.
+pub fn enable_json_output() {
+    JSON_OUTPUT.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the standard output is kept for the JSON messages of cargo
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::SeqCst)
+}
+
+/// Also write the log records to the passed file, without the color codes.
+/// The file is created if needed and truncated otherwise, the records are still written to stdout,
+/// or to stderr when the standard output is kept for JSON messages.
+pub fn log_to_file(builder: &mut env_logger::Builder, path: &Path) -> anyhow::Result<()> {
+    let file = File::create(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create log file {}: {}", path.display(), e))?;
+    let stdout: Box<dyn Write + Send> = if is_json_output() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    builder.target(env_logger::Target::Pipe(Box::new(TeeWriter {
+        stdout,
+        file,
+    })));
+    Ok(())
+}
+
+/// Logger target writing the records to stdout and their uncolored version to a file
+struct TeeWriter<O: Write, F: Write> {
+    stdout: O,
+    file: F,
+}
+
+impl<O: Write, F: Write> Write for TeeWriter<O, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stdout.write_all(buf)?;
+        self.file
+            .write_all(remove_ansi_codes(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stdout.flush()?;
+        self.file.flush()
+    }
+}
+
 /// Determine the LogLevel for the logger
 fn get_log_level() -> log::LevelFilter {
     // DEBUG
@@ -41,17 +211,135 @@ fn get_log_level() -> log::LevelFilter {
     log::LevelFilter::Info
 }
 
-/// Group Macro
+static GROUPS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Log the groups as plain lines prefixed with their titles instead of sections, it is done by `init_xtask`
+/// with `--no-group`.
+pub fn disable_groups() {
+    GROUPS_DISABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the groups are logged as sections, collapsible ones on CI
+pub fn is_grouping_enabled() -> bool {
+    !GROUPS_DISABLED.load(Ordering::SeqCst)
+}
+
+/// How the groups are framed in the output
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GroupStyle {
+    /// Nested group headers are indented
+    Plain,
+    /// Outermost groups are GitHub Actions groups
+    Ci,
+    /// No framing, the headers and the lines of the groups are prefixed with the titles
+    Flat,
+}
+
+fn group_style() -> GroupStyle {
+    if !is_grouping_enabled() {
+        GroupStyle::Flat
+    } else if std::env::var("CI").is_ok() {
+        GroupStyle::Ci
+    } else {
+        GroupStyle::Plain
+    }
+}
+
+/// Titles of the open groups, the last one is the innermost
+static GROUPS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn open_groups() -> std::sync::MutexGuard<'static, Vec<String>> {
+    GROUPS.lock().expect("groups lock should not be poisoned")
+}
+
+/// Open a group nested in the open ones, it is done by `group!` which passes the module of the caller
+#[doc(hidden)]
+pub fn open_group(target: &str, title: String) {
+    if is_json_output() {
+        return;
+    }
+    let mut groups = open_groups();
+    let style = group_style();
+    let header = group_header(&groups, &title, style);
+    if style == GroupStyle::Ci {
+        println!("{}", header)
+    } else {
+        log!(target: target, log::Level::Info, "{}", header)
+    }
+    groups.push(title);
+}
+
+/// Log a line of the innermost open group, it is done by `group_info!` which passes the module of the caller
+#[doc(hidden)]
+pub fn log_group_info(target: &str, message: String) {
+    if is_json_output() {
+        return;
+    }
+    match group_style() {
+        GroupStyle::Ci => println!("{}", message),
+        GroupStyle::Plain => log!(target: target, log::Level::Info, "{}", message),
+        GroupStyle::Flat => {
+            let line = group_line(&open_groups(), &message);
+            log!(target: target, log::Level::Info, "{}", line)
+        }
+    }
+}
+
+/// Close the innermost open group, it is done by `endgroup!`
+#[doc(hidden)]
+pub fn close_group() {
+    if is_json_output() {
+        return;
+    }
+    let mut groups = open_groups();
+    groups.pop();
+    // GitHub Actions does not nest the groups, only the outermost one is an actual group
+    if groups.is_empty() && group_style() == GroupStyle::Ci {
+        println!("::endgroup::")
+    }
+}
+
+/// Returns the number of open groups
+pub(crate) fn group_depth() -> usize {
+    open_groups().len()
+}
+
+/// Close the groups opened after the passed depth without printing anything, so that the groups left open
+/// by a failure do not indent the output of the following ones.
+pub(crate) fn restore_group_depth(depth: usize) {
+    open_groups().truncate(depth);
+}
+
+/// Returns the header of a group: the title indented by the number of parent groups in plain mode.
+/// On CI the outermost group is a GitHub Actions group and the nested groups are labels prefixed
+/// with the titles of their parents, without groups the header is the bracketed path of the group.
+fn group_header(parents: &[String], title: &str, style: GroupStyle) -> String {
+    match (style, parents.is_empty()) {
+        (GroupStyle::Ci, true) => format!("::group::{title}"),
+        (GroupStyle::Ci, false) => format!("{} > {title}", parents.join(" > ")),
+        (GroupStyle::Plain, _) => format!("{}{title}", "  ".repeat(parents.len())),
+        (GroupStyle::Flat, _) => {
+            let mut path = parents.to_vec();
+            path.push(title.to_string());
+            format!("[{}]", path.join(" > "))
+        }
+    }
+}
+
+/// Returns a line of the open groups prefixed with the title of the innermost one when they are not framed
+fn group_line(groups: &[String], message: &str) -> String {
+    match groups.last() {
+        Some(title) => format!("[{title}] {message}"),
+        None => message.to_string(),
+    }
+}
+
+/// Group Macro, the nested groups are indented
 #[macro_export]
 macro_rules! group {
     // group!()
     ($($arg:tt)*) => {
-        let title = format!($($arg)*);
-        if std::env::var("CI").is_ok() {
-            println!("::group::{}", title)
-        } else {
-            log!(log::Level::Info, "{}", title)
-        }
+        $crate::logging::open_group(module_path!(), format!($($arg)*))
     };
 }
 
@@ -59,13 +347,38 @@ macro_rules! group {
 macro_rules! group_info {
     // group_info!()
     ($($arg:tt)*) => {
-        let title = format!($($arg)*);
-        if std::env::var("CI").is_ok() {
-            println!("{}", title)
+        $crate::logging::log_group_info(module_path!(), format!($($arg)*))
+    };
+}
+
+/// Group Error Macro, the line is an error annotation on CI.
+/// Unlike the other group macros it is still logged when the standard output is kept for JSON messages.
+#[macro_export]
+macro_rules! group_error {
+    // group_error!()
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        if std::env::var("CI").is_ok() && !$crate::logging::is_json_output() {
+            println!("::error::{}", message)
         } else {
-            log!(log::Level::Info, "{}", title)
+            log!(log::Level::Error, "{}", message)
         }
-    };
+    }};
+}
+
+/// Group Warning Macro, the line is a warning annotation on CI.
+/// Unlike the other group macros it is still logged when the standard output is kept for JSON messages.
+#[macro_export]
+macro_rules! group_warn {
+    // group_warn!()
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        if std::env::var("CI").is_ok() && !$crate::logging::is_json_output() {
+            println!("::warning::{}", message)
+        } else {
+            log!(log::Level::Warn, "{}", message)
+        }
+    }};
 }
 
 /// End Group Macro
@@ -73,8 +386,81 @@ macro_rules! group_info {
 macro_rules! endgroup {
     // endgroup!()
     () => {
-        if std::env::var("CI").is_ok() {
-            println!("::endgroup::")
-        }
+        $crate::logging::close_group()
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::outermost(vec![], GroupStyle::Plain, "Lint Workspace")]
+    #[case::nested(vec!["Check", "Lint Workspace"], GroupStyle::Plain, "    Lint: foo")]
+    #[case::outermost_ci(vec![], GroupStyle::Ci, "::group::Lint Workspace")]
+    #[case::nested_ci(
+        vec!["Check", "Lint Workspace"],
+        GroupStyle::Ci,
+        "Check > Lint Workspace > Lint: foo"
+    )]
+    #[case::outermost_flat(vec![], GroupStyle::Flat, "[Lint Workspace]")]
+    #[case::nested_flat(vec!["Lint Workspace"], GroupStyle::Flat, "[Lint Workspace > Lint: foo]")]
+    fn test_group_header(
+        #[case] parents: Vec<&str>,
+        #[case] style: GroupStyle,
+        #[case] expected: &str,
+    ) {
+        let parents: Vec<String> = parents.into_iter().map(String::from).collect();
+        let title = if parents.is_empty() {
+            "Lint Workspace"
+        } else {
+            "Lint: foo"
+        };
+        assert_eq!(group_header(&parents, title, style), expected);
+    }
+
+    #[rstest]
+    #[case::no_group(vec![], "Command line: cargo build")]
+    #[case::innermost_group(
+        vec!["Lint Workspace", "Lint: foo"],
+        "[Lint: foo] Command line: cargo build"
+    )]
+    fn test_group_line(#[case] groups: Vec<&str>, #[case] expected: &str) {
+        let groups: Vec<String> = groups.into_iter().map(String::from).collect();
+        assert_eq!(group_line(&groups, "Command line: cargo build"), expected);
+    }
+
+    #[rstest]
+    #[case::default_level("my_xtask::build", log::Level::Info, true)]
+    #[case::silenced_module("noisy::client", log::Level::Info, false)]
+    #[case::silenced_module_error("noisy::client", log::Level::Error, true)]
+    #[case::verbose_module("chatty", log::Level::Debug, true)]
+    fn test_logger_module_levels(
+        #[case] target: &str,
+        #[case] level: log::Level,
+        #[case] expected: bool,
+    ) {
+        let options = LoggerOptions::new()
+            .module_level("noisy", log::LevelFilter::Error)
+            .module_level("chatty", log::LevelFilter::Debug);
+        let logger = init_logger_with(&options).build();
+        let metadata = log::Metadata::builder().target(target).level(level).build();
+        assert_eq!(log::Log::enabled(&logger, &metadata), expected);
+    }
+
+    #[rstest]
+    fn test_tee_writer_strips_colors_in_file() {
+        let mut writer = TeeWriter {
+            stdout: Vec::new(),
+            file: Vec::new(),
+        };
+        let record = b"\x1b[90m[\x1b[0m\x1b[32mINFO \x1b[0m tracel_xtask\x1b[90m]\x1b[0m Build\n";
+        writer.write_all(record).unwrap();
+        assert_eq!(writer.stdout, record);
+        assert_eq!(
+            String::from_utf8(writer.file).unwrap(),
+            "[INFO  tracel_xtask] Build\n"
+        );
+    }
+}
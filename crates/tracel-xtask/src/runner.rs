@@ -0,0 +1,229 @@
+use std::{ffi::OsString, marker::PhantomData};
+
+use clap::FromArgMatches;
+
+use crate::{
+    initialize,
+    utils::{
+        hooks::with_hooks,
+        outcome::{with_outcome, CommandOutcome},
+        sccache::print_sccache_stats,
+    },
+    xtask_command, NoExtraArgs, XtaskArgs,
+};
+
+/// Command registered at runtime with `XtaskRunner::command`, it holds the name of the command
+/// and the matches of its own arguments.
+#[derive(Clone, Debug)]
+pub struct CustomCommand {
+    pub name: String,
+    pub matches: clap::ArgMatches,
+}
+
+impl FromArgMatches for CustomCommand {
+    fn from_arg_matches(matches: &clap::ArgMatches) -> Result<Self, clap::Error> {
+        match matches.subcommand() {
+            Some((name, matches)) => Ok(Self {
+                name: name.to_string(),
+                matches: matches.clone(),
+            }),
+            None => Err(clap::Error::new(clap::error::ErrorKind::MissingSubcommand)),
+        }
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &clap::ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+// the custom commands are added to the clap command by the runner
+impl clap::Subcommand for CustomCommand {
+    fn augment_subcommands(cmd: clap::Command) -> clap::Command {
+        cmd
+    }
+
+    fn augment_subcommands_for_update(cmd: clap::Command) -> clap::Command {
+        cmd
+    }
+
+    fn has_subcommand(_name: &str) -> bool {
+        false
+    }
+}
+
+/// Arguments passed to the handlers of the custom commands: the global options and the custom command.
+pub type CustomCommandArgs<A = NoExtraArgs> = XtaskArgs<CustomCommand, A>;
+
+type Handler<A> = Box<dyn Fn(CustomCommandArgs<A>) -> anyhow::Result<()>>;
+
+enum ParsedArgs<C: clap::Subcommand, A: clap::Args> {
+    Typed(String, XtaskArgs<C, A>),
+    Custom(usize, CustomCommandArgs<A>),
+}
+
+/// Builder of an xtask binary combining the typed commands `C`, usually the enum of the `base_commands`
+/// macro, with custom commands registered along with their handler. `run` parses the command line,
+/// initializes xtask like `init_xtask_with` and dispatches the command.
+pub struct XtaskRunner<C: clap::Subcommand, A: clap::Args = NoExtraArgs> {
+    commands: Vec<(clap::Command, Handler<A>)>,
+    _marker: PhantomData<C>,
+}
+
+impl<C: clap::Subcommand, A: clap::Args> Default for XtaskRunner<C, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: clap::Subcommand, A: clap::Args> XtaskRunner<C, A> {
+    pub fn new() -> Self {
+        Self {
+            commands: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Register a custom command, its arguments are declared on the passed clap command and are available in
+    /// the `matches` of the custom command. Panics if a command with the same name is already declared.
+    pub fn command(
+        mut self,
+        command: clap::Command,
+        handler: impl Fn(CustomCommandArgs<A>) -> anyhow::Result<()> + 'static,
+    ) -> Self {
+        let name = command.get_name().to_string();
+        assert!(
+            self.clap_command().find_subcommand(&name).is_none(),
+            "Command '{name}' is already declared"
+        );
+        self.commands.push((command, Box::new(handler)));
+        self
+    }
+
+    /// Returns the clap command tree with the typed and the custom commands
+    pub fn clap_command(&self) -> clap::Command {
+        self.commands
+            .iter()
+            .fold(xtask_command::<C, A>(), |cmd, (command, _)| {
+                cmd.subcommand(command.clone())
+            })
+    }
+
+    /// Parse the command line, initialize xtask and run the command. The custom commands are run by their
+    /// handler with the hooks registered for their name, the typed commands are passed to `dispatch`.
+    pub fn run(
+        self,
+        dispatch: impl FnOnce(XtaskArgs<C, A>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.run_with_outcome(dispatch).0
+    }
+
+    /// Same as `run` and also returns the outcome of the command: the steps it ran, the warnings
+    /// it logged, the tools it installed and the files it produced.
+    pub fn run_with_outcome(
+        self,
+        dispatch: impl FnOnce(XtaskArgs<C, A>) -> anyhow::Result<()>,
+    ) -> (anyhow::Result<()>, CommandOutcome) {
+        match self
+            .parse_from(std::env::args_os())
+            .unwrap_or_else(|e| e.exit())
+        {
+            ParsedArgs::Typed(name, args) => with_outcome(&name, || {
+                initialize(&args)?;
+                dispatch(args)
+            }),
+            ParsedArgs::Custom(index, args) => {
+                let name = args.command.name.clone();
+                with_outcome(&name, || {
+                    initialize(&args)?;
+                    let result = with_hooks(&name, || (self.commands[index].1)(args));
+                    print_sccache_stats();
+                    result
+                })
+            }
+        }
+    }
+
+    fn parse_from<I, T>(&self, itr: I) -> Result<ParsedArgs<C, A>, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = self.clap_command().try_get_matches_from(itr)?;
+        let name = matches.subcommand_name().unwrap_or_default().to_string();
+        let custom = matches.subcommand_name().and_then(|name| {
+            self.commands
+                .iter()
+                .position(|(command, _)| command.get_name() == name)
+        });
+        match custom {
+            Some(index) => Ok(ParsedArgs::Custom(
+                index,
+                CustomCommandArgs::<A>::from_arg_matches(&matches)?,
+            )),
+            None => Ok(ParsedArgs::Typed(
+                name,
+                XtaskArgs::<C, A>::from_arg_matches(&matches)?,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[derive(clap::Subcommand)]
+    enum TestCommand {
+        Run,
+    }
+
+    fn runner() -> XtaskRunner<TestCommand> {
+        XtaskRunner::new().command(
+            clap::Command::new("hello").arg(clap::Arg::new("name").long("name")),
+            |_| Ok(()),
+        )
+    }
+
+    #[rstest]
+    fn test_parse_custom_command() {
+        let parsed = runner()
+            .parse_from(["xtask", "--locked", "hello", "--name", "ferris"])
+            .unwrap();
+        let ParsedArgs::Custom(index, args) = parsed else {
+            panic!("hello should be a custom command");
+        };
+        assert_eq!(index, 0);
+        assert!(args.locked);
+        assert_eq!(args.command.name, "hello");
+        assert_eq!(
+            args.command
+                .matches
+                .get_one::<String>("name")
+                .map(String::as_str),
+            Some("ferris")
+        );
+    }
+
+    #[rstest]
+    fn test_parse_typed_command() {
+        let parsed = runner().parse_from(["xtask", "run"]).unwrap();
+        assert!(matches!(
+            parsed,
+            ParsedArgs::Typed(
+                ref name,
+                XtaskArgs {
+                    command: TestCommand::Run,
+                    ..
+                }
+            ) if name == "run"
+        ));
+    }
+
+    #[rstest]
+    #[should_panic(expected = "Command 'run' is already declared")]
+    fn test_duplicate_command() {
+        runner().command(clap::Command::new("run"), |_| Ok(()));
+    }
+}
@@ -1,52 +1,447 @@
-use std::process::Command;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
 
 use anyhow::Ok;
 use regex::Regex;
 
-use crate::{endgroup, group, utils::process::run_process};
+use crate::{
+    endgroup, group,
+    utils::{
+        explain::is_explain_enabled,
+        outcome::record_installed_tool,
+        process::{cargo_program, run_process, run_query_with_output, split_vector},
+    },
+    versions::{CARGO_HACK_VERSION, CROSS_VERSION},
+};
 
-/// Ensure that a cargo crate is installed
+/// Container engines that cross can use to run the cross-compilation toolchains
+const CROSS_CONTAINER_ENGINES: &[&str] = &["docker", "podman"];
+
+/// Parser of the output of a `--version` command
+type VersionParser = fn(&str) -> Option<String>;
+
+/// Command printing the version of a tool installed by a cargo crate along with the parser of its output.
+/// By default the version is read with `cargo <subcommand> --version` for the cargo subcommands and
+/// with `<crate name> --version` for the other crates, it is parsed as the first `x.y.z` version.
+struct ToolVersionCommand {
+    crate_name: &'static str,
+    program: &'static str,
+    args: &'static [&'static str],
+    parse: VersionParser,
+}
+
+/// Tools whose version cannot be read or parsed with the defaults
+const TOOL_VERSION_OVERRIDES: &[ToolVersionCommand] = &[ToolVersionCommand {
+    crate_name: "typos-cli",
+    program: "typos",
+    args: &["--version"],
+    parse: parse_version,
+}];
+
+static VERSION_CHECK_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Disable the verification of the versions of the installed tools against their pinned version,
+/// it is done by `init_xtask` with `--no-version-check`.
+pub fn disable_version_check() {
+    VERSION_CHECK_DISABLED.store(true, Ordering::SeqCst);
+}
+
+static TOOL_INSTALLATION_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Fail instead of installing the missing tools, it is done by `init_xtask` with `--offline-tools` or `--offline`.
+pub fn disable_tool_installation() {
+    TOOL_INSTALLATION_DISABLED.store(true, Ordering::SeqCst);
+}
+
+/// Ensure that a cargo crate is installed.
+/// When a version is passed the version of the already installed tool must match it, otherwise the
+/// crate is reinstalled with the passed version.
+/// The installation is serialized with the other xtask processes by a lock file of the cargo home, a
+/// process waiting for the lock checks again whether the crate is installed before installing it.
+/// When the installation is disabled an error is returned instead.
 pub fn ensure_cargo_crate_is_installed(
     crate_name: &str,
     features: Option<&str>,
     version: Option<&str>,
     locked: bool,
 ) -> anyhow::Result<()> {
-    if !is_cargo_crate_installed(crate_name) {
-        group!("Cargo: install crate '{}'", crate_name);
-        let mut args = vec!["install", crate_name];
-        if locked {
-            args.push("--locked");
-        }
-        if let Some(features) = features {
-            if !features.is_empty() {
-                args.extend(vec!["--features", features]);
+    let Some(mut force) = install_reason(crate_name, version) else {
+        return Ok(());
+    };
+    if TOOL_INSTALLATION_DISABLED.load(Ordering::SeqCst) {
+        return Err(offline_tool_error(crate_name, version, force));
+    }
+    let lock = InstallLock::acquire(&install_locks_dir(), crate_name, INSTALL_LOCK_STALE_AFTER)?;
+    if lock.waited {
+        match install_reason(crate_name, version) {
+            Some(reinstall) => force = reinstall,
+            None => {
+                info!("'{}' has been installed by another process.", crate_name);
+                return Ok(());
             }
         }
-        if let Some(version) = version {
-            args.extend(vec!["--version", version]);
+    }
+    group!("Cargo: install crate '{}'", crate_name);
+    let mut args = vec!["install", crate_name];
+    if locked {
+        args.push("--locked");
+    }
+    if let Some(features) = features {
+        if !features.is_empty() {
+            args.extend(vec!["--features", features]);
         }
-        run_process(
-            "cargo",
-            &args,
-            None,
-            None,
-            &format!("crate '{}' should be installed", crate_name),
-        )?;
-        endgroup!();
     }
+    if let Some(version) = version {
+        args.extend(vec!["--version", version]);
+    }
+    if force {
+        args.push("--force");
+    }
+    run_process(
+        "cargo",
+        &args,
+        None,
+        None,
+        &format!("crate '{}' should be installed", crate_name),
+    )?;
+    if !is_explain_enabled() {
+        record_installed_tool(match version {
+            Some(version) => format!("{crate_name}@{version}"),
+            None => crate_name.to_string(),
+        });
+    }
+    endgroup!();
     Ok(())
 }
 
+/// Returns whether the crate must be installed, with true when the installed tool must be replaced
+/// because its version does not match the passed version.
+fn install_reason(crate_name: &str, version: Option<&str>) -> Option<bool> {
+    let installed = is_cargo_crate_installed(crate_name);
+    let mismatch = match version {
+        Some(version) if installed && !VERSION_CHECK_DISABLED.load(Ordering::SeqCst) => {
+            match installed_tool_version(crate_name) {
+                Some(installed_version) if installed_version != version => {
+                    warn!(
+                        "Installed '{}' version {} does not match the pinned version {}, reinstalling it.",
+                        crate_name, installed_version, version
+                    );
+                    true
+                }
+                Some(_) => false,
+                None => {
+                    warn!(
+                        "Cannot determine the installed version of '{}', skipping the version check.",
+                        crate_name
+                    );
+                    false
+                }
+            }
+        }
+        _ => false,
+    };
+    (!installed || mismatch).then_some(mismatch)
+}
+
+/// Returns the error of a tool which cannot be installed because of the offline mode
+fn offline_tool_error(crate_name: &str, version: Option<&str>, reinstall: bool) -> anyhow::Error {
+    match version {
+        Some(version) if reinstall => anyhow::anyhow!(
+            "Tool '{}' must be reinstalled with version {} and the offline mode is set, install it before running \
+             xtask or pass --no-version-check to use the installed version.",
+            crate_name,
+            version
+        ),
+        Some(version) => anyhow::anyhow!(
+            "Tool '{}' not installed and the offline mode is set, install version {} before running xtask.",
+            crate_name,
+            version
+        ),
+        None => anyhow::anyhow!(
+            "Tool '{}' not installed and the offline mode is set, install it before running xtask.",
+            crate_name
+        ),
+    }
+}
+
+/// Age after which an installation lock is considered left over by a killed process and is removed
+const INSTALL_LOCK_STALE_AFTER: Duration = Duration::from_secs(30 * 60);
+
+/// Interval at which a held installation lock is checked again
+const INSTALL_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Returns the directory of the installation locks in the cargo home, `CARGO_HOME` or `~/.cargo`
+fn install_locks_dir() -> PathBuf {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".cargo"))
+        })
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".xtask-locks")
+}
+
+/// Lock of the installation of a crate held by a single process at a time, the lock file is created
+/// atomically and removed when the lock is dropped.
+struct InstallLock {
+    path: PathBuf,
+    /// true when the lock was held by another process when it was requested
+    waited: bool,
+}
+
+impl InstallLock {
+    fn acquire(dir: &Path, crate_name: &str, stale_after: Duration) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(format!("{crate_name}.lock"));
+        let mut waited = false;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Result::Ok(mut file) => {
+                    // the process id helps to find the holder of a lock
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { path, waited });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path, stale_after) {
+                        warn!("Removing the stale installation lock {}.", path.display());
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if !waited {
+                        info!(
+                            "Waiting for another process to install '{}', {} is locked.",
+                            crate_name,
+                            path.display()
+                        );
+                        waited = true;
+                    }
+                    std::thread::sleep(INSTALL_LOCK_POLL_INTERVAL);
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to create the installation lock {}: {}",
+                        path.display(),
+                        e
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Returns true if the lock file was created longer ago than the passed duration
+fn is_stale(path: &Path, stale_after: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age > stale_after)
+        })
+}
+
+/// Returns the program and the arguments printing the version of the tool installed by the crate
+fn tool_version_command(crate_name: &str) -> (String, Vec<String>, VersionParser) {
+    if let Some(cmd) = TOOL_VERSION_OVERRIDES
+        .iter()
+        .find(|cmd| cmd.crate_name == crate_name)
+    {
+        let args = cmd.args.iter().map(|a| a.to_string()).collect();
+        return (cmd.program.to_string(), args, cmd.parse);
+    }
+    match crate_name.strip_prefix("cargo-") {
+        Some(subcommand) => (
+            "cargo".to_string(),
+            vec![subcommand.to_string(), "--version".to_string()],
+            parse_version,
+        ),
+        None => (
+            crate_name.to_string(),
+            vec!["--version".to_string()],
+            parse_version,
+        ),
+    }
+}
+
+/// Returns the version of the tool installed by the crate
+fn installed_tool_version(crate_name: &str) -> Option<String> {
+    let (program, args, parse) = tool_version_command(crate_name);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run_query_with_output(&program, &args, None).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse(&String::from_utf8_lossy(&output.stdout))
+}
+
 /// Returns true if the passed cargo crate is installed locally
 pub fn is_cargo_crate_installed(crate_name: &str) -> bool {
-    let output = Command::new("cargo")
+    let output = Command::new(cargo_program())
         .arg("install")
         .arg("--list")
         .output()
         .expect("Should get the list of installed cargo commands");
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    output_str.lines().any(|line| line.contains(crate_name))
+    is_crate_in_install_list(&String::from_utf8_lossy(&output.stdout), crate_name)
+}
+
+/// Returns true if the crate is listed in the output of `cargo install --list`.
+/// The crates are listed as `name vX.Y.Z:` lines followed by their indented binaries.
+fn is_crate_in_install_list(list: &str, crate_name: &str) -> bool {
+    list.lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .any(|line| line.split_whitespace().next() == Some(crate_name))
+}
+
+/// Availability of a tool in the PATH
+#[derive(Debug, PartialEq)]
+pub enum ToolStatus {
+    /// The tool is not in the PATH
+    Missing,
+    /// The tool is in the PATH but it is older than the required version, or its version is unknown
+    Outdated {
+        path: PathBuf,
+        version: Option<String>,
+    },
+    /// The tool is in the PATH with a suitable version
+    Available(PathBuf),
+}
+
+/// Returns the path of the tool if it is available in the PATH
+pub fn find_tool(name: &str) -> Option<PathBuf> {
+    which::which(name).ok()
+}
+
+/// Returns true if the tool is available in the PATH
+pub fn is_tool_available(name: &str) -> bool {
+    find_tool(name).is_some()
+}
+
+/// Returns the version reported by `<tool> --version`
+pub fn tool_version(name: &str) -> Option<String> {
+    let output = run_query_with_output(name, &["--version"], None).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the status of the tool, its version is checked against the passed minimum version if any
+pub fn tool_status(name: &str, min_version: Option<&str>) -> ToolStatus {
+    let Some(path) = find_tool(name) else {
+        return ToolStatus::Missing;
+    };
+    let Some(min_version) = min_version else {
+        return ToolStatus::Available(path);
+    };
+    match tool_version(name) {
+        Some(version) if !is_version_older(&version, min_version) => ToolStatus::Available(path),
+        version => ToolStatus::Outdated { path, version },
+    }
+}
+
+/// Returns the first `x.y.z` version of the output of a `--version` command
+pub(crate) fn parse_version(output: &str) -> Option<String> {
+    let re = Regex::new(r"\d+\.\d+\.\d+").unwrap();
+    re.find(output).map(|m| m.as_str().to_string())
+}
+
+/// Returns true if the version is older than the required one, pre-release suffixes are ignored
+pub(crate) fn is_version_older(version: &str, required: &str) -> bool {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.split(['.', '-', '+'])
+            .take(3)
+            .map(|n| n.parse().unwrap_or(0))
+            .collect()
+    };
+    numbers(version) < numbers(required)
+}
+
+/// Ensure that cross is installed and warn when none of the container engines it requires is available
+pub fn ensure_cross_is_installed() -> anyhow::Result<()> {
+    ensure_cargo_crate_is_installed("cross", None, Some(CROSS_VERSION), false)?;
+    let engine = std::env::var("CROSS_CONTAINER_ENGINE").ok();
+    let available = engine
+        .iter()
+        .map(String::as_str)
+        .chain(CROSS_CONTAINER_ENGINES.iter().copied())
+        .any(|engine| {
+            Command::new(engine)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        });
+    if !available {
+        warn!("cross requires Docker or Podman but none of them is available, the commands run with cross will fail.");
+    }
+    Ok(())
+}
+
+/// Run a cargo subcommand over the feature powerset of the selected crates using cargo-hack
+pub fn run_cargo_hack(
+    subcommand: &str,
+    args: &[&str],
+    excluded: &[String],
+    only: &[String],
+    error_msg: &str,
+) -> anyhow::Result<()> {
+    ensure_cargo_crate_is_installed("cargo-hack", None, Some(CARGO_HACK_VERSION), false)?;
+    let hack_args = cargo_hack_args(subcommand, args, excluded, only);
+    run_process(
+        "cargo",
+        &hack_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        None,
+        error_msg,
+    )
+}
+
+/// Build the cargo-hack command line, exclude and only are translated to
+/// cargo-hack --exclude and --package arguments.
+fn cargo_hack_args(
+    subcommand: &str,
+    args: &[&str],
+    excluded: &[String],
+    only: &[String],
+) -> Vec<String> {
+    let (cargo_args, binary_args) = split_vector(args, "--");
+    let mut hack_args = vec![
+        "hack".to_string(),
+        subcommand.to_string(),
+        "--feature-powerset".to_string(),
+    ];
+    if only.is_empty() {
+        hack_args.push("--workspace".to_string());
+        excluded
+            .iter()
+            .for_each(|ex| hack_args.extend(["--exclude".to_string(), ex.clone()]));
+    } else {
+        only.iter()
+            .filter(|p| !excluded.contains(p))
+            .for_each(|p| hack_args.extend(["--package".to_string(), p.clone()]));
+    }
+    hack_args.extend(cargo_args.iter().map(|a| a.to_string()));
+    hack_args.extend(binary_args.iter().map(|a| a.to_string()));
+    hack_args
 }
 
 pub fn parse_cargo_search_output(output: &str) -> Option<(String, String)> {
@@ -69,6 +464,56 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    #[rstest]
+    #[case::missing(None, false, "Tool 'cargo-foo' not installed and the offline mode is set, install it before running xtask.")]
+    #[case::missing_pinned(Some("1.2.3"), false, "Tool 'cargo-foo' not installed and the offline mode is set, install version 1.2.3 before running xtask.")]
+    #[case::outdated(Some("1.2.3"), true, "Tool 'cargo-foo' must be reinstalled with version 1.2.3 and the offline mode is set, install it before running xtask or pass --no-version-check to use the installed version.")]
+    fn test_offline_tool_error(
+        #[case] version: Option<&str>,
+        #[case] reinstall: bool,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            offline_tool_error("cargo-foo", version, reinstall).to_string(),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_install_lock() {
+        let dir = std::env::temp_dir().join(format!("xtask-install-lock-{}", std::process::id()));
+        let stale_after = Duration::from_secs(60);
+        let lock = InstallLock::acquire(&dir, "cargo-foo", stale_after).unwrap();
+        assert!(!lock.waited);
+        let waiting = {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                InstallLock::acquire(&dir, "cargo-foo", stale_after)
+                    .map(|lock| lock.waited)
+                    .unwrap()
+            })
+        };
+        // another crate is not locked
+        assert!(
+            !InstallLock::acquire(&dir, "cargo-bar", stale_after)
+                .unwrap()
+                .waited
+        );
+        std::thread::sleep(INSTALL_LOCK_POLL_INTERVAL * 2);
+        drop(lock);
+        let waited = waiting.join().unwrap();
+        // a lock left over by a killed process is removed once it is stale
+        std::fs::File::create(dir.join("cargo-baz.lock"))
+            .and_then(|file| file.set_modified(SystemTime::now() - stale_after * 2))
+            .unwrap();
+        let stale = InstallLock::acquire(&dir, "cargo-baz", stale_after).map(|lock| lock.waited);
+        let removed = !dir.join("cargo-foo.lock").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(waited);
+        assert!(!stale.unwrap());
+        assert!(removed);
+    }
+
     #[rstest]
     #[case::valid_input("tracel-xtask-macros = \"1.0.1\"", Some(("tracel-xtask-macros", "1.0.1")))]
     #[case::valid_input_with_comments("heat-sdk-cli-macros = \"0.1.0\"    # Macros for Tracel Heat SDK CLI.\n", Some(("heat-sdk-cli-macros", "0.1.0")))]
@@ -83,4 +528,91 @@ mod tests {
         let result = parse_cargo_search_output(input);
         assert_eq!(result, expected);
     }
+
+    #[rstest]
+    #[case::typos("typos-cli 1.24.1\n", Some("1.24.1"))]
+    #[case::cargo_subcommand("cargo-deny 0.16.1\n", Some("0.16.1"))]
+    #[case::with_commit("cross 0.2.5 (19be834 2023-02-06)\n", Some("0.2.5"))]
+    #[case::no_version("unknown\n", None)]
+    fn test_parse_version(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(parse_version(output).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case::cargo_subcommand("cargo-hack", "cargo", vec!["hack", "--version"])]
+    #[case::binary("grcov", "grcov", vec!["--version"])]
+    #[case::override_binary("typos-cli", "typos", vec!["--version"])]
+    fn test_tool_version_command(
+        #[case] crate_name: &str,
+        #[case] program: &str,
+        #[case] args: Vec<&str>,
+    ) {
+        let (actual_program, actual_args, _) = tool_version_command(crate_name);
+        assert_eq!(actual_program, program);
+        assert_eq!(actual_args, args);
+    }
+
+    #[rstest]
+    #[case::older_patch("1.24.0", "1.24.1", true)]
+    #[case::older_major("0.9.9", "1.0.0", true)]
+    #[case::same("1.24.1", "1.24.1", false)]
+    #[case::newer("1.25.0", "1.24.1", false)]
+    #[case::numeric_not_lexicographic("1.10.0", "1.9.0", false)]
+    #[case::pre_release("1.24.1-beta.1", "1.24.1", false)]
+    fn test_is_version_older(
+        #[case] version: &str,
+        #[case] required: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(is_version_older(version, required), expected);
+    }
+
+    #[rstest]
+    fn test_tool_status() {
+        assert_eq!(
+            tool_status("does-not-exist-xtask", None),
+            ToolStatus::Missing
+        );
+        assert!(matches!(
+            tool_status("cargo", Some("1.0.0")),
+            ToolStatus::Available(_)
+        ));
+        assert!(matches!(
+            tool_status("cargo", Some("999.0.0")),
+            ToolStatus::Outdated {
+                version: Some(_),
+                ..
+            }
+        ));
+    }
+
+    const INSTALL_LIST: &str = "cargo-audit v0.21.0:\n    cargo-audit\ncargo-auditable v0.6.4:\n    cargo-auditable\ntypos-cli v1.24.1:\n    typos\n";
+
+    #[rstest]
+    #[case::installed("typos-cli", true)]
+    #[case::binary_name("typos", false)]
+    #[case::installed_with_similar_name("cargo-audit", true)]
+    #[case::substring_of_installed_crates("cargo-a", false)]
+    #[case::not_installed("cargo-hack", false)]
+    fn test_is_crate_in_install_list(#[case] crate_name: &str, #[case] expected: bool) {
+        assert_eq!(is_crate_in_install_list(INSTALL_LIST, crate_name), expected);
+    }
+
+    #[rstest]
+    #[case::workspace(&["--no-deps"], &[], &[], vec!["hack", "clippy", "--feature-powerset", "--workspace", "--no-deps"])]
+    #[case::workspace_with_excluded(&["--no-deps"], &["a"], &[], vec!["hack", "clippy", "--feature-powerset", "--workspace", "--exclude", "a", "--no-deps"])]
+    #[case::only(&["--no-deps"], &[], &["a", "b"], vec!["hack", "clippy", "--feature-powerset", "--package", "a", "--package", "b", "--no-deps"])]
+    #[case::only_and_excluded(&[], &["b"], &["a", "b"], vec!["hack", "clippy", "--feature-powerset", "--package", "a"])]
+    #[case::binary_args(&["--no-deps", "--", "--deny", "warnings"], &[], &[], vec!["hack", "clippy", "--feature-powerset", "--workspace", "--no-deps", "--", "--deny", "warnings"])]
+    fn test_cargo_hack_args(
+        #[case] args: &[&str],
+        #[case] excluded: &[&str],
+        #[case] only: &[&str],
+        #[case] expected: Vec<&str>,
+    ) {
+        let excluded: Vec<String> = excluded.iter().map(|s| s.to_string()).collect();
+        let only: Vec<String> = only.iter().map(|s| s.to_string()).collect();
+        let result = cargo_hack_args("clippy", args, &excluded, &only);
+        assert_eq!(result, expected);
+    }
 }
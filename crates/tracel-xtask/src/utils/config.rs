@@ -0,0 +1,154 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::utils::{hooks::HookConfig, workspace::workspace_root};
+
+/// Name of the xtask configuration file at the root of the workspace
+pub const XTASK_CONFIG_FILE: &str = "xtask.toml";
+
+/// Configuration read from the xtask.toml file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct XtaskConfig {
+    pub coverage: CoverageConfig,
+    pub doc: DocConfig,
+    pub format: FormatConfig,
+    /// Shell commands run before and after the commands, by command name
+    pub hooks: BTreeMap<String, HookConfig>,
+}
+
+/// Configuration of the coverage commands
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CoverageConfig {
+    /// Minimum line coverage percentage of every crate, overridden by `--min-coverage`
+    pub min: Option<f64>,
+    /// Minimum line coverage percentage by crate name, it takes precedence over the minimum of all the crates
+    pub crates: BTreeMap<String, f64>,
+}
+
+/// Configuration of the doc commands
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DocConfig {
+    /// Crates which are not required to document all their public items by `doc check-missing`
+    pub missing_docs_exclude: Vec<String>,
+}
+
+/// Configuration of the format commands
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// Unstable rustfmt options passed with --config when formatting with the nightly toolchain
+    pub rustfmt: BTreeMap<String, toml::Value>,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            rustfmt: BTreeMap::from([
+                (
+                    "group_imports".to_string(),
+                    toml::Value::String("StdExternalCrate".to_string()),
+                ),
+                (
+                    "imports_granularity".to_string(),
+                    toml::Value::String("Crate".to_string()),
+                ),
+            ]),
+        }
+    }
+}
+
+impl FormatConfig {
+    /// Returns the rustfmt options formatted for the --config argument of rustfmt
+    pub fn rustfmt_config_arg(&self) -> String {
+        self.rustfmt
+            .iter()
+            .map(|(key, value)| match value {
+                toml::Value::String(s) => format!("{key}={s}"),
+                v => format!("{key}={v}"),
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl XtaskConfig {
+    /// Load the xtask.toml file at the root of the workspace, returns the default configuration
+    /// if there is no such file.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(&workspace_root().join(XTASK_CONFIG_FILE))
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty_file("", "group_imports=StdExternalCrate,imports_granularity=Crate")]
+    #[case::format_section_without_rustfmt(
+        "[format]",
+        "group_imports=StdExternalCrate,imports_granularity=Crate"
+    )]
+    #[case::custom_options(
+        "[format.rustfmt]\nimports_granularity = \"Module\"\nreorder_imports = true",
+        "imports_granularity=Module,reorder_imports=true"
+    )]
+    fn test_rustfmt_config_arg(#[case] contents: &str, #[case] expected: &str) {
+        let config = XtaskConfig::parse(contents).expect("config should be parsed");
+        assert_eq!(config.format.rustfmt_config_arg(), expected);
+    }
+
+    #[rstest]
+    fn test_coverage_config() {
+        let config =
+            XtaskConfig::parse("[coverage]\nmin = 80\n\n[coverage.crates]\nmy-crate = 62.5")
+                .expect("config should be parsed");
+        assert_eq!(config.coverage.min, Some(80.0));
+        assert_eq!(
+            config.coverage.crates,
+            BTreeMap::from([("my-crate".to_string(), 62.5)])
+        );
+    }
+
+    #[rstest]
+    fn test_doc_config() {
+        let config = XtaskConfig::parse("[doc]\nmissing_docs_exclude = [\"internal\"]")
+            .expect("config should be parsed");
+        assert_eq!(config.doc.missing_docs_exclude, vec!["internal"]);
+    }
+
+    #[rstest]
+    fn test_hooks_config() {
+        let config = XtaskConfig::parse("[hooks.build]\npre = \"make proto\"")
+            .expect("config should be parsed");
+        assert_eq!(
+            config.hooks,
+            BTreeMap::from([(
+                "build".to_string(),
+                HookConfig {
+                    pre: Some("make proto".to_string()),
+                    post: None,
+                }
+            )])
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::BTreeMap, path::Path};
+
+/// Env file loaded when no file is passed with `--env-file`
+pub const DEFAULT_ENV_FILE: &str = ".env";
+
+/// Load the variables of a dotenv file.
+/// The default `.env` file is optional while an explicitly passed file must exist.
+pub fn load_env_file(path: Option<&Path>) -> anyhow::Result<BTreeMap<String, String>> {
+    let (path, explicit) = match path {
+        Some(path) => (path, true),
+        None => (Path::new(DEFAULT_ENV_FILE), false),
+    };
+    if !explicit && !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read env file {}: {}", path.display(), e))?;
+    parse_env_file(&content).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// Parse the content of a dotenv file.
+/// Supported syntax: `KEY=value` lines with an optional `export` prefix, `#` comments,
+/// single-quoted literal values and double-quoted values with `\n`, `\"` and `\\` escapes.
+pub fn parse_env_file(content: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("line {}: expected KEY=VALUE", i + 1))?;
+        let key = key.trim();
+        if !is_valid_name(key) {
+            return Err(anyhow::anyhow!(
+                "line {}: invalid variable name '{}'",
+                i + 1,
+                key
+            ));
+        }
+        vars.insert(
+            key.to_string(),
+            parse_value(value.trim()).map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?,
+        );
+    }
+    Ok(vars)
+}
+
+fn parse_value(value: &str) -> anyhow::Result<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest
+            .find('\'')
+            .ok_or_else(|| anyhow::anyhow!("unterminated single quote"))?;
+        return Ok(rest[..end].to_string());
+    }
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(parsed),
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some(escaped) => parsed.push(escaped),
+                    None => break,
+                },
+                c => parsed.push(c),
+            }
+        }
+        return Err(anyhow::anyhow!("unterminated double quote"));
+    }
+    // unquoted values end at the first inline comment
+    let value = match value.find(" #") {
+        Some(pos) => &value[..pos],
+        None => value,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+/// Returns true if the variable name can be set in the environment of a process
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '\0')
+}
+
+/// Parse the `KEY=VALUE` arguments of `--env`.
+pub fn parse_env_arg(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if is_valid_name(key) => Ok((key.to_string(), value.to_string())),
+        Some((key, _)) => Err(format!("invalid variable name '{}'", key)),
+        None => Err(format!("expected KEY=VALUE, got '{}'", arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_parse_env_file() {
+        let content = r#"
+# database
+export DATABASE_URL=postgres://localhost/db
+EMPTY=
+UNQUOTED = some value # a comment
+SINGLE='literal \n # kept'
+DOUBLE="line1\nline2 \"quoted\""
+"#;
+        let vars = parse_env_file(content).unwrap();
+        assert_eq!(
+            vars,
+            BTreeMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost/db".to_string()
+                ),
+                ("DOUBLE".to_string(), "line1\nline2 \"quoted\"".to_string()),
+                ("EMPTY".to_string(), String::new()),
+                ("SINGLE".to_string(), "literal \\n # kept".to_string()),
+                ("UNQUOTED".to_string(), "some value".to_string()),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case::missing_equal("FOO")]
+    #[case::invalid_name("MY VAR=1")]
+    #[case::unterminated_quote("FOO=\"bar")]
+    fn test_parse_env_file_errors(#[case] content: &str) {
+        let err = parse_env_file(content).unwrap_err();
+        assert!(err.to_string().starts_with("line 1:"), "{}", err);
+    }
+
+    #[rstest]
+    fn test_load_missing_explicit_env_file() {
+        let missing = Path::new("does-not-exist.env");
+        assert!(load_env_file(Some(missing)).is_err());
+    }
+
+    #[rstest]
+    #[case::simple("FOO=bar", Ok(("FOO".to_string(), "bar".to_string())))]
+    #[case::value_with_equal("FOO=a=b", Ok(("FOO".to_string(), "a=b".to_string())))]
+    #[case::empty_value("FOO=", Ok(("FOO".to_string(), String::new())))]
+    #[case::missing_value("FOO", Err("expected KEY=VALUE, got 'FOO'".to_string()))]
+    #[case::missing_key("=bar", Err("invalid variable name ''".to_string()))]
+    #[case::invalid_key("MY VAR=bar", Err("invalid variable name 'MY VAR'".to_string()))]
+    fn test_parse_env_arg(#[case] arg: &str, #[case] expected: Result<(String, String), String>) {
+        assert_eq!(parse_env_arg(arg), expected);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{
+    group_info,
+    utils::process::{is_secret_env, redact_args},
+};
+
+static EXPLAIN_ENABLED: AtomicBool = AtomicBool::new(false);
+static EXPLAINED_COMMANDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Print the commands instead of running them, it is done by `init_xtask` with `--explain`.
+pub fn enable_explain() {
+    EXPLAIN_ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the commands are printed as a plan instead of being run.
+pub fn is_explain_enabled() -> bool {
+    EXPLAIN_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Print the command as the next step of the plan when the explain mode is enabled and returns true,
+/// in which case the command must not be run.
+pub(crate) fn explain_command(
+    program: &str,
+    args: &[String],
+    envs: Option<&HashMap<&str, &str>>,
+    path: Option<&Path>,
+) -> bool {
+    if !is_explain_enabled() {
+        return false;
+    }
+    let step = EXPLAINED_COMMANDS.fetch_add(1, Ordering::SeqCst) + 1;
+    group_info!(
+        "{}",
+        format_step(step, program, &redact_args(args), envs, path)
+    );
+    true
+}
+
+/// Print the passed change of the file system as the next step of the plan when the explain mode is
+/// enabled and returns true, in which case the change must not be made.
+pub(crate) fn explain_write(action: &str) -> bool {
+    if !is_explain_enabled() {
+        return false;
+    }
+    let step = EXPLAINED_COMMANDS.fetch_add(1, Ordering::SeqCst) + 1;
+    group_info!("{step:>3}. {action}");
+    true
+}
+
+/// Returns the line of a command of the plan with its environment variables and its directory
+fn format_step(
+    step: usize,
+    program: &str,
+    args: &[String],
+    envs: Option<&HashMap<&str, &str>>,
+    path: Option<&Path>,
+) -> String {
+    let mut envs: Vec<String> = envs
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| {
+            let value = if is_secret_env(key) { "***" } else { value };
+            format!("{key}=\"{value}\"")
+        })
+        .collect();
+    envs.sort();
+    let mut line = format!("{step:>3}. ");
+    envs.iter()
+        .for_each(|env| line.push_str(&format!("{env} ")));
+    line.push_str(program);
+    args.iter()
+        .for_each(|arg| line.push_str(&format!(" {arg}")));
+    if let Some(path) = path {
+        line.push_str(&format!("  (in {})", path.display()));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::command(None, None, "  1. cargo build --workspace")]
+    #[case::envs(
+        Some(HashMap::from([("RUSTDOCFLAGS", "-D missing_docs"), ("CARGO_INCREMENTAL", "0")])),
+        None,
+        "  1. CARGO_INCREMENTAL=\"0\" RUSTDOCFLAGS=\"-D missing_docs\" cargo build --workspace"
+    )]
+    #[case::secret_envs(
+        Some(HashMap::from([("CARGO_REGISTRY_TOKEN", "secret")])),
+        None,
+        "  1. CARGO_REGISTRY_TOKEN=\"***\" cargo build --workspace"
+    )]
+    #[case::path(
+        None,
+        Some(Path::new("crates/foo")),
+        "  1. cargo build --workspace  (in crates/foo)"
+    )]
+    fn test_format_step(
+        #[case] envs: Option<HashMap<&str, &str>>,
+        #[case] path: Option<&Path>,
+        #[case] expected: &str,
+    ) {
+        let args = vec!["build".to_string(), "--workspace".to_string()];
+        assert_eq!(
+            format_step(1, "cargo", &args, envs.as_ref(), path),
+            expected
+        );
+    }
+}
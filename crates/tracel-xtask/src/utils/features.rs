@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use regex::Regex;
+
+/// Returns the features of the `[features]` table of the passed manifest with the features and
+/// dependencies each of them enables.
+pub fn declared_features(manifest: &str) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+    let manifest: toml::Value = toml::from_str(manifest)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the manifest: {}", e))?;
+    let Some(features) = manifest.get("features").and_then(toml::Value::as_table) else {
+        return Ok(BTreeMap::new());
+    };
+    Ok(features
+        .iter()
+        .map(|(name, enabled)| {
+            let enabled = enabled
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            (name.clone(), enabled)
+        })
+        .collect())
+}
+
+/// Returns the features referenced by the passed source code, either with `feature = "name"` in a
+/// `cfg` attribute or macro, or with the `CARGO_FEATURE_<NAME>` variable of build scripts.
+pub fn referenced_features(source: &str) -> BTreeSet<String> {
+    let cfg_rx = Regex::new(r#"feature\s*=\s*"([^"]+)""#).unwrap();
+    let env_rx = Regex::new(r"CARGO_FEATURE_([A-Z0-9_]+)").unwrap();
+    cfg_rx
+        .captures_iter(source)
+        .map(|c| c[1].to_string())
+        .chain(env_rx.captures_iter(source).map(|c| c[1].to_string()))
+        .collect()
+}
+
+/// Returns the features of the package in the passed directory which are never referenced by its
+/// Rust files. The `default` feature and the features enabling other features or dependencies are
+/// not reported as they are used for what they enable.
+pub fn unused_features(package_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let manifest_path = package_dir.join("Cargo.toml");
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let declared = declared_features(&manifest)?;
+    if declared.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut referenced = BTreeSet::new();
+    collect_referenced_features(package_dir, &mut referenced)?;
+    Ok(declared
+        .into_iter()
+        .filter(|(name, enabled)| {
+            name != "default"
+                && enabled.is_empty()
+                && !referenced.contains(name)
+                && !referenced.contains(&env_feature_name(name))
+        })
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Name of the feature in the `CARGO_FEATURE_<NAME>` variable set by cargo for build scripts
+fn env_feature_name(feature: &str) -> String {
+    feature.to_uppercase().replace('-', "_")
+}
+
+/// Collect the features referenced by the Rust files of the directory, the target directory, the
+/// hidden directories and the nested packages are skipped.
+fn collect_referenced_features(
+    dir: &Path,
+    referenced: &mut BTreeSet<String>,
+) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+    {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if name != "target" && !name.starts_with('.') && !path.join("Cargo.toml").exists() {
+                collect_referenced_features(&path, referenced)?;
+            }
+        } else if name.ends_with(".rs") {
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+            referenced.extend(referenced_features(&source));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_declared_features() {
+        let manifest = r#"
+[package]
+name = "my-crate"
+
+[features]
+default = ["std"]
+std = ["serde/std"]
+simd = []
+"#;
+        let features = declared_features(manifest).unwrap();
+        assert_eq!(
+            features,
+            BTreeMap::from([
+                ("default".to_string(), vec!["std".to_string()]),
+                ("simd".to_string(), vec![]),
+                ("std".to_string(), vec!["serde/std".to_string()]),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case::cfg_attribute(r#"#[cfg(feature = "simd")]"#, vec!["simd"])]
+    #[case::cfg_macro(r#"if cfg!(all(feature="std", not(feature = "no-alloc"))) {}"#, vec!["no-alloc", "std"])]
+    #[case::build_script(r#"env::var("CARGO_FEATURE_NO_ALLOC")"#, vec!["NO_ALLOC"])]
+    #[case::none("fn main() {}", vec![])]
+    fn test_referenced_features(#[case] source: &str, #[case] expected: Vec<&str>) {
+        let expected: BTreeSet<String> = expected.into_iter().map(String::from).collect();
+        assert_eq!(referenced_features(source), expected);
+    }
+
+    #[rstest]
+    fn test_unused_features() {
+        let dir = std::env::temp_dir().join(format!("xtask-features-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[features]\ndefault = []\nstd = [\"serde/std\"]\nsimd = []\nno-alloc = []\nlegacy = []\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "#[cfg(feature = \"simd\")]\nmod simd;",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("build.rs"),
+            "env::var(\"CARGO_FEATURE_NO_ALLOC\");",
+        )
+        .unwrap();
+        let unused = unused_features(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(unused.unwrap(), vec!["legacy"]);
+    }
+}
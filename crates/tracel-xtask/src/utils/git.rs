@@ -0,0 +1,309 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::process::{run_process, run_process_with_output, run_query_with_output};
+
+/// Returns true if the current directory is inside a git repository
+pub fn is_repo() -> bool {
+    run_query_with_output("git", &["rev-parse", "--is-inside-work-tree"], None)
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Returns the root directory of the current git repository
+pub fn repository_root() -> anyhow::Result<PathBuf> {
+    let output = run_query_with_output("git", &["rev-parse", "--show-toplevel"], None)?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Not in a git repository"));
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+/// Returns the absolute paths of the files changed since the passed git reference,
+/// including the uncommitted changes and the untracked files.
+pub fn changed_files(reference: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let root = repository_root()?;
+    let mut files = vec![];
+    for args in [
+        vec!["diff", "--name-only", reference],
+        vec!["ls-files", "--others", "--exclude-standard"],
+    ] {
+        let output = run_query_with_output("git", &args, Some(&root))?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to get the changed files with 'git {}'",
+                args.join(" ")
+            ));
+        }
+        files.extend(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| root.join(line)),
+        );
+    }
+    Ok(files)
+}
+
+/// Lines changed since a git reference per absolute file path, the untracked files are changed as a whole
+#[derive(Debug, Default, PartialEq)]
+pub struct ChangedLines {
+    pub reference: String,
+    files: BTreeMap<PathBuf, Vec<(u64, u64)>>,
+}
+
+impl ChangedLines {
+    /// Returns true if one of the lines from `start` to `end` of the file is changed
+    pub fn contains(&self, file: &Path, start: u64, end: u64) -> bool {
+        self.files.get(file).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|(first, last)| start <= *last && *first <= end)
+        })
+    }
+}
+
+/// Returns the lines changed since the passed git reference, including the uncommitted changes
+/// and the untracked files.
+pub fn changed_lines(reference: &str) -> anyhow::Result<ChangedLines> {
+    let root = repository_root()?;
+    let output = run_query_with_output(
+        "git",
+        &[
+            "diff",
+            "--unified=0",
+            "--no-color",
+            "--no-ext-diff",
+            reference,
+        ],
+        Some(&root),
+    )?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to get the changed lines with 'git diff {}'",
+            reference
+        ));
+    }
+    let mut changed = parse_changed_lines(&root, &String::from_utf8_lossy(&output.stdout));
+    changed.reference = reference.to_string();
+    let output = run_query_with_output(
+        "git",
+        &["ls-files", "--others", "--exclude-standard"],
+        Some(&root),
+    )?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .for_each(|line| {
+            changed.files.insert(root.join(line), vec![(1, u64::MAX)]);
+        });
+    Ok(changed)
+}
+
+/// Parse the line ranges of the new files of a `git diff --unified=0` output, the hunk headers are
+/// `@@ -<old start>[,<old count>] +<new start>[,<new count>] @@`. The removed lines are not part of the
+/// new files, the lines around them are changed instead.
+pub(crate) fn parse_changed_lines(root: &Path, diff: &str) -> ChangedLines {
+    let mut changed = ChangedLines::default();
+    let mut current: Option<PathBuf> = None;
+    for line in diff.lines() {
+        if let Some(file) = line.strip_prefix("+++ ") {
+            current = file.strip_prefix("b/").map(|file| root.join(file));
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            let (Some(file), Some(new)) = (&current, hunk.split(' ').nth(1)) else {
+                continue;
+            };
+            let new = new.trim_start_matches('+');
+            let (start, count) = match new.split_once(',') {
+                Some((start, count)) => (start.parse::<u64>(), count.parse::<u64>()),
+                None => (new.parse::<u64>(), Ok(1)),
+            };
+            let (Ok(start), Ok(count)) = (start, count) else {
+                continue;
+            };
+            let range = match count {
+                0 => (start, start + 1),
+                _ => (start, start + count - 1),
+            };
+            changed.files.entry(file.clone()).or_default().push(range);
+        }
+    }
+    changed
+}
+
+/// Returns the absolute paths of the files staged in the index, the deleted files are skipped
+pub fn staged_files() -> anyhow::Result<Vec<PathBuf>> {
+    diff_files(&["diff", "--cached", "--name-only", "--diff-filter=ACMR"])
+}
+
+/// Returns the absolute paths of the tracked files whose changes are not staged
+pub fn unstaged_files() -> anyhow::Result<Vec<PathBuf>> {
+    diff_files(&["diff", "--name-only"])
+}
+
+/// Stage the passed files
+pub fn stage_files(files: &[PathBuf]) -> anyhow::Result<()> {
+    let mut args = vec!["add".to_string(), "--".to_string()];
+    args.extend(files.iter().map(|file| file.to_string_lossy().into_owned()));
+    run_process(
+        "git",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        None,
+        Some(&repository_root()?),
+        "Failed to stage the fixed files",
+    )
+}
+
+fn diff_files(args: &[&str]) -> anyhow::Result<Vec<PathBuf>> {
+    let root = repository_root()?;
+    let output = run_query_with_output("git", args, Some(&root))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to get the files with 'git {}'",
+            args.join(" ")
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| root.join(line))
+        .collect())
+}
+
+/// Returns true if the passed tag exists in the current git repository
+pub fn tag_exists(tag: &str) -> anyhow::Result<bool> {
+    let reference = format!("refs/tags/{tag}");
+    let output = run_query_with_output(
+        "git",
+        &["rev-parse", "--quiet", "--verify", &reference],
+        None,
+    )?;
+    Ok(output.status.success())
+}
+
+/// Returns the paths relative to the repository root of the tracked files with uncommitted changes
+pub fn modified_files() -> anyhow::Result<Vec<String>> {
+    let root = repository_root()?;
+    let output = run_query_with_output("git", &["diff", "--name-only", "HEAD"], Some(&root))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to get the modified files with 'git diff'"
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Returns the paths of the uncommitted and untracked files reported by `git status --porcelain`
+pub fn uncommitted_files() -> anyhow::Result<Vec<String>> {
+    let output = run_query_with_output("git", &["status", "--porcelain"], None)?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to get the status of the git tree"));
+    }
+    Ok(parse_porcelain_status(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Returns true if the git tree has neither uncommitted changes nor untracked files
+pub fn is_clean() -> anyhow::Result<bool> {
+    Ok(uncommitted_files()?.is_empty())
+}
+
+/// Returns the most recent tag reachable from the current commit, if any
+pub fn last_tag() -> anyhow::Result<Option<String>> {
+    let output = run_query_with_output("git", &["describe", "--tags", "--abbrev=0"], None)?;
+    // git describe fails when there is no tag, with an error on the standard error
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|t| !t.is_empty()))
+}
+
+/// Create an annotated tag of the current commit
+pub fn create_tag(tag: &str, message: &str) -> anyhow::Result<()> {
+    let output = run_process_with_output("git", &["tag", "-a", tag, "-m", message], None, None)?;
+    // the error of git is printed on the inherited standard error
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to create the git tag {}", tag));
+    }
+    Ok(())
+}
+
+/// Returns the name of the current git branch, `HEAD` when it is detached
+pub fn current_branch() -> anyhow::Result<String> {
+    let output = run_query_with_output("git", &["rev-parse", "--abbrev-ref", "HEAD"], None)?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to get the current git branch"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns the paths of the porcelain status lines, each line is a two-letter status followed by a path
+fn parse_porcelain_status(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::clean("", vec![])]
+    #[case::dirty(
+        " M Cargo.toml\n?? notes.txt\nR  old.rs -> new.rs\n",
+        vec!["Cargo.toml", "notes.txt", "old.rs -> new.rs"]
+    )]
+    fn test_parse_porcelain_status(#[case] status: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(parse_porcelain_status(status), expected);
+    }
+
+    #[rstest]
+    fn test_parse_changed_lines() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn a() {
+-    old();
++    new();
+@@ -10,0 +11,3 @@ fn b() {
++    c();
++    d();
++    e();
+@@ -20,2 +22,0 @@
+-    f();
+-    g();
+diff --git a/removed.rs b/removed.rs
+deleted file mode 100644
+--- a/removed.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn removed() {}
+-
+";
+        let changed = parse_changed_lines(Path::new("/ws"), diff);
+        let lib = Path::new("/ws/src/lib.rs");
+        assert_eq!(
+            changed.files,
+            BTreeMap::from([(lib.to_path_buf(), vec![(3, 3), (11, 13), (22, 23)])])
+        );
+        assert!(changed.contains(lib, 3, 3));
+        assert!(changed.contains(lib, 1, 11));
+        assert!(!changed.contains(lib, 4, 10));
+        assert!(changed.contains(lib, 23, 30));
+        assert!(!changed.contains(Path::new("/ws/src/main.rs"), 3, 3));
+    }
+}
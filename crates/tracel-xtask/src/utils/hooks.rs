@@ -0,0 +1,217 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+
+use crate::utils::process::run_process;
+
+/// Rust function run as a hook
+pub type HookFn = Arc<dyn Fn() -> anyhow::Result<()> + Send + Sync>;
+
+/// Stage of a command at which a hook runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HookStage {
+    /// Before the command, a failure aborts the command
+    Pre,
+    /// After the command when it succeeded
+    Post,
+}
+
+#[derive(Clone)]
+enum Hook {
+    Shell(String),
+    Rust(HookFn),
+}
+
+/// Shell commands of the `[hooks.<command>]` tables of the xtask.toml file
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HookConfig {
+    pub pre: Option<String>,
+    pub post: Option<String>,
+}
+
+/// Hooks run around the base commands, the hooks of a command and stage run in registration order.
+///
+/// ```ignore
+/// Hooks::new()
+///     .pre("build", || run_codegen())
+///     .post("test", || cleanup())
+///     .register();
+/// let args = init_xtask::<Command>()?;
+/// ```
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: BTreeMap<(String, HookStage), Vec<Hook>>,
+}
+
+static HOOKS: Mutex<Option<Hooks>> = Mutex::new(None);
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the passed function before the command, the command name is the one of the command line
+    pub fn pre(
+        self,
+        command: &str,
+        hook: impl Fn() -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.with(command, HookStage::Pre, Hook::Rust(Arc::new(hook)))
+    }
+
+    /// Run the passed function after the command when it succeeded
+    pub fn post(
+        self,
+        command: &str,
+        hook: impl Fn() -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.with(command, HookStage::Post, Hook::Rust(Arc::new(hook)))
+    }
+
+    /// Add the shell commands of the hooks configured in the xtask.toml file
+    pub fn with_config(mut self, config: &BTreeMap<String, HookConfig>) -> Self {
+        for (command, hook) in config {
+            for (stage, shell) in [(HookStage::Pre, &hook.pre), (HookStage::Post, &hook.post)] {
+                if let Some(shell) = shell {
+                    self = self.with(command, stage, Hook::Shell(shell.clone()));
+                }
+            }
+        }
+        self
+    }
+
+    fn with(mut self, command: &str, stage: HookStage, hook: Hook) -> Self {
+        self.hooks
+            .entry((command.to_string(), stage))
+            .or_default()
+            .push(hook);
+        self
+    }
+
+    /// Register the hooks, they are added to the hooks registered before
+    pub fn register(self) {
+        let mut registered = HOOKS.lock().expect("hooks lock should not be poisoned");
+        let mut hooks = registered.take().unwrap_or_default();
+        for (key, mut added) in self.hooks {
+            hooks.hooks.entry(key).or_default().append(&mut added);
+        }
+        *registered = Some(hooks);
+    }
+
+    /// Returns the number of hooks of the command at the passed stage
+    pub fn count(&self, command: &str, stage: HookStage) -> usize {
+        self.hooks
+            .get(&(command.to_string(), stage))
+            .map_or(0, Vec::len)
+    }
+
+    fn run(&self, command: &str, stage: HookStage) -> anyhow::Result<()> {
+        let Some(hooks) = self.hooks.get(&(command.to_string(), stage)) else {
+            return Ok(());
+        };
+        for hook in hooks {
+            let result = match hook {
+                Hook::Shell(shell) => run_shell(shell),
+                Hook::Rust(f) => f(),
+            };
+            result
+                .map_err(|e| anyhow::anyhow!("{:?} hook of '{}' failed: {}", stage, command, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn run_shell(command: &str) -> anyhow::Result<()> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    run_process(
+        shell,
+        &[flag, command],
+        None,
+        None,
+        &format!("Hook command '{command}' failed"),
+    )
+}
+
+/// Run the passed command handler between the pre and post hooks of the command.
+/// It is done by the dispatcher of the base commands, custom commands can call it too.
+pub fn with_hooks<T>(command: &str, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    // the hooks are cloned so that they can register other hooks
+    let hooks = HOOKS
+        .lock()
+        .expect("hooks lock should not be poisoned")
+        .clone()
+        .unwrap_or_default();
+    hooks.run(command, HookStage::Pre)?;
+    let output = f()?;
+    hooks.run(command, HookStage::Post)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn recording_hook(
+        calls: &Arc<Mutex<Vec<String>>>,
+        name: &str,
+    ) -> impl Fn() -> anyhow::Result<()> {
+        let calls = calls.clone();
+        let name = name.to_string();
+        move || {
+            calls.lock().unwrap().push(name.clone());
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    fn test_hooks_run_in_order() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let hooks = Hooks::new()
+            .pre("build", recording_hook(&calls, "pre 1"))
+            .post("build", recording_hook(&calls, "post"))
+            .pre("build", recording_hook(&calls, "pre 2"))
+            .pre("test", recording_hook(&calls, "other command"));
+        hooks.run("build", HookStage::Pre).unwrap();
+        hooks.run("build", HookStage::Post).unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec!["pre 1", "pre 2", "post"]);
+    }
+
+    #[rstest]
+    fn test_failing_hook_stops_the_next_hooks() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let hooks = Hooks::new()
+            .pre("build", || Err(anyhow::anyhow!("codegen failed")))
+            .pre("build", move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        let err = hooks.run("build", HookStage::Pre).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Pre hook of 'build' failed: codegen failed"
+        );
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+    }
+
+    #[rstest]
+    fn test_hooks_with_config() {
+        let config: BTreeMap<String, HookConfig> =
+            toml::from_str("[build]\npre = \"make proto\"\n\n[test]\npost = \"make clean\"")
+                .unwrap();
+        let hooks = Hooks::new().with_config(&config);
+        assert_eq!(hooks.count("build", HookStage::Pre), 1);
+        assert_eq!(hooks.count("build", HookStage::Post), 0);
+        assert_eq!(hooks.count("test", HookStage::Post), 1);
+    }
+}
@@ -1,10 +1,22 @@
 use std::process::Command;
 
 pub mod cargo;
+pub mod config;
+pub mod env_file;
+pub mod explain;
+pub mod features;
+pub mod git;
 pub mod helpers;
+pub mod hooks;
+pub mod outcome;
 pub mod process;
+pub mod progress;
 pub mod prompt;
 pub mod rustup;
+pub mod sarif;
+pub mod sccache;
+pub mod snapshot;
+pub(crate) mod steps;
 pub mod time;
 pub mod workspace;
 
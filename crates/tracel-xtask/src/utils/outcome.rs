@@ -0,0 +1,120 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+pub use crate::utils::steps::{StepOutcome, StepReport};
+
+/// What happened during a command: the steps it ran, the warnings it logged, the tools it installed
+/// and the files it produced. It is collected by `with_outcome` and returned by `XtaskRunner::run_with_outcome`
+/// for the embedders and the reporting features, the CLI discards it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CommandOutcome {
+    pub command: String,
+    pub duration: Duration,
+    pub steps: Vec<StepReport>,
+    pub warnings: Vec<String>,
+    pub installed_tools: Vec<String>,
+    pub artifacts: Vec<PathBuf>,
+}
+
+impl CommandOutcome {
+    fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Append the steps, warnings, tools and artifacts of a nested outcome
+    fn extend(&mut self, other: CommandOutcome) {
+        self.steps.extend(other.steps);
+        self.warnings.extend(other.warnings);
+        self.installed_tools.extend(other.installed_tools);
+        self.artifacts.extend(other.artifacts);
+    }
+}
+
+static OUTCOME: Mutex<Option<CommandOutcome>> = Mutex::new(None);
+
+/// Run the passed function and returns its result along with the outcome collected while it was running.
+/// A nested call returns its own outcome which is also added to the outcome of the enclosing call.
+pub fn with_outcome(
+    command: &str,
+    f: impl FnOnce() -> anyhow::Result<()>,
+) -> (anyhow::Result<()>, CommandOutcome) {
+    let previous = lock_outcome().replace(CommandOutcome::new(command));
+    let start = Instant::now();
+    let result = f();
+    let mut outcome = lock_outcome()
+        .take()
+        .unwrap_or_else(|| CommandOutcome::new(command));
+    outcome.duration = start.elapsed();
+    if let Some(mut previous) = previous {
+        previous.extend(outcome.clone());
+        *lock_outcome() = Some(previous);
+    }
+    (result, outcome)
+}
+
+fn lock_outcome() -> std::sync::MutexGuard<'static, Option<CommandOutcome>> {
+    OUTCOME.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Update the outcome being collected, nothing is done outside of `with_outcome`
+fn record(f: impl FnOnce(&mut CommandOutcome)) {
+    if let Some(outcome) = lock_outcome().as_mut() {
+        f(outcome);
+    }
+}
+
+pub(crate) fn record_steps(reports: &[StepReport]) {
+    record(|outcome| outcome.steps.extend(reports.iter().cloned()));
+}
+
+pub(crate) fn record_warning(message: String) {
+    record(|outcome| outcome.warnings.push(message));
+}
+
+pub(crate) fn record_installed_tool(name: String) {
+    record(|outcome| outcome.installed_tools.push(name));
+}
+
+pub(crate) fn record_artifact(path: &Path) {
+    record(|outcome| outcome.artifacts.push(path.to_path_buf()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_with_outcome() {
+        let (result, outcome) = with_outcome("check", || {
+            record_warning("Typos not found".to_string());
+            let (nested_result, nested) = with_outcome("coverage", || {
+                record_artifact(Path::new("lcov.info"));
+                Err(anyhow::anyhow!("Coverage below the minimum"))
+            });
+            assert!(nested_result.is_err());
+            assert_eq!(nested.command, "coverage");
+            assert!(nested.artifacts.contains(&PathBuf::from("lcov.info")));
+            assert!(nested.warnings.is_empty());
+            record_installed_tool("cargo-hack".to_string());
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(outcome.command, "check");
+        assert_eq!(outcome.warnings, vec!["Typos not found"]);
+        assert_eq!(outcome.installed_tools, vec!["cargo-hack"]);
+        // the artifacts of the tests running in parallel can be recorded as well
+        assert!(outcome.artifacts.contains(&PathBuf::from("lcov.info")));
+        // nothing is collected outside of with_outcome
+        record_warning("ignored".to_string());
+        assert!(lock_outcome().is_none());
+    }
+}
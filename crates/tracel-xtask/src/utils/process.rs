@@ -1,20 +1,375 @@
 use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader},
-    path::Path,
-    process::{Command, Stdio},
-    sync::mpsc,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Once, OnceLock,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 use anyhow;
 use rand::Rng;
 use regex::Regex;
+use serde_json::Value;
 
 use crate::group_info;
+use crate::logging::is_json_output;
+use crate::utils::explain::{explain_command, is_explain_enabled};
+use crate::utils::progress::{forward_output, start_spinner};
 use crate::{endgroup, group};
+use crate::{ColorChoice, ExecutionEnvironment};
 
-/// Run a process
+/// Maximum number of messages printed per crate in the diagnostics summary
+const SUMMARY_MAX_MESSAGES: usize = 3;
+
+/// Cargo subcommands accepting the --locked, --frozen and --offline flags
+const MANIFEST_OPTIONS_SUBCOMMANDS: &[&str] = &[
+    "bench",
+    "build",
+    "check",
+    "clippy",
+    "doc",
+    "fetch",
+    "fix",
+    "generate-lockfile",
+    "hack",
+    "install",
+    "metadata",
+    "package",
+    "pkgid",
+    "publish",
+    "run",
+    "rustdoc",
+    "test",
+    "tree",
+    "update",
+    "vendor",
+];
+
+/// Cargo subcommands passed the target triple of the execution environment
+const TARGET_SUBCOMMANDS: &[&str] = &["build", "check"];
+
+/// Cargo subcommands which are passed the `--message-format` option
+const MESSAGE_FORMAT_SUBCOMMANDS: &[&str] = &["build", "check", "clippy", "test"];
+
+/// Cargo subcommands run with cross when it is enabled, the other ones are run with cargo
+const CROSS_SUBCOMMANDS: &[&str] = &[
+    "bench", "build", "check", "clippy", "doc", "run", "rustc", "test",
+];
+
+/// Options applied by the process runners to all the spawned cargo processes
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ProcessOptions {
+    /// Coloring of the output of the processes, it overrides any --color argument
+    pub color: ColorChoice,
+    /// Pass --locked to cargo
+    pub locked: bool,
+    /// Pass --frozen to cargo
+    pub frozen: bool,
+    /// Pass --offline to cargo
+    pub offline: bool,
+    /// Duration after which the child processes are killed
+    pub timeout: Option<Duration>,
+    /// Program run in place of cargo, for instance a wrapper like cargo-zigbuild
+    pub cargo_path: Option<PathBuf>,
+    /// Run the build, check and test cargo subcommands with cross
+    pub use_cross: bool,
+    /// Environment variables set for all the processes, the variables passed to the runners
+    /// take precedence
+    pub envs: BTreeMap<String, String>,
+    /// Execution environment, the build and check cargo subcommands are passed its target triple if any
+    pub execution_environment: ExecutionEnvironment,
+    /// Message format passed to the build, check, clippy and test cargo subcommands
+    pub message_format: Option<String>,
+    /// Workspace members outside of `workspace.default-members`, they are excluded from the cargo
+    /// commands run with `--workspace`
+    pub non_default_members: Vec<String>,
+}
+
+impl ProcessOptions {
+    /// Returns the program to run in place of cargo with these options, see `cargo_program`
+    pub fn cargo_program(&self) -> String {
+        process_program("cargo", &[], self)
+    }
+}
+
+static PROCESS_OPTIONS: OnceLock<ProcessOptions> = OnceLock::new();
+static DEFAULT_PROCESS_OPTIONS: OnceLock<ProcessOptions> = OnceLock::new();
+
+/// Set the options applied to all the spawned cargo processes, can only be set once
+/// and it is done by `init_xtask`.
+pub fn set_process_options(options: ProcessOptions) {
+    if PROCESS_OPTIONS.set(options).is_err() {
+        warn!("Process options have already been set.");
+    }
+}
+
+/// Returns the options applied to all the spawned cargo processes, the default options until they
+/// are set so that the processes spawned during the initialization do not prevent setting them.
+pub fn process_options() -> &'static ProcessOptions {
+    PROCESS_OPTIONS
+        .get()
+        .unwrap_or_else(|| DEFAULT_PROCESS_OPTIONS.get_or_init(ProcessOptions::default))
+}
+
+/// Returns the program to run in place of cargo: the `--cargo-path` option if set, then the
+/// `CARGO` environment variable and finally `cargo` from the PATH.
+pub fn cargo_program() -> String {
+    process_options().cargo_program()
+}
+
+/// Returns the program to execute for the process with the given name and arguments.
+fn process_program(name: &str, args: &[String], options: &ProcessOptions) -> String {
+    if name != "cargo" {
+        return name.to_string();
+    }
+    let subcommand = args.iter().find(|a| !a.starts_with('+'));
+    if options.use_cross && subcommand.is_some_and(|s| CROSS_SUBCOMMANDS.contains(&s.as_str())) {
+        return "cross".to_string();
+    }
+    if let Some(path) = &options.cargo_path {
+        return path.to_string_lossy().into_owned();
+    }
+    // CARGO points to the cargo binary of a toolchain which does not support the `+toolchain`
+    // overrides of the rustup proxy
+    if args.first().is_some_and(|a| a.starts_with('+')) {
+        return name.to_string();
+    }
+    std::env::var("CARGO")
+        .ok()
+        .filter(|cargo| !cargo.is_empty())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Resolve the program from the PATH like a shell would, including the PATHEXT extensions such as
+/// `.cmd` or `.bat` on Windows which are not resolved by `Command`.
+/// The program is returned unchanged when it is not found so that spawning it reports the error.
+pub(crate) fn resolve_program(program: &str) -> PathBuf {
+    which::which(program).unwrap_or_else(|_| PathBuf::from(program))
+}
+
+/// Returns the command executing the program with the environment variables of the process options
+fn process_command(program: &str) -> Command {
+    let mut command = Command::new(resolve_program(program));
+    command.envs(&process_options().envs);
+    command
+}
+
+/// Returns the arguments of the process with the process options applied
+pub(crate) fn process_args(name: &str, args: &[&str]) -> Vec<String> {
+    let options = process_options();
+    if name != "cargo" {
+        return args.iter().map(|a| a.to_string()).collect();
+    }
+    let color = if options.color.is_enabled() {
+        "always"
+    } else {
+        "never"
+    };
+    args_with_color(&cargo_args_with_options(args, options), color)
+}
+
+/// Replace the value of the cargo --color arguments with the passed color,
+/// the binary arguments after the first `--` are left untouched.
+fn args_with_color(args: &[String], color: &str) -> Vec<String> {
+    let mut color_value = false;
+    let mut binary_args = false;
+    args.iter()
+        .map(|arg| {
+            if binary_args {
+                arg.clone()
+            } else if arg == "--" {
+                binary_args = true;
+                arg.clone()
+            } else if color_value {
+                color_value = false;
+                color.to_string()
+            } else if arg == "--color" {
+                color_value = true;
+                arg.clone()
+            } else if arg.starts_with("--color=") {
+                format!("--color={color}")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect()
+}
+
+/// Insert the cargo flags of the passed options after the cargo subcommand arguments
+/// and before the binary arguments.
+fn cargo_args_with_options(args: &[&str], options: &ProcessOptions) -> Vec<String> {
+    let (cargo_args, binary_args) = split_vector(args, "--");
+    let mut cmd_args: Vec<String> = cargo_args.iter().map(|a| a.to_string()).collect();
+    // skip the toolchain override if any to find the subcommand
+    let subcommand = cargo_args.iter().find(|a| !a.starts_with('+'));
+    if subcommand.is_some_and(|s| MANIFEST_OPTIONS_SUBCOMMANDS.contains(s)) {
+        [
+            (options.locked, "--locked"),
+            (options.frozen, "--frozen"),
+            (options.offline, "--offline"),
+        ]
+        .iter()
+        .filter(|(enabled, flag)| *enabled && !cargo_args.contains(flag))
+        .for_each(|(_, flag)| cmd_args.push(flag.to_string()));
+    }
+    // the commands parsing the messages of cargo pass their own format
+    if let Some(format) = &options.message_format {
+        let has_format = cargo_args
+            .iter()
+            .any(|a| *a == "--message-format" || a.starts_with("--message-format="));
+        if subcommand.is_some_and(|s| MESSAGE_FORMAT_SUBCOMMANDS.contains(s)) && !has_format {
+            cmd_args.push(format!("--message-format={format}"));
+        }
+    }
+    if cargo_args.contains(&"--workspace") {
+        let excluded: Vec<&str> = cargo_args
+            .windows(2)
+            .filter(|pair| pair[0] == "--exclude")
+            .map(|pair| pair[1])
+            .collect();
+        options
+            .non_default_members
+            .iter()
+            .filter(|member| !excluded.contains(&member.as_str()))
+            .for_each(|member| cmd_args.extend(["--exclude".to_string(), member.clone()]));
+    }
+    if let Some(target) = options.execution_environment.cargo_target() {
+        let has_target = cargo_args
+            .iter()
+            .any(|a| *a == "--target" || a.starts_with("--target="));
+        if subcommand.is_some_and(|s| TARGET_SUBCOMMANDS.contains(s)) && !has_target {
+            cmd_args.extend(["--target".to_string(), target.to_string()]);
+        }
+    }
+    cmd_args.extend(binary_args.iter().map(|a| a.to_string()));
+    cmd_args
+}
+
+/// Interval at which the running child processes are polled
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static RUNNING_CHILDREN: AtomicUsize = AtomicUsize::new(0);
+static SIGNAL_HANDLER: Once = Once::new();
+
+/// Install the Ctrl-C handler. If no child process is running then xtask exits right away,
+/// otherwise the processes runners kill the running child and return an error.
+fn install_signal_handler() {
+    SIGNAL_HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            if RUNNING_CHILDREN.load(Ordering::SeqCst) == 0 {
+                std::process::exit(130);
+            }
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        });
+        if let Err(e) = result {
+            warn!("Failed to install the Ctrl-C handler: {}", e);
+        }
+    });
+}
+
+/// Returns true if xtask has been interrupted with Ctrl-C
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+fn interrupted_error() -> anyhow::Error {
+    anyhow::anyhow!("Interrupted by user (Ctrl-C).")
+}
+
+/// Spawn a child process which is then tracked by the Ctrl-C handler until `wait_child` returns.
+fn spawn_child(command: &mut Command) -> anyhow::Result<Child> {
+    if is_interrupted() {
+        return Err(interrupted_error());
+    }
+    install_signal_handler();
+    // with a timeout the child leads its own process group so that `kill_child` also kills the
+    // processes it started, otherwise they would keep its output pipes open
+    #[cfg(unix)]
+    if process_options().timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let child = command.spawn()?;
+    RUNNING_CHILDREN.fetch_add(1, Ordering::SeqCst);
+    Ok(child)
+}
+
+/// Kill a child process spawned with `spawn_child` along with the processes it started
+fn kill_child(child: &mut Child) {
+    #[cfg(unix)]
+    if process_options().timeout.is_some() {
+        // the child is not reaped yet so its process group still exists
+        unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+    }
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &child.id().to_string()])
+        .output();
+    let _ = child.kill();
+}
+
+/// Returns true if the child process started at the given instant exceeded the timeout
+fn is_timed_out(start: Instant) -> bool {
+    process_options()
+        .timeout
+        .is_some_and(|timeout| start.elapsed() >= timeout)
+}
+
+fn timeout_error() -> anyhow::Error {
+    let timeout = process_options().timeout.unwrap_or_default();
+    anyhow::anyhow!(
+        "Process killed because it exceeded the timeout of {} seconds.",
+        timeout.as_secs()
+    )
+}
+
+/// Wait for a child process spawned with `spawn_child` at the given instant to exit.
+/// The child is killed and an error is returned if xtask is interrupted with Ctrl-C
+/// or if the child exceeds the timeout.
+fn wait_child(child: &mut Child, start: Instant) -> anyhow::Result<ExitStatus> {
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if is_interrupted() || is_timed_out(start) => {
+                timed_out = !is_interrupted();
+                kill_child(child);
+                break child.wait();
+            }
+            Ok(None) => thread::sleep(WAIT_POLL_INTERVAL),
+            Err(e) => break Err(e),
+        }
+    };
+    RUNNING_CHILDREN.fetch_sub(1, Ordering::SeqCst);
+    // the child may have exited by itself because it received the interruption as well
+    if is_interrupted() {
+        return Err(interrupted_error());
+    }
+    if timed_out {
+        return Err(timeout_error());
+    }
+    status.map_err(|e| anyhow::anyhow!("Failed to wait for the process to finish: {}", e))
+}
+
+/// Output of the commands which are not run in explain mode: a success without any output
+fn explained_output() -> Output {
+    Output {
+        status: ExitStatus::default(),
+        stdout: vec![],
+        stderr: vec![],
+    }
+}
+
+/// Run a process.
+/// For interactive runs a spinner is shown until the process exits, the output of the process is then
+/// forwarded line by line above the spinner.
 pub fn run_process(
     name: &str,
     args: &[&str],
@@ -22,16 +377,272 @@ pub fn run_process(
     path: Option<&Path>,
     error_msg: &str,
 ) -> anyhow::Result<()> {
+    let args = process_args(name, args);
+    let program = process_program(name, &args, process_options());
+    if explain_command(&program, &args, envs.as_ref(), path) {
+        return anyhow::Ok(());
+    }
+    let joined_args = args.join(" ");
+    group_info!("Command line: {} {}", program, &joined_args);
+    let mut command = process_command(&program);
+    if let Some(path) = path {
+        command.current_dir(path);
+    }
+    if let Some(envs) = envs {
+        command.envs(&envs);
+    }
+    command.args(&args);
+    let spinner = start_spinner(&format!("{} {}", program, &joined_args));
+    if spinner.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        // keep the colors of cargo now that its output is not a terminal anymore
+        if process_options().color.is_enabled() && std::env::var("CARGO_TERM_COLOR").is_err() {
+            command.env("CARGO_TERM_COLOR", "always");
+        }
+    }
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to execute {} {}: {}",
+            name,
+            args.first().unwrap(),
+            e
+        )
+    })?;
+    let forwarders = spinner.as_ref().map(|spinner| {
+        [
+            forward_output(spinner, child.stdout.take().unwrap(), std::io::stdout()),
+            forward_output(spinner, child.stderr.take().unwrap(), std::io::stderr()),
+        ]
+    });
+    let status = wait_child(&mut child, start);
+    for forwarder in forwarders.into_iter().flatten() {
+        let _ = forwarder.join();
+    }
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let status = status?;
+    if !status.success() {
+        return Err(process_error(error_msg, &program, &args, path, status));
+    }
+    anyhow::Ok(())
+}
+
+/// Run a process and capture its standard output, the standard error is inherited.
+/// The exit status is not checked, it is up to the caller to handle it.
+pub fn run_process_with_output(
+    name: &str,
+    args: &[&str],
+    envs: Option<HashMap<&str, &str>>,
+    path: Option<&Path>,
+) -> anyhow::Result<Output> {
+    capture_output(name, args, envs, path, true)
+}
+
+/// Same as `run_process_with_output` for the commands which only inspect the workspace or the
+/// installed tools, they also run in explain mode as the plan depends on their output.
+pub(crate) fn run_query_with_output(
+    name: &str,
+    args: &[&str],
+    path: Option<&Path>,
+) -> anyhow::Result<Output> {
+    capture_output(name, args, None, path, false)
+}
+
+fn capture_output(
+    name: &str,
+    args: &[&str],
+    envs: Option<HashMap<&str, &str>>,
+    path: Option<&Path>,
+    explainable: bool,
+) -> anyhow::Result<Output> {
+    let args = process_args(name, args);
+    let program = process_program(name, &args, process_options());
+    if explainable && explain_command(&program, &args, envs.as_ref(), path) {
+        return anyhow::Ok(explained_output());
+    }
+    let joined_args = args.join(" ");
+    // the queries are not part of the plan printed in explain mode
+    if !is_explain_enabled() {
+        group_info!("Command line: {} {}", program, &joined_args);
+    }
+    let mut command = process_command(&program);
+    if let Some(path) = path {
+        command.current_dir(path);
+    }
+    if let Some(envs) = envs {
+        command.envs(&envs);
+    }
+    command
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to execute {} {}: {}",
+            name,
+            args.first().unwrap(),
+            e
+        )
+    })?;
+    // read stdout in a thread so that the child does not block on a full pipe
+    let stdout_thread = child.stdout.take().map(|mut stdout| {
+        thread::spawn(move || {
+            let mut buffer = vec![];
+            stdout.read_to_end(&mut buffer).map(|_| buffer)
+        })
+    });
+    let status = wait_child(&mut child, start)?;
+    let stdout = match stdout_thread {
+        Some(handle) => handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("Failed to read the output of {}", name))?
+            .map_err(|e| anyhow::anyhow!("Failed to read the output of {}: {}", name, e))?,
+        None => vec![],
+    };
+    anyhow::Ok(Output {
+        status,
+        stdout,
+        stderr: vec![],
+    })
+}
+
+/// Run a process while streaming its output and keeping a copy of it.
+/// The exit status is not checked, it is up to the caller to handle it.
+pub fn run_process_with_tee(
+    name: &str,
+    args: &[&str],
+    envs: Option<HashMap<&str, &str>>,
+    path: Option<&Path>,
+) -> anyhow::Result<Output> {
+    let args = process_args(name, args);
+    let program = process_program(name, &args, process_options());
+    if explain_command(&program, &args, envs.as_ref(), path) {
+        return anyhow::Ok(explained_output());
+    }
     let joined_args = args.join(" ");
-    group_info!("Command line: {} {}", name, &joined_args);
-    let mut command = Command::new(name);
+    group_info!("Command line: {} {}", program, &joined_args);
+    let mut command = process_command(&program);
+    if let Some(path) = path {
+        command.current_dir(path);
+    }
+    if let Some(envs) = envs {
+        command.envs(&envs);
+    }
+    command
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to execute {} {}: {}",
+            name,
+            args.first().unwrap(),
+            e
+        )
+    })?;
+    let stdout_thread = child.stdout.take().map(|stdout| {
+        thread::spawn(move || {
+            let mut copy = vec![];
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{}", line);
+                copy.extend(line.into_bytes());
+                copy.push(b'\n');
+            }
+            copy
+        })
+    });
+    let stderr_thread = child.stderr.take().map(|stderr| {
+        thread::spawn(move || {
+            let mut copy = vec![];
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                eprintln!("{}", line);
+                copy.extend(line.into_bytes());
+                copy.push(b'\n');
+            }
+            copy
+        })
+    });
+    let status = wait_child(&mut child, start)?;
+    let stdout = stdout_thread
+        .map(|handle| handle.join().unwrap_or_default())
+        .unwrap_or_default();
+    let stderr = stderr_thread
+        .map(|handle| handle.join().unwrap_or_default())
+        .unwrap_or_default();
+    anyhow::Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Output stream of a child process
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Output of a process captured by `run_process_buffered`, the lines of both streams are kept
+/// in the order they were received.
+#[derive(Debug)]
+pub struct BufferedOutput {
+    pub status: ExitStatus,
+    pub lines: Vec<(OutputStream, String)>,
+}
+
+impl BufferedOutput {
+    /// Print the captured lines on the stream they were written to, framed in a group with the passed title
+    pub fn replay(&self, title: &str) {
+        group!("{}", title);
+        self.replay_to(&mut std::io::stdout(), &mut std::io::stderr());
+        endgroup!();
+    }
+
+    fn replay_to(&self, stdout: &mut impl Write, stderr: &mut impl Write) {
+        for (stream, line) in &self.lines {
+            let _ = match stream {
+                OutputStream::Stdout => writeln!(stdout, "{}", line),
+                OutputStream::Stderr => writeln!(stderr, "{}", line),
+            };
+        }
+    }
+}
+
+/// Run a process and capture its output to replay it later, for instance once all the processes
+/// run concurrently for several members have exited, so that their outputs do not interleave.
+/// The exit status is not checked, it is up to the caller to handle it.
+pub fn run_process_buffered(
+    name: &str,
+    args: &[&str],
+    envs: Option<HashMap<&str, &str>>,
+    path: Option<&Path>,
+) -> anyhow::Result<BufferedOutput> {
+    let args = process_args(name, args);
+    let program = process_program(name, &args, process_options());
+    if explain_command(&program, &args, envs.as_ref(), path) {
+        return anyhow::Ok(BufferedOutput {
+            status: ExitStatus::default(),
+            lines: vec![],
+        });
+    }
+    let mut command = process_command(&program);
     if let Some(path) = path {
         command.current_dir(path);
     }
     if let Some(envs) = envs {
         command.envs(&envs);
     }
-    let status = command.args(args).status().map_err(|e| {
+    command
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
         anyhow::anyhow!(
             "Failed to execute {} {}: {}",
             name,
@@ -39,12 +650,251 @@ pub fn run_process(
             e
         )
     })?;
+    let (tx, rx) = mpsc::channel();
+    let stdout_tx = tx.clone();
+    let readers = [
+        child.stdout.take().map(|stdout| {
+            thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    let _ = stdout_tx.send((OutputStream::Stdout, line));
+                }
+            })
+        }),
+        child.stderr.take().map(|stderr| {
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    let _ = tx.send((OutputStream::Stderr, line));
+                }
+            })
+        }),
+    ];
+    let status = wait_child(&mut child, start)?;
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    anyhow::Ok(BufferedOutput {
+        status,
+        lines: rx.into_iter().collect(),
+    })
+}
+
+/// Run a cargo command with JSON diagnostics and print a summary of the errors and warnings per crate
+/// once it exits. The rendered compiler messages are streamed as well when verbose is true.
+pub fn run_process_with_diagnostics(
+    name: &str,
+    args: &[&str],
+    verbose: bool,
+    error_msg: &str,
+) -> anyhow::Result<()> {
+    let (cargo_args, binary_args) = split_vector(args, "--");
+    let mut cmd_args = cargo_args.to_owned();
+    cmd_args.push("--message-format=json-diagnostic-rendered-ansi");
+    cmd_args.extend(binary_args);
+    let cmd_args = process_args(name, &cmd_args);
+    let program = process_program(name, &cmd_args, process_options());
+    if explain_command(&program, &cmd_args, None, None) {
+        return anyhow::Ok(());
+    }
+    group_info!("Command line: {} {}", program, cmd_args.join(" "));
+    let mut command = process_command(&program);
+    command
+        .args(&cmd_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to execute {} {}: {}",
+            name,
+            cmd_args.first().unwrap(),
+            e
+        )
+    })?;
+    let stdout_thread = child.stdout.take().map(|stdout| {
+        thread::spawn(move || {
+            let mut summary = DiagnosticsSummary::default();
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().map_while(Result::ok) {
+                if !line.starts_with('{') {
+                    println!("{}", line);
+                } else if let Some(diagnostic) = parse_diagnostic(&line) {
+                    if verbose {
+                        print!("{}", diagnostic.rendered);
+                    }
+                    summary.add(diagnostic);
+                }
+            }
+            summary
+        })
+    });
+    let status = wait_child(&mut child, start)?;
+    let summary = stdout_thread
+        .map(|handle| handle.join().unwrap_or_default())
+        .unwrap_or_default();
+    info!("Diagnostics summary:");
+    summary.lines().iter().for_each(|line| info!("  {}", line));
     if !status.success() {
-        return Err(anyhow::anyhow!("{}", error_msg));
+        return Err(process_error(error_msg, &program, &cmd_args, None, status));
     }
     anyhow::Ok(())
 }
 
+/// A compiler error or warning reported in the JSON messages of cargo
+#[derive(Debug, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub crate_name: String,
+    pub level: String,
+    pub message: String,
+    /// lint or error code such as `clippy::needless_return` or `E0308`
+    pub code: Option<String>,
+    pub location: Option<Location>,
+    pub rendered: String,
+}
+
+/// Primary span of a diagnostic
+#[derive(Debug, PartialEq)]
+pub(crate) struct Location {
+    pub file: String,
+    pub line: u64,
+    pub end_line: u64,
+    pub column: Option<u64>,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Parse a line of the JSON messages emitted by cargo with `--message-format=json*`.
+/// Returns None for the other messages and for the compiler summaries of the messages count.
+pub(crate) fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+    let msg: Value = serde_json::from_str(line).ok()?;
+    if msg["reason"] != "compiler-message" {
+        return None;
+    }
+    let diagnostic = &msg["message"];
+    let level = diagnostic["level"].as_str()?;
+    if level != "error" && level != "warning" {
+        return None;
+    }
+    let message = diagnostic["message"].as_str()?;
+    let spans = diagnostic["spans"].as_array();
+    if spans.map_or(true, |s| s.is_empty())
+        && (message.starts_with("aborting due to") || message.ends_with("emitted"))
+    {
+        return None;
+    }
+    let location = spans
+        .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true))
+        .and_then(|span| {
+            let line = span["line_start"].as_u64()?;
+            Some(Location {
+                file: span["file_name"].as_str()?.to_string(),
+                line,
+                end_line: span["line_end"].as_u64().unwrap_or(line),
+                column: span["column_start"].as_u64(),
+            })
+        });
+    Some(Diagnostic {
+        crate_name: msg["target"]["name"].as_str()?.to_string(),
+        level: level.to_string(),
+        message: message.to_string(),
+        code: diagnostic["code"]["code"].as_str().map(String::from),
+        location,
+        rendered: diagnostic["rendered"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CrateDiagnostics {
+    errors: usize,
+    warnings: usize,
+    messages: Vec<String>,
+    // the same diagnostic is reported once per compiled target of the crate
+    seen: HashSet<String>,
+}
+
+/// Errors and warnings counts per crate along with their first messages
+#[derive(Debug, Default)]
+pub(crate) struct DiagnosticsSummary {
+    crates: BTreeMap<String, CrateDiagnostics>,
+}
+
+impl DiagnosticsSummary {
+    pub fn add(&mut self, diagnostic: Diagnostic) {
+        let entry = self.crates.entry(diagnostic.crate_name).or_default();
+        let message = match diagnostic.location {
+            Some(location) => format!(
+                "{}: {} ({})",
+                diagnostic.level, diagnostic.message, location
+            ),
+            None => format!("{}: {}", diagnostic.level, diagnostic.message),
+        };
+        if !entry.seen.insert(message.clone()) {
+            return;
+        }
+        if diagnostic.level == "error" {
+            entry.errors += 1;
+        } else {
+            entry.warnings += 1;
+        }
+        if entry.messages.len() < SUMMARY_MAX_MESSAGES {
+            entry.messages.push(message);
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        if self.crates.is_empty() {
+            return vec!["No errors or warnings.".to_string()];
+        }
+        self.crates
+            .iter()
+            .flat_map(|(name, diagnostics)| {
+                let mut lines = vec![format!(
+                    "{}: {} error(s), {} warning(s)",
+                    name, diagnostics.errors, diagnostics.warnings
+                )];
+                lines.extend(diagnostics.messages.iter().map(|m| format!("  {}", m)));
+                lines
+            })
+            .collect()
+    }
+}
+
+/// Returns the arguments restricting a workspace command to the selected packages.
+/// Without only the excluded packages are passed with `--exclude`, otherwise `--workspace` is removed
+/// and each package of only which is not excluded is passed with `-p`.
+pub(crate) fn workspace_package_args<'a>(
+    args: &[&'a str],
+    excluded: &'a [String],
+    only: &'a [String],
+) -> Vec<&'a str> {
+    let (cargo_args, binary_args) = split_vector(args, "--");
+    let mut cmd_args: Vec<&str> = if only.is_empty() {
+        let mut cmd_args = cargo_args.to_owned();
+        excluded
+            .iter()
+            .for_each(|ex| cmd_args.extend(["--exclude", ex]));
+        cmd_args
+    } else {
+        let mut cmd_args: Vec<&str> = cargo_args
+            .iter()
+            .copied()
+            .filter(|arg| *arg != "--workspace")
+            .collect();
+        only.iter()
+            .filter(|package| !excluded.contains(package))
+            .for_each(|package| cmd_args.extend(["-p", package]));
+        cmd_args
+    };
+    cmd_args.extend(binary_args);
+    cmd_args
+}
+
 /// Run a process for workspace
 /// regexp must have one capture group if defined
 #[allow(clippy::too_many_arguments)]
@@ -52,6 +902,7 @@ pub fn run_process_for_workspace<'a>(
     name: &str,
     args: &[&'a str],
     excluded: &'a [String],
+    only: &'a [String],
     group_regexp: Option<&str>,
     group_name: Option<&str>,
     error_msg: &str,
@@ -59,29 +910,27 @@ pub fn run_process_for_workspace<'a>(
     ignore_msg: Option<&str>,
 ) -> anyhow::Result<()> {
     let group_rx: Option<Regex> = group_regexp.map(|r| Regex::new(r).unwrap());
-    // split the args between cargo args and binary args so that we can extend the cargo args
-    // and then append the binary args back.
-    let (cargo_args, binary_args) = split_vector(args, "--");
-    let mut cmd_args = cargo_args.to_owned();
-    excluded
-        .iter()
-        .for_each(|ex| cmd_args.extend(["--exclude", ex]));
-    cmd_args.extend(binary_args);
-    group_info!("Command line: cargo {}", cmd_args.join(" "));
+    let cmd_args = process_args(name, &workspace_package_args(args, excluded, only));
+    let program = process_program(name, &cmd_args, process_options());
+    if explain_command(&program, &cmd_args, None, None) {
+        return anyhow::Ok(());
+    }
+    group_info!("Command line: {} {}", program, cmd_args.join(" "));
     // process
-    let mut child = Command::new(name)
+    let mut command = process_command(&program);
+    command
         .args(&cmd_args)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| {
-            anyhow::anyhow!(format!(
-                "Failed to start {} {}: {}",
-                name,
-                cmd_args.first().unwrap(),
-                e
-            ))
-        })?;
+        .stderr(Stdio::piped());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(format!(
+            "Failed to start {} {}: {}",
+            name,
+            cmd_args.first().unwrap(),
+            e
+        ))
+    })?;
 
     // handle stdout and stderr in dedicated threads using a MPSC channel for synchronization
     let (tx, rx) = mpsc::channel();
@@ -111,7 +960,20 @@ pub fn run_process_for_workspace<'a>(
     // Process the stdout to inject log groups
     let mut ignore_error = false;
     let mut close_group = false;
-    for (line, _is_stderr) in rx.iter() {
+    // the child is killed here when it exceeds the timeout, wait_child only sees it exit afterwards
+    let mut timed_out = false;
+    loop {
+        let (line, is_stderr) = match rx.recv_timeout(WAIT_POLL_INTERVAL) {
+            Ok(received) => received,
+            Err(RecvTimeoutError::Timeout) => {
+                if is_interrupted() || is_timed_out(start) {
+                    timed_out |= !is_interrupted();
+                    kill_child(&mut child);
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
         let mut skip_line = false;
 
         if let Some(rx) = &group_rx {
@@ -136,14 +998,24 @@ pub fn run_process_for_workspace<'a>(
             }
         }
 
-        if !skip_line {
+        // both streams are printed to stdout to keep their order, unless stdout is kept for JSON messages
+        if !skip_line && is_stderr && is_json_output() {
+            eprintln!("{}", line);
+        } else if !skip_line {
             println!("{}", line);
         }
     }
 
-    let status = child
-        .wait()
-        .expect("Should be able to wait for the process to finish.");
+    let status = wait_child(&mut child, start);
+    // the group opened for the current crate is closed on an interruption or a timeout, the callers
+    // close their own groups
+    if close_group && (timed_out || status.is_err()) {
+        endgroup!();
+    }
+    let status = status?;
+    if timed_out {
+        return Err(timeout_error());
+    }
 
     if status.success() || ignore_error {
         if close_group {
@@ -151,7 +1023,7 @@ pub fn run_process_for_workspace<'a>(
         }
         anyhow::Ok(())
     } else {
-        Err(anyhow::anyhow!("{}", error_msg))
+        Err(process_error(error_msg, &program, &cmd_args, None, status))
     }
 }
 
@@ -171,19 +1043,41 @@ pub fn run_process_for_package(
         group_info!("Skip '{}' because it has been excluded!", package);
         return anyhow::Ok(());
     }
+    let args = process_args(name, args);
+    let program = process_program(name, &args, process_options());
+    if explain_command(&program, &args, None, None) {
+        return anyhow::Ok(());
+    }
     let joined_args = args.join(" ");
-    group_info!("Command line: cargo {}", &joined_args);
-    let output = Command::new("cargo")
-        .args(args)
+    group_info!("Command line: {} {}", program, &joined_args);
+    let mut command = process_command(&program);
+    command
+        .args(&args)
         .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output()
+        .stderr(Stdio::piped());
+    let start = Instant::now();
+    let mut child = spawn_child(&mut command)
         .map_err(|e| anyhow::anyhow!("Failed to execute process for '{}': {}", name, e))?;
+    // forward stderr while keeping a copy of it to look for the ignore log
+    let stderr_thread = child.stderr.take().map(|stderr| {
+        thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            reader
+                .lines()
+                .map_while(Result::ok)
+                .inspect(|line| eprintln!("{}", line))
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+    });
+    let status = wait_child(&mut child, start)?;
+    let stderr = stderr_thread
+        .map(|t| t.join().unwrap_or_default())
+        .unwrap_or_default();
 
-    if output.status.success() {
+    if status.success() {
         return anyhow::Ok(());
     } else if let Some(log) = ignore_log {
-        let stderr = String::from_utf8_lossy(&output.stderr);
         if stderr.contains(log) {
             if let Some(msg) = ignore_msg {
                 warn!("{}", msg);
@@ -192,7 +1086,83 @@ pub fn run_process_for_package(
             return anyhow::Ok(());
         }
     }
-    Err(anyhow::anyhow!("{}", error_msg))
+    Err(process_error(error_msg, &program, &args, None, status))
+}
+
+/// Arguments whose value is a secret which is redacted from the errors
+const SECRET_ARGS: [&str; 1] = ["--token"];
+
+/// Returns true if the passed environment variable holds a secret, like the registry tokens of cargo
+pub(crate) fn is_secret_env(key: &str) -> bool {
+    key.ends_with("_TOKEN")
+}
+
+/// Returns the arguments with the values of the secret arguments replaced by `***`
+pub(crate) fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut secret_value = false;
+    for arg in args {
+        if secret_value {
+            redacted.push("***".to_string());
+            secret_value = false;
+        } else if let Some(name) = SECRET_ARGS
+            .iter()
+            .find(|name| arg.starts_with(&format!("{name}=")))
+        {
+            redacted.push(format!("{name}=***"));
+        } else {
+            secret_value = SECRET_ARGS.contains(&arg.as_str());
+            redacted.push(arg.clone());
+        }
+    }
+    redacted
+}
+
+/// Returns the error of a process which exited with a failure status. The passed message is the
+/// error and the command line, the working directory and the exit code are attached as its cause
+/// so that the failure can be reproduced locally.
+fn process_error(
+    error_msg: &str,
+    program: &str,
+    args: &[String],
+    path: Option<&Path>,
+    status: ExitStatus,
+) -> anyhow::Error {
+    let cwd = path
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default();
+    let code = status.code().map_or_else(
+        || "none, terminated by a signal".to_string(),
+        |c| c.to_string(),
+    );
+    anyhow::anyhow!(
+        "Command `{} {}` exited with code {} in {}",
+        program,
+        redact_args(args).join(" "),
+        code,
+        cwd.display()
+    )
+    .context(error_msg.to_string())
+}
+
+/// Open the passed file with the default application of the platform, for instance a HTML report in the browser
+pub fn open_in_browser(path: &Path) -> anyhow::Result<()> {
+    let path = path.to_string_lossy();
+    let (program, args) = if cfg!(target_os = "macos") {
+        ("open", vec![path.as_ref()])
+    } else if cfg!(windows) {
+        ("cmd", vec!["/C", "start", "", path.as_ref()])
+    } else {
+        ("xdg-open", vec![path.as_ref()])
+    };
+    run_process(
+        program,
+        &args,
+        None,
+        None,
+        &format!("Failed to open {}", path),
+    )
 }
 
 /// Return a random port between 3000 and 9999
@@ -201,8 +1171,9 @@ pub fn random_port() -> u16 {
     rng.gen_range(3000..=9999)
 }
 
-fn remove_ansi_codes(s: &str) -> String {
-    let re = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+pub(crate) fn remove_ansi_codes(s: &str) -> String {
+    // the term crate used by rustfmt also selects the ASCII character set with ESC ( B
+    let re = Regex::new(r"\x1b(\[[0-9;]*m|\(B)").unwrap();
     re.replace_all(s, "").to_string()
 }
 
@@ -212,7 +1183,7 @@ fn standardize_slashes(s: &str) -> String {
 
 /// Split given VEC into a left and right vectors where SPLIT belongs to the right vector.
 /// If SPLIT does not exist in VEC then left is a VEC slice and right is empty.
-fn split_vector<T: PartialEq>(vec: &[T], split: T) -> (&[T], &[T]) {
+pub(crate) fn split_vector<T: PartialEq>(vec: &[T], split: T) -> (&[T], &[T]) {
     let mut left = vec;
     let mut right = &vec[vec.len()..];
     if let Some(pos) = vec.iter().position(|e| *e == split) {
@@ -227,6 +1198,211 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    const DIAGNOSTICS: &str = r#"{"reason":"compiler-artifact","package_id":"path+file:///ws/crates/foo#0.1.0","target":{"name":"foo","kind":["lib"]}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"warning","message":"unused variable: `x`","code":{"code":"unused_variables"},"spans":[{"file_name":"src/lib.rs","line_start":3,"line_end":4,"column_start":9,"is_primary":true}],"rendered":"warning: unused variable"}}
+{"reason":"compiler-message","target":{"name":"bar"},"message":{"level":"error","message":"mismatched types","spans":[{"file_name":"src/main.rs","line_start":1,"is_primary":false},{"file_name":"src/main.rs","line_start":7,"is_primary":true}],"rendered":"error: mismatched types"}}
+{"reason":"compiler-message","target":{"name":"bar"},"message":{"level":"error","message":"aborting due to 1 previous error","spans":[],"rendered":"error: aborting"}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"warning","message":"1 warning emitted","spans":[],"rendered":"warning: 1 warning emitted"}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"note","message":"a note","spans":[],"rendered":"note"}}
+{"reason":"build-finished","success":false}"#;
+
+    #[rstest]
+    fn test_parse_diagnostic() {
+        let diagnostics: Vec<Diagnostic> =
+            DIAGNOSTICS.lines().filter_map(parse_diagnostic).collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    crate_name: "foo".to_string(),
+                    level: "warning".to_string(),
+                    message: "unused variable: `x`".to_string(),
+                    code: Some("unused_variables".to_string()),
+                    location: Some(Location {
+                        file: "src/lib.rs".to_string(),
+                        line: 3,
+                        end_line: 4,
+                        column: Some(9),
+                    }),
+                    rendered: "warning: unused variable".to_string(),
+                },
+                Diagnostic {
+                    crate_name: "bar".to_string(),
+                    level: "error".to_string(),
+                    message: "mismatched types".to_string(),
+                    code: None,
+                    location: Some(Location {
+                        file: "src/main.rs".to_string(),
+                        line: 7,
+                        end_line: 7,
+                        column: None,
+                    }),
+                    rendered: "error: mismatched types".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_diagnostics_summary_lines() {
+        let mut summary = DiagnosticsSummary::default();
+        // duplicated diagnostics are counted once
+        DIAGNOSTICS
+            .lines()
+            .chain(DIAGNOSTICS.lines())
+            .filter_map(parse_diagnostic)
+            .for_each(|d| summary.add(d));
+        assert_eq!(
+            summary.lines(),
+            vec![
+                "bar: 1 error(s), 0 warning(s)",
+                "  error: mismatched types (src/main.rs:7)",
+                "foo: 0 error(s), 1 warning(s)",
+                "  warning: unused variable: `x` (src/lib.rs:3)",
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_diagnostics_summary_max_messages() {
+        let mut summary = DiagnosticsSummary::default();
+        for i in 0..5 {
+            summary.add(Diagnostic {
+                crate_name: "foo".to_string(),
+                level: "warning".to_string(),
+                message: format!("warning {i}"),
+                code: None,
+                location: None,
+                rendered: String::new(),
+            });
+        }
+        let lines = summary.lines();
+        assert_eq!(lines[0], "foo: 0 error(s), 5 warning(s)");
+        assert_eq!(lines.len(), 1 + SUMMARY_MAX_MESSAGES);
+    }
+
+    #[rstest]
+    #[case::other_program("typos", vec!["--diff"], ProcessOptions::default(), "typos")]
+    #[case::cargo_path("cargo", vec!["build"], ProcessOptions { cargo_path: Some(PathBuf::from("cargo-zigbuild")), ..Default::default() }, "cargo-zigbuild")]
+    #[case::toolchain_override("cargo", vec!["+nightly", "fmt"], ProcessOptions::default(), "cargo")]
+    #[case::cross_build("cargo", vec!["build", "--workspace"], ProcessOptions { use_cross: true, ..Default::default() }, "cross")]
+    #[case::cross_toolchain_override("cargo", vec!["+nightly", "test"], ProcessOptions { use_cross: true, ..Default::default() }, "cross")]
+    #[case::cross_unsupported_subcommand("cargo", vec!["install", "grcov"], ProcessOptions { use_cross: true, cargo_path: Some(PathBuf::from("cargo")), ..Default::default() }, "cargo")]
+    fn test_process_program(
+        #[case] name: &str,
+        #[case] args: Vec<&str>,
+        #[case] options: ProcessOptions,
+        #[case] expected: &str,
+    ) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        assert_eq!(process_program(name, &args, &options), expected);
+    }
+
+    #[rstest]
+    fn test_process_program_defaults_to_cargo_env() {
+        // cargo sets CARGO when running the tests
+        assert_eq!(
+            process_program("cargo", &["build".to_string()], &ProcessOptions::default()),
+            std::env::var("CARGO").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_resolve_program() {
+        let cargo = resolve_program("cargo");
+        assert!(cargo.is_absolute(), "{}", cargo.display());
+        assert_eq!(
+            resolve_program("does-not-exist-xtask"),
+            PathBuf::from("does-not-exist-xtask")
+        );
+    }
+
+    // smoke test of the runners on all the platforms, no shell is involved
+    #[rstest]
+    fn test_runners_execute_program() {
+        run_process(
+            "cargo",
+            &["--version"],
+            None,
+            None,
+            "cargo --version failed",
+        )
+        .unwrap();
+        let output = run_process_with_output("rustc", &["--version"], None, None).unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("rustc "));
+        assert!(run_process("does-not-exist-xtask", &["--version"], None, None, "").is_err());
+    }
+
+    #[rstest]
+    #[case::no_selection(vec![], vec![], vec!["test", "--workspace", "--", "--nocapture"])]
+    #[case::excluded(vec!["b"], vec![], vec!["test", "--workspace", "--exclude", "b", "--", "--nocapture"])]
+    #[case::only(vec![], vec!["a", "b"], vec!["test", "-p", "a", "-p", "b", "--", "--nocapture"])]
+    #[case::only_and_excluded(vec!["b"], vec!["a", "b"], vec!["test", "-p", "a", "--", "--nocapture"])]
+    fn test_workspace_package_args(
+        #[case] excluded: Vec<&str>,
+        #[case] only: Vec<&str>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let excluded: Vec<String> = excluded.into_iter().map(String::from).collect();
+        let only: Vec<String> = only.into_iter().map(String::from).collect();
+        assert_eq!(
+            workspace_package_args(
+                &["test", "--workspace", "--", "--nocapture"],
+                &excluded,
+                &only
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::separate_value(vec!["publish", "--token", "secret"], vec!["publish", "--token", "***"])]
+    #[case::inline_value(vec!["publish", "--token=secret"], vec!["publish", "--token=***"])]
+    #[case::no_secret(vec!["build", "--workspace"], vec!["build", "--workspace"])]
+    fn test_redact_args(#[case] args: Vec<&str>, #[case] expected: Vec<&str>) {
+        let args: Vec<String> = args.into_iter().map(String::from).collect();
+        assert_eq!(redact_args(&args), expected);
+    }
+
+    #[rstest]
+    fn test_failed_process_error_has_the_command_line() {
+        let dir = std::env::temp_dir();
+        let err = run_process(
+            "rustc",
+            &["--no-such-flag"],
+            None,
+            Some(&dir),
+            "Compilation failed",
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Compilation failed");
+        let cause = err.root_cause().to_string();
+        assert!(cause.starts_with("Command `rustc --no-such-flag` exited with code 1 in "));
+        assert!(cause.ends_with(&dir.display().to_string()));
+    }
+
+    #[rstest]
+    fn test_run_process_buffered() {
+        let output = run_process_buffered("rustc", &["--version"], None, None).unwrap();
+        assert!(output.status.success());
+        assert!(matches!(
+            output.lines.as_slice(),
+            [(OutputStream::Stdout, line)] if line.starts_with("rustc ")
+        ));
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        BufferedOutput {
+            status: output.status,
+            lines: vec![
+                (OutputStream::Stderr, "Compiling a".to_string()),
+                (OutputStream::Stdout, "test ok".to_string()),
+            ],
+        }
+        .replay_to(&mut stdout, &mut stderr);
+        assert_eq!(String::from_utf8(stdout).unwrap(), "test ok\n");
+        assert_eq!(String::from_utf8(stderr).unwrap(), "Compiling a\n");
+    }
+
     #[rstest]
     fn test_random_port_in_range() {
         for _ in 0..10000 {
@@ -243,6 +1419,7 @@ mod tests {
     #[case::simple_escape_code("\x1b[31mRed Text\x1b[0m", "Red Text")]
     #[case::complex_escape_code("\x1b[1;34mBold Blue Text\x1b[0m", "Bold Blue Text")]
     #[case::no_escape_code("No ANSI Codes", "No ANSI Codes")]
+    #[case::term_reset("\x1b[32m+Added\x1b(B\x1b[m", "+Added")]
     fn test_remove_ansi_codes(#[case] input: &str, #[case] expected: &str) {
         let result = remove_ansi_codes(input);
         assert_eq!(
@@ -284,4 +1461,45 @@ mod tests {
         assert_eq!(left, &expected_left);
         assert_eq!(right, &expected_right);
     }
+
+    #[rstest]
+    #[case::no_options(vec!["build", "--workspace"], ProcessOptions::default(), vec!["build", "--workspace"])]
+    #[case::locked(vec!["build", "--workspace"], ProcessOptions { locked: true, ..Default::default() }, vec!["build", "--workspace", "--locked"])]
+    #[case::all_options(vec!["test", "--workspace"], ProcessOptions { locked: true, frozen: true, offline: true, ..Default::default() }, vec!["test", "--workspace", "--locked", "--frozen", "--offline"])]
+    #[case::before_binary_args(vec!["test", "--workspace", "--", "--color=always"], ProcessOptions { offline: true, ..Default::default() }, vec!["test", "--workspace", "--offline", "--", "--color=always"])]
+    #[case::toolchain_override(vec!["+nightly", "build"], ProcessOptions { frozen: true, ..Default::default() }, vec!["+nightly", "build", "--frozen"])]
+    #[case::already_present(vec!["install", "grcov", "--locked"], ProcessOptions { locked: true, ..Default::default() }, vec!["install", "grcov", "--locked"])]
+    #[case::unsupported_subcommand(vec!["fmt", "--check"], ProcessOptions { locked: true, ..Default::default() }, vec!["fmt", "--check"])]
+    #[case::wasm_build(vec!["build", "--workspace"], ProcessOptions { execution_environment: ExecutionEnvironment::WasmBrowser, ..Default::default() }, vec!["build", "--workspace", "--target", "wasm32-unknown-unknown"])]
+    #[case::wasm_explicit_target(vec!["check", "--target=x86_64-unknown-linux-gnu"], ProcessOptions { execution_environment: ExecutionEnvironment::WasmBrowser, ..Default::default() }, vec!["check", "--target=x86_64-unknown-linux-gnu"])]
+    #[case::wasm_test(vec!["test", "--workspace"], ProcessOptions { execution_environment: ExecutionEnvironment::WasmBrowser, ..Default::default() }, vec!["test", "--workspace"])]
+    #[case::message_format(vec!["clippy", "--workspace", "--", "--deny", "warnings"], ProcessOptions { message_format: Some("json".to_string()), ..Default::default() }, vec!["clippy", "--workspace", "--message-format=json", "--", "--deny", "warnings"])]
+    #[case::own_message_format(vec!["build", "--message-format=json"], ProcessOptions { message_format: Some("short".to_string()), ..Default::default() }, vec!["build", "--message-format=json"])]
+    #[case::message_format_unsupported_subcommand(vec!["fmt", "--check"], ProcessOptions { message_format: Some("json".to_string()), ..Default::default() }, vec!["fmt", "--check"])]
+    #[case::non_default_members(vec!["test", "--workspace", "--exclude", "b", "--", "--color=always"], ProcessOptions { non_default_members: vec!["b".to_string(), "c".to_string()], ..Default::default() }, vec!["test", "--workspace", "--exclude", "b", "--exclude", "c", "--", "--color=always"])]
+    #[case::non_default_members_package(vec!["build", "-p", "c"], ProcessOptions { non_default_members: vec!["c".to_string()], ..Default::default() }, vec!["build", "-p", "c"])]
+    fn test_cargo_args_with_options(
+        #[case] args: Vec<&str>,
+        #[case] options: ProcessOptions,
+        #[case] expected: Vec<&str>,
+    ) {
+        let result = cargo_args_with_options(&args, &options);
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case::separate_value(vec!["build", "--color", "always"], "never", vec!["build", "--color", "never"])]
+    #[case::equal_value(vec!["clippy", "--color=always", "--", "--deny", "warnings"], "never", vec!["clippy", "--color=never", "--", "--deny", "warnings"])]
+    #[case::cargo_and_binary_args(vec!["test", "--color", "never", "--", "--color=never"], "always", vec!["test", "--color", "always", "--", "--color=never"])]
+    #[case::binary_args_only(vec!["run", "--", "--color", "auto", "--", "--color=auto"], "never", vec!["run", "--", "--color", "auto", "--", "--color=auto"])]
+    #[case::no_color_arg(vec!["fmt", "--check"], "never", vec!["fmt", "--check"])]
+    fn test_args_with_color(
+        #[case] args: Vec<&str>,
+        #[case] color: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let result = args_with_color(&args, color);
+        assert_eq!(result, expected);
+    }
 }
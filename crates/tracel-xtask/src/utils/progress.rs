@@ -0,0 +1,91 @@
+use std::{
+    io::{BufRead, BufReader, IsTerminal, Read, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Interval at which the spinner is redrawn
+const SPINNER_TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+static PROGRESS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Disable the spinner shown while the processes run, it is done by `init_xtask` with `--no-progress`.
+pub fn disable_progress() {
+    PROGRESS_DISABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the spinner is shown while the processes run.
+/// It is only shown for interactive runs: both stdout and stderr are terminals and xtask is not running on CI.
+pub fn is_progress_enabled() -> bool {
+    progress_enabled(
+        PROGRESS_DISABLED.load(Ordering::SeqCst),
+        std::env::var("CI").is_ok(),
+        std::io::stdout().is_terminal() && std::io::stderr().is_terminal(),
+    )
+}
+
+fn progress_enabled(disabled: bool, ci: bool, terminal: bool) -> bool {
+    !disabled && !ci && terminal
+}
+
+/// Start a spinner displaying the passed message and the elapsed time, if the progress is enabled
+pub(crate) fn start_spinner(message: &str) -> Option<ProgressBar> {
+    if !is_progress_enabled() {
+        return None;
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message.to_string());
+    spinner.set_style(
+        ProgressStyle::with_template("{spinner} {wide_msg} [{elapsed}]")
+            .expect("spinner template should be valid"),
+    );
+    spinner.enable_steady_tick(SPINNER_TICK_INTERVAL);
+    Some(spinner)
+}
+
+/// Forward the lines of the passed output of a child process to the writer while the spinner is
+/// hidden, so that the spinner always yields to the output of the process.
+pub(crate) fn forward_output<R, W>(
+    spinner: &ProgressBar,
+    output: R,
+    mut writer: W,
+) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let spinner = spinner.clone();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+            spinner.suspend(|| {
+                let _ = writer.write_all(&line);
+                let _ = writer.flush();
+            });
+            line.clear();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::interactive(false, false, true, true)]
+    #[case::disabled(true, false, true, false)]
+    #[case::ci(false, true, true, false)]
+    #[case::not_a_terminal(false, false, false, false)]
+    fn test_progress_enabled(
+        #[case] disabled: bool,
+        #[case] ci: bool,
+        #[case] terminal: bool,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(progress_enabled(disabled, ci, terminal), expected);
+    }
+}
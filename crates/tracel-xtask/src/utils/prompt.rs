@@ -1,12 +1,200 @@
-use std::io::{self, Write};
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Answer the prompts without asking, it is done by `init_xtask` with `--assume-yes`.
+/// `ask_once` answers yes and the other prompts return their default.
+pub fn assume_yes() {
+    ASSUME_YES.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the prompts are answered by the user: `--assume-yes` is not set and stdin is a terminal.
+fn is_interactive() -> bool {
+    !ASSUME_YES.load(Ordering::SeqCst) && io::stdin().is_terminal()
+}
+
+/// Ask to proceed and return the answer, no is the default answer.
 pub fn ask_once(prompt: &str) -> bool {
-    print!("{}\nDo you want to proceed? (yes/no): ", prompt);
-    io::stdout().flush().expect("stdout should be flushed");
-
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("should be able to read stdin line");
-    input.trim().to_lowercase() == "yes" || input.trim().to_lowercase() == "y"
+    ask_once_with_default(prompt, false)
+}
+
+/// Ask to proceed and return the answer, the default is the answer when it is empty or at the end of the input.
+/// Unrecognized answers are asked again, the accepted answers are y, yes, n and no in any case.
+pub fn ask_once_with_default(prompt: &str, default: bool) -> bool {
+    if ASSUME_YES.load(Ordering::SeqCst) {
+        info!("{}\nProceeding as --assume-yes is set.", prompt);
+        return true;
+    }
+    read_confirmation(&mut io::stdin().lock(), &mut io::stdout(), prompt, default)
+}
+
+/// Ask a free-text question and return the answer, or the default when the answer is empty.
+/// The default is returned without asking when the run is not interactive.
+pub fn prompt_text(question: &str, default: &str) -> String {
+    if !is_interactive() {
+        return default.to_string();
+    }
+    read_text(
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        question,
+        default,
+    )
+}
+
+/// Ask to choose one of the options and return the index of the chosen option, the first one by default.
+/// The first option is returned without asking when the run is not interactive.
+pub fn prompt_select(question: &str, options: &[&str]) -> usize {
+    if !is_interactive() {
+        return 0;
+    }
+    read_select(
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        question,
+        options,
+    )
+}
+
+fn read_answer(input: &mut impl BufRead) -> Option<String> {
+    let mut answer = String::new();
+    match input
+        .read_line(&mut answer)
+        .expect("should be able to read stdin line")
+    {
+        0 => None,
+        _ => Some(answer.trim().to_string()),
+    }
+}
+
+fn read_confirmation(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    prompt: &str,
+    default: bool,
+) -> bool {
+    let choices = if default { "Y/n" } else { "y/N" };
+    writeln!(output, "{}", prompt).expect("stdout should be written");
+    loop {
+        write!(output, "Do you want to proceed? [{}]: ", choices)
+            .expect("stdout should be written");
+        output.flush().expect("stdout should be flushed");
+        let Some(answer) = read_answer(input) else {
+            return default;
+        };
+        match answer.to_lowercase().as_str() {
+            "" => return default,
+            "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => writeln!(output, "Please answer yes or no.").expect("stdout should be written"),
+        }
+    }
+}
+
+fn read_text(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> String {
+    write!(output, "{} [{}]: ", question, default).expect("stdout should be written");
+    output.flush().expect("stdout should be flushed");
+    match read_answer(input) {
+        Some(answer) if !answer.is_empty() => answer,
+        _ => default.to_string(),
+    }
+}
+
+fn read_select(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    options: &[&str],
+) -> usize {
+    writeln!(output, "{}", question).expect("stdout should be written");
+    for (i, option) in options.iter().enumerate() {
+        writeln!(output, "  {}) {}", i + 1, option).expect("stdout should be written");
+    }
+    loop {
+        write!(output, "Select an option (1-{}) [1]: ", options.len())
+            .expect("stdout should be written");
+        output.flush().expect("stdout should be flushed");
+        let answer = match read_answer(input) {
+            Some(answer) if !answer.is_empty() => answer,
+            // empty answer or end of input
+            _ => return 0,
+        };
+        match answer.parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => return choice - 1,
+            _ => {
+                writeln!(output, "Invalid option '{}'.", answer).expect("stdout should be written")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::Cursor;
+
+    #[rstest]
+    #[case::yes("yes\n", false, true)]
+    #[case::short_yes_uppercase("Y\n", false, true)]
+    #[case::no("No\n", true, false)]
+    #[case::invalid_then_yes("yeah\ny\n", false, true)]
+    #[case::empty_answer("\n", true, true)]
+    #[case::end_of_input_default_no("", false, false)]
+    #[case::end_of_input_default_yes("yeah\n", true, true)]
+    fn test_read_confirmation(#[case] input: &str, #[case] default: bool, #[case] expected: bool) {
+        let mut output = vec![];
+        let answer = read_confirmation(&mut Cursor::new(input), &mut output, "Proceed?", default);
+        assert_eq!(answer, expected);
+    }
+
+    #[rstest]
+    #[case::default_no(false, "[y/N]")]
+    #[case::default_yes(true, "[Y/n]")]
+    fn test_read_confirmation_shows_default(#[case] default: bool, #[case] choices: &str) {
+        let mut output = vec![];
+        read_confirmation(&mut Cursor::new(""), &mut output, "Proceed?", default);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("Proceed?\nDo you want to proceed? {}: ", choices)
+        );
+    }
+
+    #[rstest]
+    #[case::answer("my-crate\n", "my-crate")]
+    #[case::empty_answer("\n", "default")]
+    #[case::end_of_input("", "default")]
+    fn test_read_text(#[case] input: &str, #[case] expected: &str) {
+        let mut output = vec![];
+        let answer = read_text(&mut Cursor::new(input), &mut output, "Name?", "default");
+        assert_eq!(answer, expected);
+        assert_eq!(String::from_utf8(output).unwrap(), "Name? [default]: ");
+    }
+
+    #[rstest]
+    #[case::choice("2\n", 1)]
+    #[case::empty_answer("\n", 0)]
+    #[case::invalid_then_choice("4\nminor\n3\n", 2)]
+    #[case::invalid_then_end_of_input("0\n", 0)]
+    fn test_read_select(#[case] input: &str, #[case] expected: usize) {
+        let mut output = vec![];
+        let choice = read_select(
+            &mut Cursor::new(input),
+            &mut output,
+            "Bump?",
+            &["major", "minor", "patch"],
+        );
+        assert_eq!(choice, expected);
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("Bump?\n  1) major\n  2) minor\n  3) patch\n"));
+    }
 }
@@ -30,6 +30,36 @@ pub fn rustup_add_component(component: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Install a Rust toolchain with the given components
+pub fn rustup_install_toolchain(toolchain: &str, components: &[&str]) -> anyhow::Result<()> {
+    group!("Rustup: install toolchain {}", toolchain);
+    let mut args = vec!["toolchain", "install", toolchain, "--profile", "minimal"];
+    components
+        .iter()
+        .for_each(|c| args.extend(["--component", c]));
+    run_process(
+        "rustup",
+        &args,
+        None,
+        None,
+        &format!("Failed to install toolchain {toolchain}"),
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Returns true if the passed toolchain is installed
+pub fn is_toolchain_installed(toolchain: &str) -> bool {
+    let output = Command::new("rustup")
+        .args(["toolchain", "list"])
+        .output()
+        .expect("Should get the list of installed Rust toolchains");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    output_str
+        .lines()
+        .any(|line| line.starts_with(&format!("{toolchain}-")) || line.trim() == toolchain)
+}
+
 // Returns the output of the rustup command to get the installed targets
 pub fn rustup_get_installed_targets() -> String {
     let output = Command::new("rustup")
@@ -0,0 +1,330 @@
+use std::{collections::HashSet, path::Path};
+
+use serde_json::{json, Value};
+
+use super::{
+    explain::explain_write, outcome::record_artifact, process::Diagnostic,
+    workspace::invocation_path,
+};
+
+pub const SARIF_VERSION: &str = "2.1.0";
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Tool reported as the driver of a SARIF run
+pub struct SarifTool {
+    pub name: &'static str,
+    pub information_uri: &'static str,
+}
+
+pub const CLIPPY_TOOL: SarifTool = SarifTool {
+    name: "clippy",
+    information_uri: "https://github.com/rust-lang/rust-clippy",
+};
+
+pub const CARGO_AUDIT_TOOL: SarifTool = SarifTool {
+    name: "cargo-audit",
+    information_uri: "https://github.com/rustsec/rustsec/tree/main/cargo-audit",
+};
+
+/// A finding of a SARIF run
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SarifResult {
+    pub rule_id: Option<String>,
+    /// one of `error`, `warning` or `note`
+    pub level: String,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+}
+
+impl SarifResult {
+    fn to_json(&self) -> Value {
+        let mut result = json!({
+            "level": self.level,
+            "message": { "text": self.message },
+        });
+        if let Some(rule_id) = &self.rule_id {
+            result["ruleId"] = json!(rule_id);
+        }
+        if let Some(file) = &self.file {
+            let mut location = json!({ "artifactLocation": { "uri": file } });
+            if let Some(line) = self.line {
+                location["region"] = json!({ "startLine": line });
+                if let Some(column) = self.column {
+                    location["region"]["startColumn"] = json!(column);
+                }
+            }
+            result["locations"] = json!([{ "physicalLocation": location }]);
+        }
+        result
+    }
+}
+
+/// Build a SARIF run of the given tool and results.
+pub fn sarif_run(tool: &SarifTool, results: &[SarifResult]) -> Value {
+    let mut rule_ids: Vec<&String> = results.iter().filter_map(|r| r.rule_id.as_ref()).collect();
+    rule_ids.sort();
+    rule_ids.dedup();
+    json!({
+        "tool": {
+            "driver": {
+                "name": tool.name,
+                "informationUri": tool.information_uri,
+                "rules": rule_ids.iter().map(|id| json!({ "id": id })).collect::<Vec<Value>>(),
+            }
+        },
+        "results": results.iter().map(SarifResult::to_json).collect::<Vec<Value>>(),
+    })
+}
+
+/// Write the results of the tool to a SARIF file.
+/// The runs of the other tools already present in the file are kept so that the lint and
+/// audit findings can share the same report.
+pub fn write_sarif(path: &Path, tool: &SarifTool, results: &[SarifResult]) -> anyhow::Result<()> {
+    let path = &invocation_path(path);
+    if explain_write(&format!("write {}", path.display())) {
+        return Ok(());
+    }
+    let mut runs: Vec<Value> = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|log| log["runs"].as_array().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|run| run["tool"]["driver"]["name"] != tool.name)
+        .collect();
+    runs.push(sarif_run(tool, results));
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": runs,
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&log)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write SARIF file {}: {}", path.display(), e))?;
+    info!("SARIF report written to {}", path.display());
+    record_artifact(path);
+    Ok(())
+}
+
+/// Convert the compiler diagnostics to SARIF results.
+/// The same diagnostic reported for several targets of a crate is kept once.
+pub(crate) fn clippy_results(diagnostics: &[Diagnostic]) -> Vec<SarifResult> {
+    let mut seen = HashSet::new();
+    diagnostics
+        .iter()
+        .map(|d| SarifResult {
+            rule_id: d.code.clone(),
+            level: d.level.clone(),
+            message: d.message.clone(),
+            file: d.location.as_ref().map(|l| l.file.clone()),
+            line: d.location.as_ref().map(|l| l.line),
+            column: d.location.as_ref().and_then(|l| l.column),
+        })
+        .filter(|r| seen.insert(r.clone()))
+        .collect()
+}
+
+/// Convert the JSON report of `cargo audit --json` to SARIF results.
+/// Vulnerabilities are errors and the other advisories (unmaintained, yanked...) are warnings.
+pub(crate) fn audit_results(report: &str) -> anyhow::Result<Vec<SarifResult>> {
+    let report: Value = serde_json::from_str(report)
+        .map_err(|e| anyhow::anyhow!("Failed to parse cargo-audit report: {}", e))?;
+    let finding = |entry: &Value, level: &str| {
+        let package = format!(
+            "{} {}",
+            entry["package"]["name"].as_str().unwrap_or_default(),
+            entry["package"]["version"].as_str().unwrap_or_default()
+        );
+        let advisory = &entry["advisory"];
+        let message = match advisory["title"].as_str() {
+            Some(title) => format!("{}: {}", package, title),
+            None => format!("{}: {}", package, entry["kind"].as_str().unwrap_or(level)),
+        };
+        SarifResult {
+            rule_id: advisory["id"]
+                .as_str()
+                .or(entry["kind"].as_str())
+                .map(String::from),
+            level: level.to_string(),
+            message,
+            file: Some("Cargo.lock".to_string()),
+            line: None,
+            column: None,
+        }
+    };
+    let mut results: Vec<SarifResult> = report["vulnerabilities"]["list"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| finding(entry, "error"))
+        .collect();
+    if let Some(warnings) = report["warnings"].as_object() {
+        results.extend(
+            warnings
+                .values()
+                .filter_map(Value::as_array)
+                .flatten()
+                .map(|entry| finding(entry, "warning")),
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::process::parse_diagnostic;
+    use rstest::rstest;
+
+    const CLIPPY_OUTPUT: &str = r#"{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"error","message":"unneeded `return` statement","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"crates/foo/src/lib.rs","line_start":4,"column_start":5,"is_primary":true}],"rendered":"error: unneeded `return` statement"}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"error","message":"unneeded `return` statement","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"crates/foo/src/lib.rs","line_start":4,"column_start":5,"is_primary":true}],"rendered":"error: unneeded `return` statement"}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"crates/foo/src/main.rs","line_start":7,"column_start":12,"is_primary":true}],"rendered":"error: mismatched types"}}
+{"reason":"compiler-message","target":{"name":"foo"},"message":{"level":"error","message":"aborting due to 2 previous errors","spans":[],"rendered":"error: aborting"}}
+{"reason":"build-finished","success":false}"#;
+
+    const AUDIT_OUTPUT: &str = r#"{
+  "vulnerabilities": {
+    "found": true,
+    "count": 1,
+    "list": [
+      {
+        "advisory": { "id": "RUSTSEC-2020-0071", "package": "time", "title": "Potential segfault in the time crate" },
+        "package": { "name": "time", "version": "0.1.45" }
+      }
+    ]
+  },
+  "warnings": {
+    "unmaintained": [
+      {
+        "kind": "unmaintained",
+        "advisory": { "id": "RUSTSEC-2021-0139", "package": "ansi_term", "title": "ansi_term is Unmaintained" },
+        "package": { "name": "ansi_term", "version": "0.12.1" }
+      }
+    ],
+    "yanked": [
+      { "kind": "yanked", "advisory": null, "package": { "name": "foo", "version": "1.0.0" } }
+    ]
+  }
+}"#;
+
+    /// Check the log against the required properties and constraints of the SARIF 2.1.0 schema
+    /// for the objects emitted by xtask.
+    fn assert_valid_sarif(log: &Value) {
+        assert_eq!(log["version"], SARIF_VERSION);
+        assert!(log["$schema"].is_string());
+        let runs = log["runs"].as_array().expect("runs must be an array");
+        for run in runs {
+            let driver = &run["tool"]["driver"];
+            assert!(driver["name"].is_string(), "tool.driver.name is required");
+            let rules = driver["rules"].as_array().expect("rules must be an array");
+            assert!(rules.iter().all(|rule| rule["id"].is_string()));
+            for result in run["results"].as_array().expect("results must be an array") {
+                assert!(
+                    result["message"]["text"].is_string(),
+                    "message.text is required"
+                );
+                assert!(["none", "note", "warning", "error"]
+                    .contains(&result["level"].as_str().unwrap_or_default()));
+                if let Some(rule_id) = result["ruleId"].as_str() {
+                    assert!(rules.iter().any(|rule| rule["id"] == rule_id));
+                }
+                for location in result["locations"].as_array().into_iter().flatten() {
+                    let physical = &location["physicalLocation"];
+                    assert!(physical["artifactLocation"]["uri"].is_string());
+                    let region = &physical["region"];
+                    if !region.is_null() {
+                        assert!(region["startLine"].as_u64().unwrap() >= 1);
+                        assert!(region["startColumn"].as_u64().map_or(true, |c| c >= 1));
+                    }
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_clippy_results() {
+        let diagnostics: Vec<Diagnostic> =
+            CLIPPY_OUTPUT.lines().filter_map(parse_diagnostic).collect();
+        assert_eq!(
+            clippy_results(&diagnostics),
+            vec![
+                SarifResult {
+                    rule_id: Some("clippy::needless_return".to_string()),
+                    level: "error".to_string(),
+                    message: "unneeded `return` statement".to_string(),
+                    file: Some("crates/foo/src/lib.rs".to_string()),
+                    line: Some(4),
+                    column: Some(5),
+                },
+                SarifResult {
+                    rule_id: Some("E0308".to_string()),
+                    level: "error".to_string(),
+                    message: "mismatched types".to_string(),
+                    file: Some("crates/foo/src/main.rs".to_string()),
+                    line: Some(7),
+                    column: Some(12),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_audit_results() {
+        let results = audit_results(AUDIT_OUTPUT).unwrap();
+        let summary: Vec<(Option<&str>, &str, &str)> = results
+            .iter()
+            .map(|r| (r.rule_id.as_deref(), r.level.as_str(), r.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some("RUSTSEC-2020-0071"),
+                    "error",
+                    "time 0.1.45: Potential segfault in the time crate"
+                ),
+                (
+                    Some("RUSTSEC-2021-0139"),
+                    "warning",
+                    "ansi_term 0.12.1: ansi_term is Unmaintained"
+                ),
+                (Some("yanked"), "warning", "foo 1.0.0: yanked"),
+            ]
+        );
+        assert!(results
+            .iter()
+            .all(|r| r.file.as_deref() == Some("Cargo.lock")));
+    }
+
+    #[rstest]
+    fn test_write_sarif_is_valid_and_keeps_other_runs() {
+        let path = std::env::temp_dir().join(format!("xtask-sarif-{}.json", std::process::id()));
+        let diagnostics: Vec<Diagnostic> =
+            CLIPPY_OUTPUT.lines().filter_map(parse_diagnostic).collect();
+        write_sarif(&path, &CLIPPY_TOOL, &clippy_results(&diagnostics)).unwrap();
+        write_sarif(
+            &path,
+            &CARGO_AUDIT_TOOL,
+            &audit_results(AUDIT_OUTPUT).unwrap(),
+        )
+        .unwrap();
+        // writing the lint results again replaces the previous clippy run
+        write_sarif(&path, &CLIPPY_TOOL, &clippy_results(&diagnostics)).unwrap();
+        let log: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_valid_sarif(&log);
+        let tools: Vec<&str> = log["runs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|run| run["tool"]["driver"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(tools, vec!["cargo-audit", "clippy"]);
+        assert_eq!(log["runs"][1]["results"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            log["runs"][1]["results"][0]["locations"][0]["physicalLocation"]["region"],
+            json!({ "startLine": 4, "startColumn": 5 })
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{
+    endgroup, group,
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        process::{run_process, run_query_with_output},
+    },
+};
+
+/// Environment variable of cargo naming the program wrapping rustc
+pub const RUSTC_WRAPPER: &str = "RUSTC_WRAPPER";
+
+static SCCACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if the passed `RUSTC_WRAPPER` value runs sccache
+fn is_sccache_wrapper(wrapper: &str) -> bool {
+    std::path::Path::new(wrapper)
+        .file_stem()
+        .is_some_and(|stem| stem == "sccache")
+}
+
+/// Returns the `RUSTC_WRAPPER` variable to set for the spawned processes, if any.
+/// With `--sccache` sccache is installed if needed and set as the wrapper of rustc, otherwise a
+/// `RUSTC_WRAPPER` already running sccache is detected. In both cases the statistics of sccache
+/// are printed at the end of the run.
+pub(crate) fn setup_sccache(requested: bool) -> anyhow::Result<Option<(String, String)>> {
+    let wrapper = std::env::var(RUSTC_WRAPPER).ok();
+    if !requested {
+        if wrapper.as_deref().is_some_and(is_sccache_wrapper) {
+            SCCACHE_ENABLED.store(true, Ordering::SeqCst);
+        }
+        return Ok(None);
+    }
+    if let Some(wrapper) = wrapper.filter(|w| !w.is_empty() && !is_sccache_wrapper(w)) {
+        warn!(
+            "{} is set to '{}', it is replaced by sccache because of '--sccache' flag.",
+            RUSTC_WRAPPER, wrapper
+        );
+    }
+    if !run_query_with_output("sccache", &["--version"], None)
+        .is_ok_and(|output| output.status.success())
+    {
+        ensure_cargo_crate_is_installed("sccache", None, None, false)?;
+    }
+    SCCACHE_ENABLED.store(true, Ordering::SeqCst);
+    Ok(Some((RUSTC_WRAPPER.to_string(), "sccache".to_string())))
+}
+
+/// Returns true if the rustc invocations of the spawned cargo processes go through sccache
+pub fn is_sccache_enabled() -> bool {
+    SCCACHE_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Warn that the coverage instrumentation changes the compiler flags and thus the cache keys of sccache
+pub(crate) fn warn_if_sccache_with_coverage() {
+    if is_sccache_enabled() {
+        warn!(
+            "Coverage instrumentation is enabled along with sccache, the instrumented builds do not share the cache \
+             of the regular builds and the profiling flags can make the crates non-cacheable."
+        );
+    }
+}
+
+/// Print the statistics of sccache when it is enabled, it is done by the dispatcher of the base
+/// commands at the end of the run. Failing to get the statistics only emits a warning.
+pub fn print_sccache_stats() {
+    if !is_sccache_enabled() {
+        return;
+    }
+    group!("Sccache statistics");
+    if let Err(e) = run_process(
+        "sccache",
+        &["--show-stats"],
+        None,
+        None,
+        "Failed to get the sccache statistics",
+    ) {
+        warn!("{}", e);
+    }
+    endgroup!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::name("sccache", true)]
+    #[case::path("/usr/local/bin/sccache", true)]
+    #[case::windows_exe("C:\\tools\\sccache.exe", cfg!(windows))]
+    #[case::other_wrapper("cachepot", false)]
+    fn test_is_sccache_wrapper(#[case] wrapper: &str, #[case] expected: bool) {
+        assert_eq!(is_sccache_wrapper(wrapper), expected);
+    }
+}
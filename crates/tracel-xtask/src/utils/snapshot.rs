@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Contents of the files under some paths, taken before and after generating files
+#[derive(Debug, Default, PartialEq)]
+pub struct Snapshot {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Read the files under the passed paths, the missing paths are skipped
+    pub fn take(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut snapshot = Self::default();
+        for path in paths {
+            snapshot.read(path)?;
+        }
+        Ok(snapshot)
+    }
+
+    fn read(&mut self, path: &Path) -> anyhow::Result<()> {
+        if path.is_dir() {
+            for entry in std::fs::read_dir(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?
+            {
+                self.read(&entry?.path())?;
+            }
+        } else if path.is_file() {
+            let contents = std::fs::read(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+            self.files.insert(path.to_path_buf(), contents);
+        }
+        Ok(())
+    }
+
+    /// Returns the files created, modified or removed since the passed snapshot
+    pub fn changed_since(&self, before: &Snapshot) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|(path, contents)| before.files.get(*path) != Some(contents))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            before
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+
+    /// Write back the files of the snapshot and remove the files created since it was taken
+    pub fn restore(&self, current: &Snapshot) -> anyhow::Result<()> {
+        for path in current
+            .files
+            .keys()
+            .filter(|p| !self.files.contains_key(*p))
+        {
+            std::fs::remove_file(path)
+                .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        for (path, contents) in &self.files {
+            if current.files.get(path) != Some(contents) {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, contents)
+                    .map_err(|e| anyhow::anyhow!("Failed to restore {}: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run the passed generation and fail if it changed the files under the passed paths.
+/// The files are restored afterwards so that the tree is not modified, the error tells to run the
+/// passed xtask command to update the generated output.
+pub fn check_generated_output<T>(
+    paths: &[PathBuf],
+    command: &str,
+    generate: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let before = Snapshot::take(paths)?;
+    let result = generate();
+    let after = Snapshot::take(paths)?;
+    before.restore(&after)?;
+    let output = result?;
+    let changed = after.changed_since(&before);
+    if !changed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The generated output is out of date, run `cargo xtask {}` to update it:\n  {}",
+            command,
+            changed
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join("\n  ")
+        ));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("xtask-snapshot-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("gen")).unwrap();
+        std::fs::write(dir.join("gen/api.rs"), "old").unwrap();
+        dir
+    }
+
+    #[rstest]
+    fn test_up_to_date_output() {
+        let dir = temp_dir("up-to-date");
+        let result = check_generated_output(&[dir.join("gen")], "generate", || {
+            std::fs::write(dir.join("gen/api.rs"), "old")?;
+            Ok(())
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[rstest]
+    fn test_stale_output_is_restored() {
+        let dir = temp_dir("stale");
+        let result = check_generated_output(&[dir.join("gen")], "generate", || {
+            std::fs::write(dir.join("gen/api.rs"), "new")?;
+            std::fs::write(dir.join("gen/added.rs"), "added")?;
+            Ok(())
+        });
+        let contents = std::fs::read_to_string(dir.join("gen/api.rs")).unwrap();
+        let added_exists = dir.join("gen/added.rs").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let err = result.unwrap_err().to_string();
+        assert!(err.starts_with(
+            "The generated output is out of date, run `cargo xtask generate` to update it:"
+        ));
+        assert!(err.contains("added.rs") && err.contains("api.rs"));
+        assert_eq!(contents, "old");
+        assert!(!added_exists);
+    }
+
+    #[rstest]
+    fn test_removed_files_are_changed() {
+        let before = Snapshot {
+            files: BTreeMap::from([(PathBuf::from("a"), vec![1]), (PathBuf::from("b"), vec![2])]),
+        };
+        let after = Snapshot {
+            files: BTreeMap::from([(PathBuf::from("a"), vec![1])]),
+        };
+        assert_eq!(after.changed_since(&before), vec![PathBuf::from("b")]);
+    }
+}
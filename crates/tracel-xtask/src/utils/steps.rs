@@ -0,0 +1,191 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::logging::{group_depth, restore_group_depth};
+use crate::utils::{
+    explain::is_explain_enabled,
+    outcome::record_steps,
+    process::{is_interrupted, process_options},
+    time::format_duration,
+};
+
+/// Outcome of a step of a command running several steps
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepOutcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl StepOutcome {
+    fn label(&self, colored: bool) -> String {
+        let (label, color) = match self {
+            StepOutcome::Passed => ("PASSED", "32"),
+            StepOutcome::Failed => ("FAILED", "31"),
+            StepOutcome::Skipped => ("SKIPPED", "33"),
+        };
+        if colored {
+            format!("\x1b[{color}m{label:<7}\x1b[0m")
+        } else {
+            format!("{label:<7}")
+        }
+    }
+}
+
+/// Outcome and duration of a step
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StepReport {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub duration: Duration,
+}
+
+/// Run the steps in order and log a summary table of their outcomes and durations at the end.
+/// The first error stops the run and the remaining steps are reported as skipped, unless keep_going
+/// is true in which case all the steps run and their errors are aggregated into a single one.
+pub(crate) fn run_steps<T: Display>(
+    steps: impl IntoIterator<Item = T>,
+    keep_going: bool,
+    f: impl FnMut(&T) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let (reports, mut errors) = execute_steps(steps, keep_going, f);
+    // the steps do not run anything in explain mode
+    if !is_explain_enabled() {
+        info!("Steps summary:");
+        summary_lines(&reports, process_options().color.is_enabled())
+            .iter()
+            .for_each(|line| info!("  {}", line));
+        record_steps(&reports);
+    }
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        n => Err(anyhow::anyhow!(
+            "{} error(s) occurred:\n  {}",
+            n,
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join("\n  ")
+        )),
+    }
+}
+
+fn execute_steps<T: Display>(
+    steps: impl IntoIterator<Item = T>,
+    keep_going: bool,
+    mut f: impl FnMut(&T) -> anyhow::Result<()>,
+) -> (Vec<StepReport>, Vec<anyhow::Error>) {
+    let mut reports = vec![];
+    let mut errors = vec![];
+    for step in steps {
+        let name = step.to_string();
+        if !errors.is_empty() && (!keep_going || is_interrupted()) {
+            reports.push(StepReport {
+                name,
+                outcome: StepOutcome::Skipped,
+                duration: Duration::ZERO,
+            });
+            continue;
+        }
+        let start = Instant::now();
+        let depth = group_depth();
+        let result = f(&step);
+        restore_group_depth(depth);
+        let outcome = match result {
+            Ok(()) => StepOutcome::Passed,
+            Err(e) => {
+                errors.push(e);
+                StepOutcome::Failed
+            }
+        };
+        reports.push(StepReport {
+            name,
+            outcome,
+            duration: start.elapsed(),
+        });
+    }
+    (reports, errors)
+}
+
+/// Returns the lines of the summary table of the steps, the names are aligned
+fn summary_lines(reports: &[StepReport], colored: bool) -> Vec<String> {
+    let width = reports.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    reports
+        .iter()
+        .map(|report| {
+            let duration = match report.outcome {
+                StepOutcome::Skipped => String::new(),
+                _ => format_duration(&report.duration),
+            };
+            format!(
+                "{:<width$}  {}  {}",
+                report.name,
+                report.outcome.label(colored),
+                duration
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::fail_fast(false, vec![StepOutcome::Passed, StepOutcome::Failed, StepOutcome::Skipped])]
+    #[case::keep_going(true, vec![StepOutcome::Passed, StepOutcome::Failed, StepOutcome::Passed])]
+    fn test_execute_steps(#[case] keep_going: bool, #[case] expected: Vec<StepOutcome>) {
+        let (reports, errors) =
+            execute_steps(
+                ["format", "lint", "typos"],
+                keep_going,
+                |step| match *step {
+                    "lint" => Err(anyhow::anyhow!("Lint failed")),
+                    _ => Ok(()),
+                },
+            );
+        let outcomes: Vec<StepOutcome> = reports.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, expected);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[rstest]
+    fn test_summary_lines() {
+        let reports = [
+            StepReport {
+                name: "format".to_string(),
+                outcome: StepOutcome::Passed,
+                duration: Duration::from_secs(3),
+            },
+            StepReport {
+                name: "lint".to_string(),
+                outcome: StepOutcome::Failed,
+                duration: Duration::from_secs(62),
+            },
+            StepReport {
+                name: "typos".to_string(),
+                outcome: StepOutcome::Skipped,
+                duration: Duration::ZERO,
+            },
+        ];
+        assert_eq!(
+            summary_lines(&reports, false),
+            vec![
+                "format  PASSED   00:00:03",
+                "lint    FAILED   00:01:02",
+                "typos   SKIPPED",
+            ]
+        );
+        assert!(summary_lines(&reports, true)[0].contains("\x1b[32mPASSED \x1b[0m"));
+    }
+}
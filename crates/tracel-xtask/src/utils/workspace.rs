@@ -1,7 +1,14 @@
 use serde_json::Value;
-use std::{path::Path, process::Command};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use crate::utils::process::{process_options, run_query_with_output};
 
-const MEMBER_PATH_PREFIX: &str = if cfg!(target_os = "windows") {
+pub(crate) const MEMBER_PATH_PREFIX: &str = if cfg!(target_os = "windows") {
     "path+file:///"
 } else {
     "path+file://"
@@ -10,6 +17,8 @@ const MEMBER_PATH_PREFIX: &str = if cfg!(target_os = "windows") {
 pub enum WorkspaceMemberType {
     Crate,
     Example,
+    /// Crates which are published, i.e. whose manifest does not set `publish = false`
+    PublishableCrate,
 }
 
 #[derive(Debug)]
@@ -25,19 +34,171 @@ impl WorkspaceMember {
     }
 }
 
-/// Get workspace crates
-pub fn get_workspace_members(w_type: WorkspaceMemberType) -> Vec<WorkspaceMember> {
-    // Run `cargo metadata` command to get project metadata
-    let output = Command::new("cargo")
-        .arg("metadata")
+/// Returns the member containing the passed file, the deepest member wins for nested members
+pub(crate) fn member_containing<'a>(
+    file: &Path,
+    members: &'a [WorkspaceMember],
+) -> Option<&'a WorkspaceMember> {
+    members
+        .iter()
+        .filter(|member| file.starts_with(&member.path))
+        .max_by_key(|member| member.path.len())
+}
+
+static WORKSPACE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+static INVOCATION_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Returns the root directory of the cargo workspace containing the passed directory,
+/// it is the parent directory of the manifest returned by `cargo locate-project --workspace`.
+/// The workspace is located before the process options are set, so the passed cargo program is
+/// run, usually `ProcessOptions::cargo_program`.
+pub fn locate_workspace_root(dir: &Path, cargo: &str) -> anyhow::Result<PathBuf> {
+    let output = Command::new(cargo)
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .current_dir(dir)
         .output()
-        .expect("Failed to execute command");
-    // Parse the JSON output
-    let metadata: Value = serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        .map_err(|e| anyhow::anyhow!("Failed to execute cargo locate-project: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to locate the cargo workspace of {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    manifest
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow::anyhow!("Invalid workspace manifest path: {}", manifest.display()))
+}
+
+/// Set the workspace root and make it the current directory so that the executed commands are run
+/// from it. The previous current directory is kept to resolve the paths passed on the command line
+/// with `invocation_path`. It is done once by `init_xtask`.
+pub fn set_workspace_root(root: &Path) -> anyhow::Result<()> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("Invalid workspace root {}: {}", root.display(), e))?;
+    if !root.join("Cargo.toml").is_file() {
+        return Err(anyhow::anyhow!(
+            "Invalid workspace root {}: no Cargo.toml found",
+            root.display()
+        ));
+    }
+    let invocation_dir = std::env::current_dir()?;
+    std::env::set_current_dir(&root)?;
+    let _ = INVOCATION_DIR.set(invocation_dir);
+    if WORKSPACE_ROOT.set(root).is_err() {
+        warn!("Workspace root has already been set.");
+    }
+    Ok(())
+}
+
+/// Returns the workspace root set by `init_xtask`, defaults to the current directory.
+pub fn workspace_root() -> PathBuf {
+    WORKSPACE_ROOT
+        .get()
+        .cloned()
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+}
+
+/// Returns the passed path resolved from the directory xtask was run from, as the relative paths
+/// passed on the command line would otherwise be resolved from the workspace root.
+pub fn invocation_path(path: &Path) -> PathBuf {
+    INVOCATION_DIR
+        .get()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Returns the target directory of cargo: the `CARGO_TARGET_DIR` variable if set, otherwise the
+/// `target` directory of the workspace root.
+pub fn target_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| workspace_root().join("target"))
+}
+
+/// Get workspace crates, the crates and examples outside of `workspace.default-members` are skipped
+/// unless `--all-members` is passed. The publishable crates are always all returned.
+pub fn get_workspace_members(w_type: WorkspaceMemberType) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let metadata = metadata()?;
+    let non_default = match w_type {
+        WorkspaceMemberType::PublishableCrate => &[][..],
+        _ => &process_options().non_default_members[..],
+    };
+    Ok(members_from_metadata(&metadata, w_type)?
+        .into_iter()
+        .filter(|member| !non_default.contains(&member.name))
+        .collect())
+}
+
+/// Returns the names of the members of the workspace at the passed root which are not part of its
+/// `workspace.default-members`, none if the manifest of the workspace does not declare them.
+/// Like `locate_workspace_root` it runs the passed cargo program as the process options are not set yet.
+pub fn non_default_member_names(root: &Path, cargo: &str) -> anyhow::Result<Vec<String>> {
+    let manifest_path = root.join("Cargo.toml");
+    let manifest: toml::Value = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", manifest_path.display(), e))?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path.display(), e))?;
+    if manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("default-members"))
+        .is_none()
+    {
+        return Ok(vec![]);
+    }
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(root)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to execute cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to get the cargo metadata"));
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the cargo metadata: {}", e))?;
+    Ok(non_default_members_from_metadata(&metadata))
+}
+
+/// Returns the names of the workspace members which are not listed in the resolved default members
+/// of the metadata, available since cargo 1.71, sorted by name.
+fn non_default_members_from_metadata(metadata: &Value) -> Vec<String> {
+    let Some(defaults) = metadata["workspace_default_members"].as_array() else {
+        return vec![];
+    };
+    let defaults: BTreeSet<&str> = defaults.iter().filter_map(Value::as_str).collect();
+    let members: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let mut names: Vec<String> = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|p| {
+            p["id"]
+                .as_str()
+                .is_some_and(|id| members.contains(id) && !defaults.contains(id))
+        })
+        .filter_map(|p| p["name"].as_str().map(String::from))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Returns the workspace members of the passed type sorted by name, so that the commands run over
+/// the members in the same order on every machine.
+fn members_from_metadata(
+    metadata: &Value,
+    w_type: WorkspaceMemberType,
+) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let publishable = publishable_member_names(metadata);
     // Extract workspace members from the metadata
-    let workspaces = metadata["workspace_members"]
+    let mut members: Vec<WorkspaceMember> = metadata["workspace_members"]
         .as_array()
-        .expect("Expected an array of workspace members")
+        .ok_or_else(|| anyhow::anyhow!("The cargo metadata has no array of workspace members"))?
         .iter()
         .filter_map(|member| {
             let member_str = member.as_str()?;
@@ -54,12 +215,39 @@ pub fn get_workspace_members(w_type: WorkspaceMemberType) -> Vec<WorkspaceMember
                 WorkspaceMemberType::Example if path.contains("examples/") => {
                     Some(WorkspaceMember::new(name.to_string(), path.to_string()))
                 }
+                WorkspaceMemberType::PublishableCrate
+                    if !path.contains("examples/") && publishable.contains(&name) =>
+                {
+                    Some(WorkspaceMember::new(name.to_string(), path.to_string()))
+                }
                 _ => None,
             }
         })
         .collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(members)
+}
 
-    workspaces
+/// Returns the names of the workspace members which can be published.
+/// The `publish` field of the metadata is null when unset or true, a list of registries when restricted
+/// and an empty list for `publish = false`.
+fn publishable_member_names(metadata: &Value) -> BTreeSet<String> {
+    let ids: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|p| p["id"].as_str().is_some_and(|id| ids.contains(id)))
+        .filter(|p| {
+            p["publish"]
+                .as_array()
+                .map_or(true, |registries| !registries.is_empty())
+        })
+        .filter_map(|p| p["name"].as_str().map(String::from))
+        .collect()
 }
 
 /// Legacy cargo metadata format for member specs (rust < 1.77)
@@ -88,3 +276,397 @@ fn parse_workspace_member1(specs: &str) -> Option<(String, String)> {
     let path = path.to_str()?;
     Some((name.to_string(), path.to_string()))
 }
+
+/// Returns the output of `cargo metadata` as JSON
+pub fn metadata() -> anyhow::Result<Value> {
+    let output = run_query_with_output("cargo", &["metadata", "--format-version", "1"], None)?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to get the cargo metadata"));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the cargo metadata: {}", e))
+}
+
+/// A workspace member with the names of the workspace members it depends on
+struct MemberNode {
+    name: String,
+    path: PathBuf,
+    dependencies: Vec<String>,
+}
+
+fn member_nodes(metadata: &Value) -> Vec<MemberNode> {
+    let ids: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let packages: Vec<&Value> = metadata["packages"]
+        .as_array()
+        .map(|packages| {
+            packages
+                .iter()
+                .filter(|p| p["id"].as_str().is_some_and(|id| ids.contains(id)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let names: BTreeSet<&str> = packages.iter().filter_map(|p| p["name"].as_str()).collect();
+    packages
+        .iter()
+        .filter_map(|package| {
+            let path = Path::new(package["manifest_path"].as_str()?).parent()?;
+            let dependencies = package["dependencies"]
+                .as_array()
+                .map(|deps| {
+                    deps.iter()
+                        .filter(|d| d["path"].is_string())
+                        .filter_map(|d| d["name"].as_str())
+                        .filter(|name| names.contains(name))
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(MemberNode {
+                name: package["name"].as_str()?.to_string(),
+                path: path.to_path_buf(),
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+/// Returns the names of the workspace members depending directly on the passed member
+pub(crate) fn dependent_member_names(metadata: &Value, name: &str) -> Vec<String> {
+    member_nodes(metadata)
+        .into_iter()
+        .filter(|node| node.dependencies.iter().any(|dep| dep == name))
+        .map(|node| node.name)
+        .collect()
+}
+
+/// Returns the passed members along with all the members depending on them, directly or not
+fn reverse_dependencies_closure(nodes: &[MemberNode], changed: &[String]) -> BTreeSet<String> {
+    let mut affected: BTreeSet<String> = changed.iter().cloned().collect();
+    let mut queue: Vec<String> = changed.to_vec();
+    while let Some(name) = queue.pop() {
+        for node in nodes {
+            if node.dependencies.contains(&name) && affected.insert(node.name.clone()) {
+                queue.push(node.name.clone());
+            }
+        }
+    }
+    affected
+}
+
+/// Returns the passed workspace members along with all the members depending on them,
+/// directly or transitively, according to the intra-workspace dependency graph.
+pub fn affected_members(changed: &[String]) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let metadata = metadata()?;
+    Ok(affected_members_from_metadata(&metadata, changed))
+}
+
+fn affected_members_from_metadata(metadata: &Value, changed: &[String]) -> Vec<WorkspaceMember> {
+    let nodes = member_nodes(metadata);
+    let affected = reverse_dependencies_closure(&nodes, changed);
+    nodes
+        .into_iter()
+        .filter(|node| affected.contains(&node.name))
+        .map(|node| WorkspaceMember::new(node.name, node.path.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// Returns the names of the members containing the passed files.
+/// A file belongs to the member with the deepest path containing it.
+fn members_containing_files(nodes: &[MemberNode], files: &[PathBuf]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|file| {
+            nodes
+                .iter()
+                .filter(|node| file.starts_with(&node.path))
+                .max_by_key(|node| node.path.components().count())
+                .map(|node| node.name.clone())
+        })
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// Returns the names of the workspace members containing the passed files
+pub fn member_names_containing_files(files: &[PathBuf]) -> anyhow::Result<Vec<String>> {
+    Ok(members_containing_files(&member_nodes(&metadata()?), files))
+}
+
+/// Returns the workspace members containing the passed files along with their reverse dependencies
+pub fn members_affected_by_files(files: &[PathBuf]) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let metadata = metadata()?;
+    let changed = members_containing_files(&member_nodes(&metadata), files);
+    Ok(affected_members_from_metadata(&metadata, &changed))
+}
+
+/// Returns the workspace member owning the target of the passed kind and name,
+/// for instance the package containing the binary `server` for kind `bin` and name `server`.
+pub fn find_member_target(kind: &str, name: &str) -> anyhow::Result<WorkspaceMember> {
+    find_member_target_from_metadata(&metadata()?, kind, name)
+}
+
+fn find_member_target_from_metadata(
+    metadata: &Value,
+    kind: &str,
+    name: &str,
+) -> anyhow::Result<WorkspaceMember> {
+    let ids: BTreeSet<&str> = metadata["workspace_members"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let mut available = vec![];
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        if !package["id"].as_str().is_some_and(|id| ids.contains(id)) {
+            continue;
+        }
+        for target in package["targets"].as_array().into_iter().flatten() {
+            let is_kind = target["kind"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|k| k == kind));
+            if !is_kind {
+                continue;
+            }
+            let Some(target_name) = target["name"].as_str() else {
+                continue;
+            };
+            if target_name == name {
+                let path = package["manifest_path"]
+                    .as_str()
+                    .and_then(|p| Path::new(p).parent())
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let package_name = package["name"].as_str().unwrap_or_default().to_string();
+                return Ok(WorkspaceMember::new(package_name, path));
+            }
+            available.push(target_name.to_string());
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No {} target named '{}' in the workspace. Available {} targets: {}",
+        kind,
+        name,
+        kind,
+        available.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn write_package(dir: &Path, name: &str) {
+        std::fs::create_dir_all(dir.join(name).join("src")).unwrap();
+        std::fs::write(
+            dir.join(name).join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )
+        .unwrap();
+        std::fs::write(dir.join(name).join("src/lib.rs"), "").unwrap();
+    }
+
+    #[rstest]
+    #[case::default_members(Some(r#"["a"]"#), vec!["b", "c"])]
+    #[case::no_default_members(None, vec![])]
+    fn test_non_default_member_names(
+        #[case] default_members: Option<&str>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let dir = std::env::temp_dir().join(format!(
+            "xtask-default-members-{}-{}",
+            std::process::id(),
+            default_members.is_some()
+        ));
+        ["a", "b", "c"]
+            .iter()
+            .for_each(|name| write_package(&dir, name));
+        let mut manifest =
+            "[workspace]\nresolver = \"2\"\nmembers = [\"a\", \"b\", \"c\"]\n".to_string();
+        if let Some(default_members) = default_members {
+            manifest.push_str(&format!("default-members = {default_members}\n"));
+        }
+        std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        let names = non_default_member_names(&dir, "cargo");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names.unwrap(), expected);
+    }
+
+    /// Diamond-shaped workspace where `b` and `c` depend on `a`, and `d` depends on `b` and `c`.
+    /// `a` also depends on the `serde` registry crate.
+    fn diamond_metadata() -> Value {
+        let package = |name: &str, path: &str, deps: &[&str]| {
+            let mut dependencies: Vec<Value> = deps
+                .iter()
+                .map(|dep| serde_json::json!({"name": dep, "path": format!("/ws/crates/{dep}")}))
+                .collect();
+            dependencies.push(serde_json::json!({"name": "serde", "source": "registry+https://github.com/rust-lang/crates.io-index"}));
+            serde_json::json!({
+                "name": name,
+                "id": format!("path+file://{path}#0.1.0"),
+                "manifest_path": format!("{path}/Cargo.toml"),
+                "dependencies": dependencies,
+            })
+        };
+        serde_json::json!({
+            "packages": [
+                package("a", "/ws/crates/a", &[]),
+                package("b", "/ws/crates/b", &["a"]),
+                package("c", "/ws/crates/c", &["a"]),
+                package("d", "/ws/crates/d", &["b", "c"]),
+                package("e", "/ws/crates/d/e", &[]),
+                {
+                    "name": "serde",
+                    "id": "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.200",
+                    "manifest_path": "/registry/serde/Cargo.toml",
+                    "dependencies": [],
+                },
+            ],
+            "workspace_members": [
+                "path+file:///ws/crates/a#0.1.0",
+                "path+file:///ws/crates/b#0.1.0",
+                "path+file:///ws/crates/c#0.1.0",
+                "path+file:///ws/crates/d#0.1.0",
+                "path+file:///ws/crates/d/e#0.1.0",
+            ],
+        })
+    }
+
+    #[rstest]
+    #[case::root(vec!["a"], vec!["a", "b", "c", "d"])]
+    #[case::side(vec!["b"], vec!["b", "d"])]
+    #[case::both_sides(vec!["b", "c"], vec!["b", "c", "d"])]
+    #[case::leaf(vec!["d"], vec!["d"])]
+    #[case::isolated(vec!["e"], vec!["e"])]
+    #[case::unknown(vec!["serde"], vec![])]
+    #[case::nothing(vec![], vec![])]
+    fn test_affected_members(#[case] changed: Vec<&str>, #[case] expected: Vec<&str>) {
+        let changed: Vec<String> = changed.into_iter().map(String::from).collect();
+        let affected: Vec<String> = affected_members_from_metadata(&diamond_metadata(), &changed)
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(affected, expected);
+    }
+
+    #[rstest]
+    #[case::root("a", vec!["b", "c"])]
+    #[case::side("b", vec!["d"])]
+    #[case::leaf("d", vec![])]
+    fn test_dependent_member_names(#[case] name: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(dependent_member_names(&diamond_metadata(), name), expected);
+    }
+
+    #[rstest]
+    #[case::bin("bin", "server", Some("app"))]
+    #[case::example("example", "demo", Some("lib"))]
+    #[case::wrong_kind("example", "server", None)]
+    #[case::dependency("bin", "serde_tool", None)]
+    fn test_find_member_target(
+        #[case] kind: &str,
+        #[case] name: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let metadata = serde_json::json!({
+            "packages": [
+                {
+                    "name": "app",
+                    "id": "path+file:///ws/crates/app#0.1.0",
+                    "manifest_path": "/ws/crates/app/Cargo.toml",
+                    "targets": [{"name": "server", "kind": ["bin"]}],
+                },
+                {
+                    "name": "lib",
+                    "id": "path+file:///ws/crates/lib#0.1.0",
+                    "manifest_path": "/ws/crates/lib/Cargo.toml",
+                    "targets": [{"name": "lib", "kind": ["lib"]}, {"name": "demo", "kind": ["example"]}],
+                },
+                {
+                    "name": "serde",
+                    "id": "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.200",
+                    "manifest_path": "/registry/serde/Cargo.toml",
+                    "targets": [{"name": "serde_tool", "kind": ["bin"]}],
+                },
+            ],
+            "workspace_members": [
+                "path+file:///ws/crates/app#0.1.0",
+                "path+file:///ws/crates/lib#0.1.0",
+            ],
+        });
+        let member = find_member_target_from_metadata(&metadata, kind, name).ok();
+        assert_eq!(member.map(|m| m.name).as_deref(), expected);
+    }
+
+    #[rstest]
+    fn test_members_are_sorted_by_name() {
+        let metadata = serde_json::json!({
+            "packages": [],
+            "workspace_members": [
+                "path+file:///ws/crates/zeta#0.1.0",
+                "path+file:///ws/crates/alpha#0.1.0",
+                "path+file:///ws/examples/demo#0.1.0",
+                "path+file:///ws/crates/mid#0.1.0",
+            ],
+        });
+        let names: Vec<String> = members_from_metadata(&metadata, WorkspaceMemberType::Crate)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(names, vec!["alpha", "mid", "zeta"]);
+    }
+
+    #[rstest]
+    fn test_locate_workspace_root_from_nested_directory() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let root = locate_workspace_root(&manifest_dir.join("src/utils"), "cargo").unwrap();
+        assert_eq!(root, manifest_dir.parent().unwrap().parent().unwrap());
+    }
+
+    #[rstest]
+    fn test_publishable_member_names() {
+        let package = |name: &str, publish: Value| {
+            serde_json::json!({
+                "name": name,
+                "id": format!("path+file:///ws/crates/{name}#0.1.0"),
+                "publish": publish,
+            })
+        };
+        let metadata = serde_json::json!({
+            "packages": [
+                package("unset", Value::Null),
+                package("private", serde_json::json!([])),
+                package("registry", serde_json::json!(["my-registry"])),
+                {
+                    "name": "serde",
+                    "id": "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.200",
+                    "publish": null,
+                },
+            ],
+            "workspace_members": [
+                "path+file:///ws/crates/unset#0.1.0",
+                "path+file:///ws/crates/private#0.1.0",
+                "path+file:///ws/crates/registry#0.1.0",
+            ],
+        });
+        assert_eq!(
+            publishable_member_names(&metadata),
+            BTreeSet::from(["registry".to_string(), "unset".to_string()])
+        );
+    }
+
+    #[rstest]
+    fn test_members_containing_files() {
+        let nodes = member_nodes(&diamond_metadata());
+        let files = [
+            "/ws/crates/b/src/lib.rs",
+            "/ws/crates/b/Cargo.toml",
+            "/ws/crates/d/e/src/main.rs",
+            "/ws/README.md",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(members_containing_files(&nodes, &files), vec!["b", "e"]);
+    }
+}
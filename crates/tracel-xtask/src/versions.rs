@@ -1,2 +1,12 @@
+pub(crate) const CARGO_BLOAT_VERSION: &str = "0.12.1";
+pub(crate) const CARGO_DEADLINKS_VERSION: &str = "0.8.1";
+pub(crate) const CARGO_EXPAND_VERSION: &str = "1.0.127";
+pub(crate) const CARGO_HACK_VERSION: &str = "0.6.45";
+pub(crate) const CARGO_LLVM_COV_VERSION: &str = "0.6.16";
+pub(crate) const CARGO_MACHETE_VERSION: &str = "0.9.1";
+pub(crate) const CARGO_UDEPS_VERSION: &str = "0.1.57";
+pub(crate) const CROSS_VERSION: &str = "0.2.5";
+pub(crate) const FLAMEGRAPH_VERSION: &str = "0.6.14";
 pub(crate) const GRCOV_VERSION: &str = "0.8.19";
 pub(crate) const TYPOS_VERSION: &str = "1.24.1";
+pub(crate) const WASM_PACK_VERSION: &str = "0.13.1";
@@ -0,0 +1,8 @@
+use rstest::rstest;
+
+#[rstest]
+fn test_macros_expansion() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,31 @@
+use std::time::{Duration, Instant};
+
+use rstest::rstest;
+use tracel_xtask::utils::process::{
+    run_process_for_workspace, set_process_options, ProcessOptions,
+};
+
+const TIMEOUT_ERROR: &str = "Process killed because it exceeded the timeout of 1 seconds.";
+
+// the process options are global so the timeout is tested in its own test binary
+fn set_timeout() {
+    set_process_options(ProcessOptions {
+        timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    });
+}
+
+#[rstest]
+#[case::child("sleep", &["30"])]
+// the grandchild keeps the output pipes open if only the shell is killed
+#[case::grandchild("sh", &["-c", "sleep 30; echo done"])]
+#[cfg(unix)]
+fn test_workspace_run_timeout(#[case] name: &str, #[case] args: &[&str]) {
+    set_timeout();
+    let start = Instant::now();
+    let error =
+        run_process_for_workspace(name, args, &[], &[], None, None, "Sleep failed", None, None)
+            .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(20));
+    assert_eq!(error.to_string(), TIMEOUT_ERROR);
+}
@@ -0,0 +1,6 @@
+use tracel_xtask::prelude::*;
+
+#[macros::base_commands(Build, Deploy)]
+pub enum Command {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use tracel_xtask::prelude::*;
+
+#[macros::declare_command_args(Target)]
+pub struct DeployCmdArgs {}
+
+fn main() {}
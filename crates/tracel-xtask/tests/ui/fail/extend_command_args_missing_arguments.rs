@@ -0,0 +1,6 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_command_args(BuildCmdArgs, Target)]
+pub struct ExtendedBuildCmdArgs {}
+
+fn main() {}
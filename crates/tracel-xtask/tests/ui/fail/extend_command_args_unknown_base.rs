@@ -0,0 +1,6 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_command_args(UnknownCmdArgs, Target, None)]
+pub struct ExtendedCmdArgs {}
+
+fn main() {}
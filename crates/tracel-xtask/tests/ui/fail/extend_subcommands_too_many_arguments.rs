@@ -0,0 +1,6 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_subcommands(CheckSubCommand, Target)]
+pub enum ExtendedCheckSubCommand {}
+
+fn main() {}
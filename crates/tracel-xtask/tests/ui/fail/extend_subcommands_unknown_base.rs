@@ -0,0 +1,8 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_subcommands(DeploySubCommand)]
+pub enum ExtendedDeploySubCommand {
+    Staging,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_targets]
+pub enum MyTarget {
+    #[target(crates = "web")]
+    Frontend,
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_command_args(BuildCmdArgs, Target, None)]
+pub struct ExtendedBuildCmdArgs {
+    /// Custom field which only exists on the extended struct
+    #[arg(long)]
+    pub debug: bool,
+}
+
+#[derive(clap::Parser)]
+struct Cli {
+    #[command(flatten)]
+    args: ExtendedBuildCmdArgs,
+}
+
+fn main() {
+    let cli = <Cli as clap::Parser>::parse_from([
+        "xtask",
+        "--debug",
+        "--release",
+        "--exclude",
+        "foo",
+        "--changed-since",
+        "origin/main",
+    ]);
+    assert!(cli.args.debug);
+    let args: BuildCmdArgs = cli.args.try_into().unwrap();
+    assert!(args.release);
+    assert_eq!(args.exclude, ["foo"]);
+    assert_eq!(args.changed_since.as_deref(), Some("origin/main"));
+}
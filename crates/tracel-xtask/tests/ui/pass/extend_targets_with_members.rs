@@ -0,0 +1,40 @@
+use tracel_xtask::prelude::*;
+
+#[macros::extend_targets]
+pub enum MyTarget {
+    /// Target the frontend crates.
+    #[target(members = "web, ui")]
+    Frontend,
+    /// Target without members.
+    Docs,
+}
+
+#[macros::extend_command_args(BuildCmdArgs, MyTarget, None)]
+pub struct ExtendedBuildCmdArgs {}
+
+#[derive(clap::Parser)]
+struct Cli {
+    #[command(flatten)]
+    args: ExtendedBuildCmdArgs,
+}
+
+fn parse(args: &[&str]) -> ExtendedBuildCmdArgs {
+    <Cli as clap::Parser>::parse_from(["xtask"].iter().chain(args)).args
+}
+
+fn main() {
+    assert_eq!(MyTarget::Frontend.members(), ["web", "ui"]);
+    assert!(MyTarget::Docs.members().is_empty());
+
+    let args: BuildCmdArgs = parse(&["--target", "frontend"]).try_into().unwrap();
+    assert!(args.target == Target::AllPackages);
+    assert_eq!(args.only, ["web", "ui"]);
+
+    let args: BuildCmdArgs = parse(&["--target", "frontend", "--only", "ui"])
+        .try_into()
+        .unwrap();
+    assert_eq!(args.only, ["ui"]);
+
+    let result: anyhow::Result<BuildCmdArgs> = parse(&["--target", "docs"]).try_into();
+    assert!(result.is_err());
+}
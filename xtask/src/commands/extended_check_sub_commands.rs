@@ -20,12 +20,30 @@ pub fn handle_command(args: ExtendedCheckArgsCmdArgs) -> anyhow::Result<()> {
             ExtendedCheckSubcommand::iter()
                 .filter(|c| *c != ExtendedCheckSubcommand::All)
                 .try_for_each(|c| {
+                    let hack = args.hack && c == ExtendedCheckSubcommand::Lint;
                     handle_command(ExtendedCheckArgsCmdArgs {
                         command: Some(c),
                         target: args.target.clone(),
                         exclude: args.exclude.clone(),
                         only: args.only.clone(),
+                        changed_only: args.changed_only,
+                        changed_since: args.changed_since.clone(),
+                        exclude_from: args.exclude_from.clone(),
+                        only_from: args.only_from.clone(),
                         ignore_audit: args.ignore_audit,
+                        deny_warnings: args.deny_warnings,
+                        ignored_advisories: args.ignored_advisories.clone(),
+                        hack,
+                        nightly_fmt: args.nightly_fmt,
+                        keep_going: args.keep_going,
+                        summary: args.summary,
+                        verbose: args.verbose,
+                        sarif: args.sarif.clone(),
+                        typos_config: args.typos_config.clone(),
+                        exclude_paths: args.exclude_paths.clone(),
+                        deny_unused_features: args.deny_unused_features,
+                        diff: args.diff.clone(),
+                        all_lines: args.all_lines,
                     })
                 })
         }
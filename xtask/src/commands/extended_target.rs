@@ -15,6 +15,7 @@ pub fn handle_command(args: ExtendedTargetCmdArgs) -> anyhow::Result<()> {
         MyTarget::AllPackages => println!("You chose the target: all-packages"),
         MyTarget::Crates => println!("You chose the target: crates"),
         MyTarget::Examples => println!("You chose the target: examples"),
+        MyTarget::PublishableCrates => println!("You chose the target: publishable-crates"),
         MyTarget::Workspace => println!("You chose the target: workspace"),
 
         // Additional target
@@ -50,6 +50,14 @@ pub fn handle_command(args: ExtendedFixCmdArgs, mut answer: Option<bool>) -> any
                             target: args.target.clone(),
                             exclude: args.exclude.clone(),
                             only: args.only.clone(),
+                            changed_only: args.changed_only,
+                            changed_since: args.changed_since.clone(),
+                            exclude_from: args.exclude_from.clone(),
+                            only_from: args.only_from.clone(),
+                            nightly_fmt: args.nightly_fmt,
+                            typos_config: args.typos_config.clone(),
+                            exclude_paths: args.exclude_paths.clone(),
+                            staged: args.staged,
                         },
                         Some(answer),
                     )
@@ -83,6 +91,9 @@ fn run_new_subcommand_fix(
             FixTarget::AllPackages => println!("Executing new subcommand on all packages."),
             FixTarget::Crates => println!("Executing new subcommand on all crates."),
             FixTarget::Examples => println!("Executing new subcommand on all examples."),
+            FixTarget::PublishableCrates => {
+                println!("Executing new subcommand on all publishable crates.")
+            }
             FixTarget::Workspace => println!("Executing new subcommand on workspace."),
             FixTarget::CI => println!("Executing new subcommand on CI."),
         }
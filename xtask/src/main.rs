@@ -2,19 +2,25 @@ mod commands;
 
 use tracel_xtask::prelude::*;
 
-#[macro_use]
-extern crate log;
-
 #[macros::base_commands(
+    Bench,
     Bump,
     Build,
     Check,
     Compile,
+    Completions,
     Coverage,
     Doc,
     Dependencies,
+    Expand,
     Fix,
+    Generate,
+    Graph,
+    ListTargets,
+    Man,
+    Profile,
     Publish,
+    Size,
     Test,
     Validate,
     Vulnerabilities
@@ -43,24 +49,34 @@ pub enum Command {
 }
 
 fn main() -> anyhow::Result<()> {
-    let args = init_xtask::<Command>()?;
-    match args.command {
-        Command::ExtendedBuildArgs(args) => commands::extended_build_args::handle_command(args),
-        Command::ExtendedBuildNewSubCommands(args) => {
-            commands::extended_build_new_sub_commands::handle_command(args)
-        }
-        Command::ExtendedCheckSubCommands(args) => {
-            commands::extended_check_sub_commands::handle_command(args)
-        }
-        Command::ExtendedTestArgs(args) => commands::extended_test_args::handle_command(args),
-        Command::ExtendedFix(args) => commands::fix::handle_command(args, None),
-        Command::ExtendedTarget(args) => commands::extended_target::handle_command(args),
-        Command::MyCommand(args) => commands::my_command::handle_command(args),
-        Command::MyCommandWithSubCommand(args) => {
-            commands::my_command_with_sub_commands::handle_command(args)
-        }
-        // dispatch_base_commands function is generated by the commands macro
-        _ => dispatch_base_commands(args),
-    }?;
-    Ok(())
+    XtaskRunner::<Command>::new()
+        .command(
+            // Example of a one-off command registered with the runner, without a variant of the Command enum
+            clap::Command::new("greet")
+                .about("Example of a custom command registered with the runner.")
+                .arg(clap::Arg::new("name").long("name").default_value("xtask")),
+            |args| {
+                let name = args.command.matches.get_one::<String>("name").unwrap();
+                println!("Hello {name} from greet");
+                Ok(())
+            },
+        )
+        .run(|args| match args.command {
+            Command::ExtendedBuildArgs(args) => commands::extended_build_args::handle_command(args),
+            Command::ExtendedBuildNewSubCommands(args) => {
+                commands::extended_build_new_sub_commands::handle_command(args)
+            }
+            Command::ExtendedCheckSubCommands(args) => {
+                commands::extended_check_sub_commands::handle_command(args)
+            }
+            Command::ExtendedTestArgs(args) => commands::extended_test_args::handle_command(args),
+            Command::ExtendedFix(args) => commands::fix::handle_command(args, None),
+            Command::ExtendedTarget(args) => commands::extended_target::handle_command(args),
+            Command::MyCommand(args) => commands::my_command::handle_command(args),
+            Command::MyCommandWithSubCommand(args) => {
+                commands::my_command_with_sub_commands::handle_command(args)
+            }
+            // dispatch_base_commands function is generated by the commands macro
+            _ => dispatch_base_commands(args),
+        })
 }
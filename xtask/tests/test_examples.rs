@@ -1,9 +1,13 @@
-use std::process::Command;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use rstest::rstest;
 
 #[rstest]
 #[case::create_custom_command(&["my-command"], "Hello from my-command")]
+#[case::register_custom_command_with_the_runner(&["greet", "--name", "ferris"], "Hello ferris from greet")]
 #[case::create_custom_command_with_sub_commands_default_variant(&["my-command-with-sub-command"], "Execute Command 1 (default)")]
 #[case::create_custom_command_with_sub_commands_variant_1(&["my-command-with-sub-command", "command1"], "Execute Command 1 (default)")]
 #[case::create_custom_command_with_sub_commands_variant_2(&["my-command-with-sub-command", "command2"], "Execute Command 2")]
@@ -11,6 +15,7 @@ use rstest::rstest;
 #[case::create_custom_command_with_extended_target_all_packages(&["extended-target", "--target", "all-packages"], "You chose the target: all-packages")]
 #[case::create_custom_command_with_extended_target_crates(&["extended-target", "--target", "crates"], "You chose the target: crates")]
 #[case::create_custom_command_with_extended_target_examples(&["extended-target", "--target", "examples"], "You chose the target: examples")]
+#[case::create_custom_command_with_extended_target_publishable_crates(&["extended-target", "--target", "publishable-crates"], "You chose the target: publishable-crates")]
 #[case::create_custom_command_with_extended_target_workspace(&["extended-target", "--target", "workspace"], "You chose the target: workspace")]
 #[case::create_custom_command_with_extended_target_new_frontend_variant(&["extended-target", "--target", "frontend"], "You chose the target: frontend")]
 #[case::extend_base_command_with_additional_command_args_debug_1(&["extended-build-args"], "debug disabled")]
@@ -43,3 +48,53 @@ fn test_xtask_example_status_success_and_returns_expected_output(
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains(&expected_output));
 }
+
+#[rstest]
+#[case::nested_subdirectory(Path::new(env!("CARGO_MANIFEST_DIR")).join("../crates/tracel-xtask/src"), &[])]
+#[case::workspace_root_override(std::env::temp_dir(), &["--workspace-root", concat!(env!("CARGO_MANIFEST_DIR"), "/..")])]
+fn test_xtask_resolves_the_workspace_root(
+    #[case] current_dir: PathBuf,
+    #[case] xtask_args: &[&str],
+) {
+    let mut args = xtask_args.to_vec();
+    args.extend(["list-targets", "--format", "json"]);
+    let output = Command::new(env!("CARGO_BIN_EXE_xtask"))
+        .args(args)
+        .current_dir(current_dir)
+        .output()
+        .expect("xtask process should start");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"name\":\"tracel-xtask\""));
+}
+
+// the hook commands are run with sh
+#[cfg(unix)]
+#[rstest]
+fn test_xtask_runs_the_configured_hooks() {
+    let workspace = std::env::temp_dir().join(format!("xtask-hooks-{}", std::process::id()));
+    std::fs::create_dir_all(workspace.join("src")).expect("workspace should be created");
+    std::fs::write(
+        workspace.join("Cargo.toml"),
+        "[package]\nname = \"hooked\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .expect("manifest should be written");
+    std::fs::write(workspace.join("src/lib.rs"), "").expect("library should be written");
+    std::fs::write(
+        workspace.join("xtask.toml"),
+        "[hooks.list-targets]\npre = \"echo pre > hooks.log\"\npost = \"echo post >> hooks.log\"\n",
+    )
+    .expect("config should be written");
+    let output = Command::new(env!("CARGO_BIN_EXE_xtask"))
+        .args([
+            "--workspace-root",
+            workspace.to_str().unwrap(),
+            "list-targets",
+        ])
+        .output()
+        .expect("xtask process should start");
+    let log = std::fs::read_to_string(workspace.join("hooks.log"));
+    std::fs::remove_dir_all(&workspace).expect("workspace should be removed");
+    assert!(output.status.success());
+    assert_eq!(log.expect("hooks should write the log"), "pre\npost\n");
+}